@@ -1,4 +1,4 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{path::PathBuf, str::FromStr, time::Duration};
 
 use clap::Parser;
 use config::Config;
@@ -74,6 +74,33 @@ struct Settings {
     db_path: PathBuf,
 }
 
+/// Reads the time of the last request of a client, if any
+///
+/// Earlier versions of the verifier saved the seconds of the timestamp encoded
+/// with bincode; such entries are rewritten with [Timestamp::to_key_bytes].
+/// Entries which cannot be decoded at all are ignored, so the client has to
+/// prove again that it is whitelisted.
+fn last_request(db: &Db, client: &Address) -> Option<Timestamp> {
+    let saved = db.get(client.as_slice()).ok()??;
+    if let Ok(timestamp) = Timestamp::from_key_bytes(&saved) {
+        return Some(timestamp);
+    }
+    match bincode::decode_from_slice::<u64, _>(&saved, bincode::config::standard()) {
+        Ok((seconds, read)) if read == saved.len() => {
+            let timestamp = Timestamp::from_seconds(seconds);
+            let encoded = timestamp.to_key_bytes();
+            if db.insert(client.as_slice(), IVec::from(&encoded)).is_err() {
+                eprintln!("Error rewriting the saved client timestamp");
+            }
+            Some(timestamp)
+        }
+        _ => {
+            eprintln!("Cannot decode saved timestamp; ignoring it");
+            None
+        }
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> RpcWrapperResult<()> {
     let args = Args::parse();
@@ -122,10 +149,18 @@ pub async fn main() -> RpcWrapperResult<()> {
             }
         };
         // check that the signature matches the message and the sender is the signer
-        let Some(request) = SubmittedClaim::check_and_strip_signature(request.data) else {
-            eprintln!("Failed to validate request");
-            continue;
+        let request = match SubmittedClaim::check_and_strip_signature(request.data) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Failed to validate request: {}", e);
+                continue;
+            }
         };
+        // Check that the claim can still be settled
+        if account.clock().is_expired(&request.expires) {
+            eprintln!("Claim has already expired");
+            continue;
+        }
         // Check that there is exactly one verifier
         if request.to.len() != 1 {
             eprintln!("Expected a single verifier");
@@ -153,20 +188,16 @@ pub async fn main() -> RpcWrapperResult<()> {
             eprintln!("Amount requested larger than maximum allowed amount");
             continue;
         }
-        match db.get(client.as_slice()) {
-            Ok(Some(old_seconds)) => {
+        match last_request(&db, &client) {
+            Some(old_timestamp) => {
                 // check that timestamp is ok
-                let Ok((old_seconds, _)) =
-                    bincode::decode_from_slice::<u64, _>(&old_seconds, bincode::config::standard())
-                else {
-                    panic!("Cannot decode saved timestamp");
-                };
-                if old_seconds + settings.min_waiting_time > Timestamp::now().seconds() {
+                let next_allowed = old_timestamp + Duration::from_secs(settings.min_waiting_time);
+                if next_allowed > account.clock().now() {
                     eprintln!("Request came too early. ignoring");
                     continue;
                 }
             }
-            _ => {
+            None => {
                 // assume there is no entry in the db; check proof
                 let Ok(claim_id) = B256::from_str(&request.proof) else {
                     eprintln!("Cannot parse proof into a claim id");
@@ -220,13 +251,8 @@ pub async fn main() -> RpcWrapperResult<()> {
         // Checks have passed; Claim has been verified
 
         // record new timestamp
-        let Ok(encoded) =
-            bincode::encode_to_vec(Timestamp::now().seconds(), bincode::config::standard())
-        else {
-            eprintln!("Could not encode timestamp seconds to vector");
-            continue;
-        };
-        let Ok(_) = db.insert(client.as_slice(), IVec::from(encoded)) else {
+        let encoded = account.clock().now().to_key_bytes();
+        let Ok(_) = db.insert(client.as_slice(), IVec::from(&encoded)) else {
             eprintln!("Error persisting the new client timestamp");
             continue;
         };
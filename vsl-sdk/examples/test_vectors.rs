@@ -0,0 +1,51 @@
+//! Prints the signing test vectors (see `vsl_sdk::test_vectors`), or checks a signed
+//! message produced by another implementation against one of them:
+//!
+//! ```sh
+//! cargo run --example test-vectors > docs/signing_test_vectors.json
+//! cargo run --example test-vectors -- verify pay signed_pay.json
+//! ```
+use std::process::ExitCode;
+
+use vsl_sdk::test_vectors;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let vectors = test_vectors::generate();
+    match args.as_slice() {
+        [] => {
+            println!("{}", serde_json::to_string_pretty(&vectors).unwrap());
+            ExitCode::SUCCESS
+        }
+        [command, name, path] if command == "verify" => {
+            let Some(vector) = vectors.get(name) else {
+                eprintln!("Unknown test vector {}", name);
+                return ExitCode::FAILURE;
+            };
+            let signed = match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(signed) => signed,
+                Err(e) => {
+                    eprintln!("Cannot read {}: {}", path, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            match vector.verify_signed(&signed) {
+                Ok(()) => {
+                    println!("{}: ok", name);
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", name, e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => {
+            eprintln!("Usage: test-vectors [verify NAME SIGNED_MESSAGE_FILE]");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,602 @@
+//! # Amount Module
+//!
+//! This module provides the `Amount` struct, a quantity of (sub-units of) VSL
+//! tokens or of some asset. It offers checked and saturating arithmetic,
+//! percentage math, and exact proportional splitting of an amount among several
+//! recipients.
+//!
+//! Nothing in this module panics. The operators (`+`, `-`, `*`, `/`, `%`,
+//! [Sum]) and the `from_*` constructors saturate at [Amount::ZERO] and
+//! [Amount::MAX]; dividing by zero gives [Amount::MAX] and a remainder of the
+//! dividend. Use the `checked_*` methods to detect these cases instead.
+//!
+use std::fmt::Display;
+use std::iter::Sum;
+use std::num::ParseIntError;
+use std::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+
+use alloy::primitives::U256;
+use derive_more::derive;
+use serde::{Deserialize, Serialize};
+
+#[repr(transparent)]
+#[derive(
+    Clone,
+    Copy,
+    derive::From,
+    derive::Into,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    derive::Display,
+    derive::FromStr,
+    derive::LowerHex,
+    derive::UpperHex,
+    Serialize,
+    Deserialize,
+)]
+pub struct Amount(u128);
+
+const ONE_VSL_TOKEN: u128 = 1_000_000_000_000_000_000;
+
+/// Number of decimals of the native VSL token
+pub const VSL_DECIMALS: u8 = 18;
+
+#[derive(Debug)]
+pub enum ParseAmountError {
+    NotHex,
+    LeadingZeros,
+    ParseInt(ParseIntError),
+    NotDecimal,
+    TooManyDecimals,
+    /// No digits were given
+    Empty,
+    /// Amounts cannot be negative
+    Negative,
+    /// A character which is neither a digit nor a separator was found
+    InvalidCharacter(char),
+    /// A digit group separator which is not placed between digits was found
+    MisplacedSeparator(char),
+    /// The unit suffix is not known
+    UnknownUnit(String),
+    /// The amount does not fit into 128 bits
+    Overflow,
+}
+
+impl Display for ParseAmountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseAmountError::NotHex => write!(f, "hex Amount should start with 0x"),
+            ParseAmountError::LeadingZeros => {
+                write!(f, "non-zero hex Amount should not start with 0x0")
+            }
+            ParseAmountError::ParseInt(parse_int_error) => write!(f, "{}", parse_int_error),
+            ParseAmountError::NotDecimal => {
+                write!(f, "decimal Amount should be of the form <units>.<subunits>")
+            }
+            ParseAmountError::TooManyDecimals => {
+                write!(f, "decimal Amount had more decimals than supported")
+            }
+            ParseAmountError::Empty => write!(f, "Amount should contain at least one digit"),
+            ParseAmountError::Negative => write!(f, "Amount cannot be negative"),
+            ParseAmountError::InvalidCharacter(c) => {
+                write!(f, "unexpected character '{}' in Amount", c)
+            }
+            ParseAmountError::MisplacedSeparator(c) => {
+                write!(f, "digit separator '{}' should be placed between digits", c)
+            }
+            ParseAmountError::UnknownUnit(unit) => write!(f, "unknown Amount unit '{}'", unit),
+            ParseAmountError::Overflow => write!(f, "Amount does not fit into 128 bits"),
+        }
+    }
+}
+
+impl std::error::Error for ParseAmountError {}
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(u128::MAX);
+
+    pub const fn from_subunits(subunits: u128) -> Self {
+        Amount(subunits)
+    }
+
+    pub const fn subunits(&self) -> u128 {
+        self.0
+    }
+
+    pub const fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Saturates at [Amount::MAX] if the amount does not fit into 128 bits; see
+    /// [Amount::checked_from_vsl_tokens].
+    pub fn from_vsl_tokens(tokens: u128) -> Self {
+        Self::checked_from_vsl_tokens(tokens).unwrap_or(Amount::MAX)
+    }
+
+    pub const fn checked_from_vsl_tokens(tokens: u128) -> Option<Self> {
+        if let Some(v) = tokens.checked_mul(ONE_VSL_TOKEN) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    /// Saturates at [Amount::MAX] if the amount does not fit into 128 bits; see
+    /// [Amount::checked_from_tokens].
+    pub fn from_tokens(tokens: u128, decimals: u8) -> Self {
+        Self::checked_from_tokens(tokens, decimals).unwrap_or(Amount::MAX)
+    }
+
+    pub const fn checked_from_tokens(tokens: u128, decimals: u8) -> Option<Self> {
+        let Some(one_token) = 10u128.checked_pow(decimals as u32) else {
+            return None;
+        };
+        if let Some(v) = tokens.checked_mul(one_token) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Amount) -> Option<Amount> {
+        if let Some(v) = self.0.checked_sub(rhs.0) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    pub const fn checked_add(self, rhs: Amount) -> Option<Amount> {
+        if let Some(v) = self.0.checked_add(rhs.0) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    pub const fn checked_mul(self, rhs: u128) -> Option<Amount> {
+        if let Some(v) = self.0.checked_mul(rhs) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    /// Integer division (rounding down); `None` if `rhs` is zero
+    pub const fn checked_div(self, rhs: u128) -> Option<Amount> {
+        if let Some(v) = self.0.checked_div(rhs) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    /// Remainder of the integer division; `None` if `rhs` is zero
+    pub const fn checked_rem(self, rhs: u128) -> Option<Amount> {
+        if let Some(v) = self.0.checked_rem(rhs) {
+            Some(Amount(v))
+        } else {
+            None
+        }
+    }
+
+    pub const fn saturating_add(self, rhs: Amount) -> Amount {
+        Amount(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Amount) -> Amount {
+        Amount(self.0.saturating_sub(rhs.0))
+    }
+
+    pub const fn saturating_mul(self, rhs: u128) -> Amount {
+        Amount(self.0.saturating_mul(rhs))
+    }
+
+    /// Computes `self * numerator / denominator` (rounding down) without
+    /// overflowing in the intermediate product.
+    ///
+    /// Returns `None` if `denominator` is zero or the result does not fit into an [Amount].
+    pub fn checked_mul_div(self, numerator: u128, denominator: u128) -> Option<Amount> {
+        if denominator == 0 {
+            return None;
+        }
+        let result = U256::from(self.0) * U256::from(numerator) / U256::from(denominator);
+        u128::try_from(result).ok().map(Amount)
+    }
+
+    /// Computes `percent`% of `self` (rounding down)
+    pub fn checked_percent(self, percent: u128) -> Option<Amount> {
+        self.checked_mul_div(percent, 100)
+    }
+
+    /// Computes `bps` basis points (hundredths of a percent) of `self` (rounding down)
+    pub fn checked_bps(self, bps: u128) -> Option<Amount> {
+        self.checked_mul_div(bps, 10_000)
+    }
+
+    /// Adds up all amounts, returning `None` on overflow
+    pub fn checked_sum<I: IntoIterator<Item = Amount>>(amounts: I) -> Option<Amount> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |acc, amount| acc.checked_add(amount))
+    }
+
+    /// Adds up all amounts, saturating at [Amount::MAX]
+    pub fn saturating_sum<I: IntoIterator<Item = Amount>>(amounts: I) -> Amount {
+        amounts
+            .into_iter()
+            .fold(Amount::ZERO, |acc, amount| acc.saturating_add(amount))
+    }
+
+    /// Splits the amount into `parts` shares which add up exactly to `self`.
+    ///
+    /// The dust left over by the integer division is handed out, one subunit
+    /// each, to the first recipients. Returns `None` if `parts` is zero.
+    pub fn split_evenly(self, parts: usize) -> Option<Vec<Amount>> {
+        let share = self.checked_div(parts as u128)?.0;
+        let dust = self.checked_rem(parts as u128)?.0 as usize;
+        Some(
+            (0..parts)
+                .map(|i| Amount(if i < dust { share + 1 } else { share }))
+                .collect(),
+        )
+    }
+
+    /// Splits the amount proportionally to the given `weights`, such that the
+    /// shares add up exactly to `self`.
+    ///
+    /// Each recipient gets the rounded-down proportional share; the dust is then
+    /// handed out, one subunit each, to the recipients with the largest
+    /// remainders (the first of them, on ties). Returns `None` if there are no
+    /// weights or if they add up to zero (or overflow).
+    pub fn split_weighted(self, weights: &[u128]) -> Option<Vec<Amount>> {
+        let total = weights
+            .iter()
+            .try_fold(0u128, |acc, weight| acc.checked_add(*weight))?;
+        if total == 0 {
+            return None;
+        }
+        let total = U256::from(total);
+        let mut shares = Vec::with_capacity(weights.len());
+        let mut remainders = Vec::with_capacity(weights.len());
+        for (i, weight) in weights.iter().enumerate() {
+            let product = U256::from(self.0) * U256::from(*weight);
+            // the share is at most `self`, hence it fits into 128 bits
+            shares.push(u128::try_from(product / total).ok()?);
+            remainders.push((product % total, i));
+        }
+        let distributed: u128 = shares.iter().sum();
+        let dust = (self.0 - distributed) as usize;
+        remainders.sort_by(|(r1, i1), (r2, i2)| r2.cmp(r1).then(i1.cmp(i2)));
+        for (_, i) in remainders.into_iter().take(dust) {
+            shares[i] += 1;
+        }
+        Some(shares.into_iter().map(Amount).collect())
+    }
+
+    pub fn to_hex_str(&self) -> String {
+        format!("{:#x}", self)
+    }
+
+    pub fn from_hex_str(s: &str) -> Result<Self, ParseAmountError> {
+        if !s.starts_with("0x") {
+            return Err(ParseAmountError::NotHex);
+        }
+        let s = &s[2..];
+        if s == "0" {
+            return Ok(Self::ZERO);
+        }
+        if s.starts_with('0') {
+            Err(ParseAmountError::LeadingZeros)
+        } else {
+            let subunits = u128::from_str_radix(s, 16).map_err(ParseAmountError::ParseInt)?;
+            Ok(Self::from_subunits(subunits))
+        }
+    }
+
+    pub fn to_str_with_decimals(&self, decimals: u8) -> String {
+        let digits = self.0.to_string();
+        let decimals = decimals as usize;
+        let (units, subunits) = if digits.len() > decimals {
+            let (units, subunits) = digits.split_at(digits.len() - decimals);
+            (units.to_string(), subunits.to_string())
+        } else {
+            (
+                "0".to_string(),
+                format!("{:0>width$}", digits, width = decimals),
+            )
+        };
+        let subunits = subunits.trim_end_matches('0');
+        if subunits.is_empty() {
+            return units;
+        }
+        format!("{}.{}", units, subunits)
+    }
+
+    pub fn from_str_with_decimals(s: &str, decimals: u8) -> Result<Self, ParseAmountError> {
+        let mut iter = s.split(".");
+        let Some(units) = iter.next() else {
+            return Err(ParseAmountError::NotDecimal);
+        };
+        let subunits = iter.next().unwrap_or("0");
+        if iter.next().is_some() {
+            return Err(ParseAmountError::NotDecimal);
+        }
+        let decimals = decimals as usize;
+        if subunits.len() > decimals {
+            return Err(ParseAmountError::TooManyDecimals);
+        }
+        let restored = format!("{}{:0<width$}", units, subunits, width = decimals);
+        let amount = restored
+            .parse::<u128>()
+            .map_err(ParseAmountError::ParseInt)?;
+        Ok(Self(amount))
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_add].
+impl Add for Amount {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.saturating_add(rhs)
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_add].
+impl AddAssign for Amount {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// Saturates at [Amount::ZERO]; see [Amount::checked_sub].
+impl Sub for Amount {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.saturating_sub(rhs)
+    }
+}
+
+/// Saturates at [Amount::ZERO]; see [Amount::checked_sub].
+impl SubAssign for Amount {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_mul].
+impl Mul for Amount {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.saturating_mul(rhs.0)
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_mul].
+impl Mul<u128> for Amount {
+    type Output = Self;
+
+    fn mul(self, rhs: u128) -> Self::Output {
+        self.saturating_mul(rhs)
+    }
+}
+
+/// Dividing by zero gives [Amount::MAX]; see [Amount::checked_div].
+impl Div<u128> for Amount {
+    type Output = Self;
+
+    fn div(self, rhs: u128) -> Self::Output {
+        self.checked_div(rhs).unwrap_or(Amount::MAX)
+    }
+}
+
+/// The remainder of a division by zero is `self`; see [Amount::checked_rem].
+impl Rem<u128> for Amount {
+    type Output = Self;
+
+    fn rem(self, rhs: u128) -> Self::Output {
+        self.checked_rem(rhs).unwrap_or(self)
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_sum].
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Self {
+        Amount::saturating_sum(iter)
+    }
+}
+
+/// Saturates at [Amount::MAX]; see [Amount::checked_sum].
+impl<'a> Sum<&'a Amount> for Amount {
+    fn sum<I: Iterator<Item = &'a Amount>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Amount, ParseAmountError};
+
+    #[test]
+    fn test_amount_parsing_decimal() {
+        assert_eq!(
+            Amount::from_str_with_decimals("100", 2).unwrap(),
+            Amount::from_tokens(100, 2)
+        );
+        assert_eq!(
+            Amount::from_str_with_decimals("100.1", 2).unwrap(),
+            Amount::from_subunits(10010)
+        );
+        assert_eq!(
+            Amount::from_str_with_decimals("100.12", 2).unwrap(),
+            Amount::from_subunits(10012)
+        );
+        assert_eq!(
+            Amount::from_str_with_decimals("100.02", 2).unwrap(),
+            Amount::from_subunits(10002)
+        );
+        let ParseAmountError::TooManyDecimals =
+            Amount::from_str_with_decimals("100.122", 2).unwrap_err()
+        else {
+            panic!("Expected too many decimals error");
+        };
+    }
+
+    #[test]
+    fn test_amount_formatting_decimal() {
+        assert_eq!(
+            Amount::from_subunits(123456).to_str_with_decimals(3),
+            "123.456"
+        );
+        assert_eq!(
+            Amount::from_subunits(123450).to_str_with_decimals(3),
+            "123.45"
+        );
+        assert_eq!(
+            Amount::from_subunits(123400).to_str_with_decimals(3),
+            "123.4"
+        );
+        assert_eq!(Amount::from_subunits(123000).to_str_with_decimals(3), "123");
+        assert_eq!(
+            Amount::from_subunits(123045).to_str_with_decimals(3),
+            "123.045"
+        );
+        assert_eq!(
+            Amount::from_subunits(123040).to_str_with_decimals(3),
+            "123.04"
+        );
+        assert_eq!(
+            Amount::from_subunits(123004).to_str_with_decimals(3),
+            "123.004"
+        );
+    }
+
+    #[test]
+    fn test_amount_formatting_many_decimals() {
+        assert_eq!(Amount::from_subunits(45).to_str_with_decimals(3), "0.045");
+        assert_eq!(Amount::ZERO.to_str_with_decimals(3), "0");
+        assert_eq!(Amount::from_subunits(123).to_str_with_decimals(0), "123");
+        assert_eq!(
+            Amount::MAX.to_str_with_decimals(40),
+            "0.0340282366920938463463374607431768211455"
+        );
+        assert_eq!(
+            Amount::from_subunits(1).to_str_with_decimals(255).len(),
+            257
+        );
+    }
+
+    #[test]
+    fn test_checked_conversions() {
+        assert_eq!(Amount::checked_from_tokens(1, 39), None);
+        assert_eq!(
+            Amount::checked_from_tokens(1, 38),
+            Some(Amount::from_subunits(10u128.pow(38)))
+        );
+        assert_eq!(Amount::checked_from_vsl_tokens(u128::MAX), None);
+        assert_eq!(
+            Amount::checked_from_vsl_tokens(2),
+            Some(Amount::from_vsl_tokens(2))
+        );
+    }
+
+    #[test]
+    fn test_checked_and_saturating_arithmetic() {
+        let a = Amount::from_subunits(10);
+        assert_eq!(a.checked_mul(3), Some(Amount::from_subunits(30)));
+        assert_eq!(Amount::MAX.checked_mul(2), None);
+        assert_eq!(a.checked_div(3), Some(Amount::from_subunits(3)));
+        assert_eq!(a.checked_rem(3), Some(Amount::from_subunits(1)));
+        assert_eq!(a.checked_div(0), None);
+        assert_eq!(Amount::MAX.saturating_add(a), Amount::MAX);
+        assert_eq!(a.saturating_sub(Amount::MAX), Amount::ZERO);
+        assert_eq!(Amount::MAX.saturating_mul(2), Amount::MAX);
+        assert_eq!(a / 4, Amount::from_subunits(2));
+        assert_eq!(a % 4, Amount::from_subunits(2));
+        let mut b = a + a;
+        b -= a;
+        assert_eq!(b - a, Amount::ZERO);
+    }
+
+    #[test]
+    fn test_operators_saturate() {
+        let one = Amount::from_subunits(1);
+        let mut a = Amount::MAX;
+        a += one;
+        assert_eq!(a, Amount::MAX);
+        assert_eq!(Amount::ZERO - one, Amount::ZERO);
+        assert_eq!(Amount::MAX * 2, Amount::MAX);
+        assert_eq!(Amount::MAX * Amount::from_subunits(2), Amount::MAX);
+        assert_eq!(one / 0, Amount::MAX);
+        assert_eq!(one % 0, one);
+        assert_eq!([Amount::MAX, one].iter().sum::<Amount>(), Amount::MAX);
+        assert_eq!(Amount::from_vsl_tokens(u128::MAX), Amount::MAX);
+        assert_eq!(Amount::from_tokens(1, 39), Amount::MAX);
+    }
+
+    #[test]
+    fn test_percentages() {
+        let a = Amount::from_subunits(1_000);
+        assert_eq!(a.checked_percent(15), Some(Amount::from_subunits(150)));
+        assert_eq!(a.checked_bps(25), Some(Amount::from_subunits(2)));
+        assert_eq!(a.checked_mul_div(1, 0), None);
+        // the intermediate product does not overflow
+        assert_eq!(Amount::MAX.checked_mul_div(3, 3), Some(Amount::MAX));
+        assert_eq!(Amount::MAX.checked_mul_div(3, 2), None);
+    }
+
+    #[test]
+    fn test_sums() {
+        let amounts = [1u128, 2, 3].map(Amount::from_subunits);
+        assert_eq!(amounts.iter().sum::<Amount>(), Amount::from_subunits(6));
+        assert_eq!(Amount::checked_sum(amounts), Some(Amount::from_subunits(6)));
+        assert_eq!(
+            Amount::checked_sum([Amount::MAX, Amount::from_subunits(1)]),
+            None
+        );
+        assert_eq!(
+            Amount::saturating_sum([Amount::MAX, Amount::from_subunits(1)]),
+            Amount::MAX
+        );
+    }
+
+    #[test]
+    fn test_split_evenly() {
+        let shares = Amount::from_subunits(10).split_evenly(3).unwrap();
+        assert_eq!(shares, [4u128, 3, 3].map(Amount::from_subunits));
+        assert_eq!(Amount::from_subunits(10).split_evenly(0), None);
+        let shares = Amount::MAX.split_evenly(7).unwrap();
+        assert_eq!(shares.iter().sum::<Amount>(), Amount::MAX);
+    }
+
+    #[test]
+    fn test_split_weighted() {
+        let shares = Amount::from_subunits(100)
+            .split_weighted(&[1, 1, 1])
+            .unwrap();
+        assert_eq!(shares, [34u128, 33, 33].map(Amount::from_subunits));
+        let shares = Amount::from_subunits(10)
+            .split_weighted(&[1, 2, 7])
+            .unwrap();
+        assert_eq!(shares, [1u128, 2, 7].map(Amount::from_subunits));
+        // remainders: 1 * 11 % 10 = 1, 2 * 11 % 10 = 2, 7 * 11 % 10 = 7
+        let shares = Amount::from_subunits(11)
+            .split_weighted(&[1, 2, 7])
+            .unwrap();
+        assert_eq!(shares, [1u128, 2, 8].map(Amount::from_subunits));
+        let shares = Amount::MAX.split_weighted(&[u128::MAX - 1, 1, 0]).unwrap();
+        assert_eq!(shares.iter().sum::<Amount>(), Amount::MAX);
+        assert_eq!(shares[2], Amount::ZERO);
+        assert_eq!(Amount::from_subunits(1).split_weighted(&[]), None);
+        assert_eq!(Amount::from_subunits(1).split_weighted(&[0, 0]), None);
+    }
+}
@@ -0,0 +1,481 @@
+//! # Amount Formatting Module
+//!
+//! This module provides configurable, human-friendly formatting ([AmountFormat])
+//! and parsing ([AmountParser]) of [Amount]s: rounding to a given precision,
+//! digit grouping, and unit suffixes such as `"1.5 VSL"` or `"2500 atto"`.
+//!
+use crate::{Amount, ParseAmountError, VSL_DECIMALS};
+
+/// The ticker of the native VSL token
+pub const VSL_SYMBOL: &str = "VSL";
+
+/// How to round amounts which have more decimals than displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Round towards zero (truncate)
+    #[default]
+    Down,
+    /// Round away from zero
+    Up,
+    /// Round to the nearest value; halfway values are rounded up
+    HalfUp,
+    /// Round to the nearest value; halfway values are rounded to an even last digit
+    HalfEven,
+}
+
+/// How many decimals to display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// All significant decimals, without trailing zeros
+    #[default]
+    Trimmed,
+    /// Exactly the given number of decimals (padding with zeros)
+    Fixed(u8),
+    /// At most the given number of decimals, without trailing zeros
+    AtMost(u8),
+}
+
+/// A configurable [Amount] formatter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountFormat {
+    /// The number of decimals of the token being formatted
+    pub decimals: u8,
+    /// How many decimals to display
+    pub precision: Precision,
+    /// How to round the hidden decimals
+    pub rounding: Rounding,
+    /// The separator to insert between groups of three digits of the integral part
+    pub thousands_separator: Option<char>,
+    /// The unit to append (separated by a space)
+    pub unit: Option<String>,
+}
+
+impl AmountFormat {
+    /// Formats all significant decimals, without grouping nor unit;
+    /// same as [Amount::to_str_with_decimals]
+    pub fn new(decimals: u8) -> Self {
+        AmountFormat {
+            decimals,
+            precision: Precision::Trimmed,
+            rounding: Rounding::Down,
+            thousands_separator: None,
+            unit: None,
+        }
+    }
+
+    /// Formats VSL tokens, suffixed by the `VSL` unit
+    pub fn vsl() -> Self {
+        Self::new(VSL_DECIMALS).with_unit(VSL_SYMBOL)
+    }
+
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    pub fn with_thousands_separator(mut self, separator: char) -> Self {
+        self.thousands_separator = Some(separator);
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    pub fn format(&self, amount: &Amount) -> String {
+        let (shown, trim) = match self.precision {
+            Precision::Trimmed => (self.decimals, true),
+            Precision::Fixed(shown) => (shown, false),
+            Precision::AtMost(shown) => (shown.min(self.decimals), true),
+        };
+        let subunits = amount.subunits();
+        let digits = if shown >= self.decimals {
+            // nothing to round; pad with zeros up to the shown decimals
+            let mut digits = subunits.to_string();
+            digits.extend(std::iter::repeat_n('0', (shown - self.decimals) as usize));
+            digits
+        } else {
+            round(subunits, (self.decimals - shown) as u32, self.rounding).to_string()
+        };
+        let shown = shown as usize;
+        let digits = format!("{:0>width$}", digits, width = shown + 1);
+        let (units, fraction) = digits.split_at(digits.len() - shown);
+        let fraction = if trim {
+            fraction.trim_end_matches('0')
+        } else {
+            fraction
+        };
+        let mut result = match self.thousands_separator {
+            Some(separator) => group_digits(units, separator),
+            None => units.to_string(),
+        };
+        if !fraction.is_empty() {
+            result.push('.');
+            result.push_str(fraction);
+        }
+        if let Some(unit) = &self.unit {
+            result.push(' ');
+            result.push_str(unit);
+        }
+        result
+    }
+}
+
+/// Divides `value` by `10^exponent`, rounding according to `rounding`
+fn round(value: u128, exponent: u32, rounding: Rounding) -> u128 {
+    let Some(scale) = 10u128.checked_pow(exponent) else {
+        // the scale is larger than any value, which thus rounds to zero or one
+        return match rounding {
+            Rounding::Up if value > 0 => 1,
+            _ => 0,
+        };
+    };
+    let (quotient, remainder) = (value / scale, value % scale);
+    // `quotient + 1` cannot overflow because `scale >= 10`
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => remainder > 0,
+        Rounding::HalfUp => remainder >= scale - remainder,
+        Rounding::HalfEven => {
+            remainder > scale - remainder
+                || (remainder == scale - remainder && !quotient.is_multiple_of(2))
+        }
+    };
+    if round_up { quotient + 1 } else { quotient }
+}
+
+fn group_digits(digits: &str, separator: char) -> String {
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(separator);
+        }
+        result.push(digit);
+    }
+    result
+}
+
+/// A unit in which amounts can be expressed (e.g. `VSL` or `atto`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Denomination {
+    /// The (case-insensitive) unit suffix
+    pub symbol: String,
+    /// The number of decimals of the unit, i.e., one unit is `10^decimals` subunits
+    pub decimals: u8,
+}
+
+impl Denomination {
+    pub fn new(symbol: &str, decimals: u8) -> Self {
+        Denomination {
+            symbol: symbol.to_string(),
+            decimals,
+        }
+    }
+}
+
+/// A lenient [Amount] parser
+///
+/// Accepts an optional leading `+`, digits grouped with `_` or in thousands with `,`
+/// (e.g. `"1_000.5"` or `"1,000.5"`), and an optional unit suffix
+/// (e.g. `"1.5 VSL"` or `"2500 atto"`). Unsuffixed amounts are read
+/// with the default number of decimals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountParser {
+    /// The number of decimals for amounts without unit
+    pub decimals: u8,
+    /// The units accepted as suffixes
+    pub denominations: Vec<Denomination>,
+}
+
+impl AmountParser {
+    /// A parser for unsuffixed amounts with the given number of decimals
+    pub fn new(decimals: u8) -> Self {
+        AmountParser {
+            decimals,
+            denominations: Vec::new(),
+        }
+    }
+
+    /// A parser for VSL amounts, accepting the `VSL` and `atto` (or `attoVSL`) units
+    pub fn vsl() -> Self {
+        Self::new(VSL_DECIMALS)
+            .with_denomination(VSL_SYMBOL, VSL_DECIMALS)
+            .with_denomination("atto", 0)
+            .with_denomination("attoVSL", 0)
+    }
+
+    /// A parser for amounts of an asset, accepting its ticker as unit
+    pub fn for_asset(ticker_symbol: &str, decimals: u8) -> Self {
+        Self::new(decimals).with_denomination(ticker_symbol, decimals)
+    }
+
+    pub fn with_denomination(mut self, symbol: &str, decimals: u8) -> Self {
+        self.denominations.push(Denomination::new(symbol, decimals));
+        self
+    }
+
+    pub fn parse(&self, s: &str) -> Result<Amount, ParseAmountError> {
+        let s = s.trim();
+        let number_end = s
+            .find(|c: char| !(c.is_ascii_digit() || "+-_,.".contains(c)))
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(number_end);
+        let unit = unit.trim();
+        let decimals = if unit.is_empty() {
+            self.decimals
+        } else {
+            self.denominations
+                .iter()
+                .find(|d| d.symbol.eq_ignore_ascii_case(unit))
+                .ok_or_else(|| ParseAmountError::UnknownUnit(unit.to_string()))?
+                .decimals
+        };
+        parse_number(number.trim_end(), decimals)
+    }
+}
+
+fn parse_number(s: &str, decimals: u8) -> Result<Amount, ParseAmountError> {
+    if s.starts_with('-') {
+        return Err(ParseAmountError::Negative);
+    }
+    let s = s.strip_prefix('+').unwrap_or(s);
+    if s.is_empty() {
+        return Err(ParseAmountError::Empty);
+    }
+    let (units, fraction) = match s.split_once('.') {
+        Some((units, fraction)) => (units, fraction),
+        None => (s, ""),
+    };
+    if fraction.contains('.') {
+        return Err(ParseAmountError::NotDecimal);
+    }
+    check_thousands_groups(units)?;
+    let units = strip_separators(units, "_,")?;
+    let fraction = strip_separators(fraction, "_")?;
+    if units.is_empty() && fraction.is_empty() {
+        return Err(ParseAmountError::Empty);
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(ParseAmountError::TooManyDecimals);
+    }
+    let mut value: u128 = 0;
+    let padding = std::iter::repeat_n('0', decimals as usize - fraction.len());
+    for digit in units.chars().chain(fraction.chars()).chain(padding) {
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(digit as u128 - '0' as u128))
+            .ok_or(ParseAmountError::Overflow)?;
+    }
+    Ok(Amount::from_subunits(value))
+}
+
+/// Commas may only separate groups of three digits (as in `12,345,678`), so that
+/// a decimal comma (`1,5`) is not mistaken for one
+fn check_thousands_groups(units: &str) -> Result<(), ParseAmountError> {
+    let mut groups = units.split(',');
+    let first = groups.next().unwrap_or_default();
+    if units.contains(',') && first.len() > 3 {
+        return Err(ParseAmountError::MisplacedSeparator(','));
+    }
+    if groups.any(|group| group.len() != 3) {
+        return Err(ParseAmountError::MisplacedSeparator(','));
+    }
+    Ok(())
+}
+
+/// Removes the digit group separators, which are only allowed between digits
+fn strip_separators(s: &str, separators: &str) -> Result<String, ParseAmountError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut result = String::with_capacity(chars.len());
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_digit() {
+            result.push(*c);
+        } else if separators.contains(*c) {
+            let between_digits = i > 0
+                && chars[i - 1].is_ascii_digit()
+                && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+            if !between_digits {
+                return Err(ParseAmountError::MisplacedSeparator(*c));
+            }
+        } else {
+            return Err(ParseAmountError::InvalidCharacter(*c));
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trimmed_format() {
+        let format = AmountFormat::new(3);
+        assert_eq!(format.format(&Amount::from_subunits(123450)), "123.45");
+        assert_eq!(format.format(&Amount::from_subunits(45)), "0.045");
+        assert_eq!(format.format(&Amount::ZERO), "0");
+    }
+
+    #[test]
+    fn test_fixed_precision_and_rounding() {
+        let amount = Amount::from_subunits(12345);
+        let format = AmountFormat::new(3).with_precision(Precision::Fixed(2));
+        assert_eq!(format.format(&amount), "12.34");
+        assert_eq!(
+            format.clone().with_rounding(Rounding::Up).format(&amount),
+            "12.35"
+        );
+        assert_eq!(
+            format
+                .clone()
+                .with_rounding(Rounding::HalfUp)
+                .format(&amount),
+            "12.35"
+        );
+        assert_eq!(
+            format
+                .clone()
+                .with_rounding(Rounding::HalfEven)
+                .format(&amount),
+            "12.34"
+        );
+        let format = format.with_rounding(Rounding::HalfEven);
+        assert_eq!(format.format(&Amount::from_subunits(12355)), "12.36");
+        assert_eq!(format.format(&Amount::from_subunits(12356)), "12.36");
+        let format = AmountFormat::new(3).with_precision(Precision::Fixed(5));
+        assert_eq!(format.format(&amount), "12.34500");
+        let format = AmountFormat::new(3).with_precision(Precision::Fixed(0));
+        assert_eq!(
+            format
+                .with_rounding(Rounding::HalfUp)
+                .format(&Amount::from_subunits(999_500)),
+            "1000"
+        );
+    }
+
+    #[test]
+    fn test_at_most_precision() {
+        let format = AmountFormat::new(4).with_precision(Precision::AtMost(2));
+        assert_eq!(format.format(&Amount::from_subunits(12_3456)), "12.34");
+        assert_eq!(format.format(&Amount::from_subunits(12_3000)), "12.3");
+        assert_eq!(
+            format
+                .with_rounding(Rounding::Up)
+                .format(&Amount::from_subunits(12_9901)),
+            "13"
+        );
+    }
+
+    #[test]
+    fn test_grouping_and_unit() {
+        let format = AmountFormat::vsl().with_thousands_separator(',');
+        let amount = Amount::from_str_with_decimals("1234567.5", VSL_DECIMALS).unwrap();
+        assert_eq!(format.format(&amount), "1,234,567.5 VSL");
+        assert_eq!(format.format(&Amount::from_vsl_tokens(123)), "123 VSL");
+        assert_eq!(format.format(&Amount::from_vsl_tokens(1000)), "1,000 VSL");
+    }
+
+    #[test]
+    fn test_huge_decimals() {
+        let format = AmountFormat::new(100).with_precision(Precision::Fixed(2));
+        assert_eq!(format.format(&Amount::MAX), "0.00");
+        assert_eq!(
+            format.with_rounding(Rounding::Up).format(&Amount::MAX),
+            "0.01"
+        );
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let parser = AmountParser::vsl();
+        assert_eq!(
+            parser.parse("1.5 VSL").unwrap(),
+            Amount::from_subunits(15 * 10u128.pow(17))
+        );
+        assert_eq!(
+            parser.parse("1.5vsl").unwrap(),
+            parser.parse("1.5").unwrap()
+        );
+        assert_eq!(
+            parser.parse("2500 atto").unwrap(),
+            Amount::from_subunits(2500)
+        );
+        assert_eq!(
+            parser.parse("+2_500 attoVSL").unwrap(),
+            Amount::from_subunits(2500)
+        );
+        assert_eq!(
+            parser.parse("1_000.5").unwrap(),
+            Amount::from_str_with_decimals("1000.5", VSL_DECIMALS).unwrap()
+        );
+        assert_eq!(
+            parser.parse("1,000").unwrap(),
+            Amount::from_vsl_tokens(1000)
+        );
+        assert_eq!(
+            parser.parse("12,345,678").unwrap(),
+            Amount::from_vsl_tokens(12_345_678)
+        );
+        assert_eq!(parser.parse(".5").unwrap(), parser.parse("0.5").unwrap());
+        assert_eq!(
+            parser.parse("1.50000000000000000000").unwrap(),
+            parser.parse("1.5").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parsing_errors() {
+        let parser = AmountParser::for_asset("TST", 2);
+        assert_eq!(parser.parse("1.5 TST").unwrap(), Amount::from_subunits(150));
+        assert!(
+            matches!(parser.parse("1.5 VSL"), Err(ParseAmountError::UnknownUnit(u)) if u == "VSL")
+        );
+        assert!(matches!(
+            parser.parse("-1"),
+            Err(ParseAmountError::Negative)
+        ));
+        assert!(matches!(parser.parse(""), Err(ParseAmountError::Empty)));
+        assert!(matches!(parser.parse("+"), Err(ParseAmountError::Empty)));
+        assert!(matches!(
+            parser.parse("1.234"),
+            Err(ParseAmountError::TooManyDecimals)
+        ));
+        assert!(matches!(
+            parser.parse("1..2"),
+            Err(ParseAmountError::NotDecimal)
+        ));
+        assert!(matches!(
+            parser.parse("1__000"),
+            Err(ParseAmountError::MisplacedSeparator('_'))
+        ));
+        assert!(matches!(
+            parser.parse("_1"),
+            Err(ParseAmountError::MisplacedSeparator('_'))
+        ));
+        for misplaced in ["1,5", "1,0000", "1234,567", "1,000,00"] {
+            assert!(matches!(
+                AmountParser::vsl().parse(misplaced),
+                Err(ParseAmountError::MisplacedSeparator(','))
+            ));
+        }
+        assert!(matches!(
+            parser.parse("1.0,5"),
+            Err(ParseAmountError::InvalidCharacter(','))
+        ));
+        assert!(matches!(
+            parser.parse("1+2"),
+            Err(ParseAmountError::InvalidCharacter('+'))
+        ));
+        assert!(matches!(
+            parser.parse("340282366920938463463374607431768211455"),
+            Err(ParseAmountError::Overflow)
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! # Amount Serde Adapters
+//!
+//! The derived serde implementation of [Amount] emits a raw number, which cannot be
+//! represented exactly by JSON consumers using 64 bit floats (e.g. JavaScript).
+//! This module provides adapters for the string-based formats used on the wire,
+//! to be used with `#[serde(with = "...")]`:
+//!
+//! - [hex]: `u128` formatted as a `0x`-prefixed hex string (e.g. `"0x2a"`)
+//! - [decimal]: `u128` formatted as a base-10 string (e.g. `"42"`)
+//! - [vsl_tokens]: VSL tokens formatted with 18 decimals (e.g. `"1.5"`)
+//! - [TokenDecimals]: tokens formatted with a given number of decimals
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use vsl_sdk::{Amount, amount_serde};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Payout {
+//!     #[serde(with = "amount_serde::hex")]
+//!     fee: Amount,
+//!     #[serde(with = "amount_serde::TokenDecimals::<6>")]
+//!     amount: Amount,
+//! }
+//! ```
+//!
+use std::marker::PhantomData;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::{Amount, VSL_DECIMALS};
+
+fn deserialize_with<'de, D, E>(
+    deserializer: D,
+    parse: impl FnOnce(&str) -> Result<Amount, E>,
+) -> Result<Amount, D::Error>
+where
+    D: Deserializer<'de>,
+    E: std::fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+    parse(&s).map_err(D::Error::custom)
+}
+
+/// Serializes an [Amount] as a `0x`-prefixed hex string, as used in the RPC messages
+pub mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&amount.to_hex_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        deserialize_with(deserializer, Amount::from_hex_str)
+    }
+}
+
+/// Serializes an [Amount] as a base-10 string of its subunits
+pub mod decimal {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&amount.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        deserialize_with(deserializer, |s| s.parse::<Amount>())
+    }
+}
+
+/// Serializes an [Amount] as a decimal string of VSL tokens (e.g. `"1.5"`)
+pub mod vsl_tokens {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        TokenDecimals::<VSL_DECIMALS>::serialize(amount, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        TokenDecimals::<VSL_DECIMALS>::deserialize(deserializer)
+    }
+}
+
+/// Serializes an [Amount] as a decimal string of tokens with `DECIMALS` decimals
+/// (e.g. `"1.5"`); use as `#[serde(with = "amount_serde::TokenDecimals::<6>")]`
+pub struct TokenDecimals<const DECIMALS: u8>(PhantomData<()>);
+
+impl<const DECIMALS: u8> TokenDecimals<DECIMALS> {
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&amount.to_str_with_decimals(DECIMALS))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        deserialize_with(deserializer, |s| {
+            Amount::from_str_with_decimals(s, DECIMALS)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::Amount;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Amounts {
+        #[serde(with = "super::hex")]
+        hex: Amount,
+        #[serde(with = "super::decimal")]
+        decimal: Amount,
+        #[serde(with = "super::vsl_tokens")]
+        vsl: Amount,
+        #[serde(with = "super::TokenDecimals::<2>")]
+        cents: Amount,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let amounts = Amounts {
+            hex: Amount::from_subunits(42),
+            decimal: Amount::MAX,
+            vsl: Amount::from_subunits(15 * 10u128.pow(17)),
+            cents: Amount::from_subunits(1234),
+        };
+        let json = serde_json::to_string(&amounts).unwrap();
+        assert_eq!(
+            json,
+            r#"{"hex":"0x2a","decimal":"340282366920938463463374607431768211455","vsl":"1.5","cents":"12.34"}"#
+        );
+        assert_eq!(serde_json::from_str::<Amounts>(&json).unwrap(), amounts);
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let json = r#"{"hex":"42","decimal":"1","vsl":"1","cents":"1"}"#;
+        assert!(serde_json::from_str::<Amounts>(json).is_err());
+        let json = r#"{"hex":"0x2a","decimal":1,"vsl":"1","cents":"1"}"#;
+        assert!(serde_json::from_str::<Amounts>(json).is_err());
+        let json = r#"{"hex":"0x2a","decimal":"1","vsl":"1","cents":"1.234"}"#;
+        assert!(serde_json::from_str::<Amounts>(json).is_err());
+    }
+}
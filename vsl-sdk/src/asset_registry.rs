@@ -0,0 +1,272 @@
+//! # Asset Registry Module
+//!
+//! The metadata of an asset ([AssetData]) is fixed when the asset is created, so
+//! an [AssetRegistry] fetches it at most once per asset and keeps it for the
+//! lifetime of the registry (clones of a registry share its cache). Assets which
+//! are not (yet) created are not cached.
+//!
+//! The registry also looks up the IDs of known assets by ticker symbol, and lists
+//! the balances of an account together with the ticker and decimals of each asset.
+//!
+//! ```no_run
+//! # async fn run() -> vsl_sdk::rpc_wrapper::RpcWrapperResult<()> {
+//! use jsonrpsee::http_client::HttpClientBuilder;
+//! use vsl_sdk::Address;
+//! use vsl_sdk::asset_registry::AssetRegistry;
+//!
+//! let client = HttpClientBuilder::new().build("http://127.0.0.1:44444").unwrap();
+//! let registry = AssetRegistry::new(client);
+//! for balance in registry.asset_balances(&Address::repeat_byte(0x01)).await? {
+//!     println!("{} {}", balance.formatted, balance.ticker);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use jsonrpsee::core::client::ClientT;
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_wrapper::{self, AssetData, RpcWrapperError, RpcWrapperResult};
+use crate::{Address, Amount, AmountFormat, AssetId, TokenAmount};
+
+/// The balance of an account in an asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetBalance {
+    pub asset_id: AssetId,
+    pub ticker: String,
+    pub decimals: u8,
+    /// The balance, in subunits of the asset
+    #[serde(with = "crate::amount_serde::decimal")]
+    pub amount: Amount,
+    /// The balance, in tokens of the asset (e.g. `12.5`)
+    pub formatted: String,
+}
+
+impl AssetBalance {
+    pub fn new(asset_id: AssetId, asset_data: &AssetData, amount: Amount) -> Self {
+        AssetBalance {
+            asset_id,
+            ticker: asset_data.ticker_symbol.clone(),
+            decimals: asset_data.decimals,
+            amount,
+            formatted: AmountFormat::new(asset_data.decimals).format(&amount),
+        }
+    }
+
+    pub fn token_amount(&self) -> TokenAmount {
+        TokenAmount::of_asset(self.asset_id, self.amount, self.decimals)
+    }
+}
+
+/// A client caching the metadata of assets
+#[derive(Debug, Clone)]
+pub struct AssetRegistry<T> {
+    rpc_client: T,
+    assets: Arc<RwLock<HashMap<AssetId, AssetData>>>,
+}
+
+impl<T: ClientT> AssetRegistry<T> {
+    pub fn new(rpc_client: T) -> Self {
+        AssetRegistry {
+            rpc_client,
+            assets: Arc::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.rpc_client
+    }
+
+    /// Records the metadata of an asset (e.g., one just created)
+    pub fn insert(&self, asset_id: AssetId, asset_data: AssetData) {
+        self.assets.write().unwrap().insert(asset_id, asset_data);
+    }
+
+    /// The metadata of the asset, if already known
+    pub fn cached(&self, asset_id: &AssetId) -> Option<AssetData> {
+        self.assets.read().unwrap().get(asset_id).cloned()
+    }
+
+    /// The metadata of the asset, fetched unless already known, or `None` if no
+    /// asset with that ID was created
+    pub async fn get(&self, asset_id: &AssetId) -> RpcWrapperResult<Option<AssetData>> {
+        if let Some(asset_data) = self.cached(asset_id) {
+            return Ok(Some(asset_data));
+        }
+        let asset_data = rpc_wrapper::get_asset_by_id(&self.rpc_client, asset_id).await?;
+        if let Some(asset_data) = &asset_data {
+            self.insert(*asset_id, asset_data.clone());
+        }
+        Ok(asset_data)
+    }
+
+    /// Same as [AssetRegistry::get], failing if the asset was not created
+    pub async fn expect(&self, asset_id: &AssetId) -> RpcWrapperResult<AssetData> {
+        self.get(asset_id)
+            .await?
+            .ok_or(RpcWrapperError::NonExistentAsset)
+    }
+
+    /// The known assets, by ID
+    pub fn known(&self) -> Vec<(AssetId, AssetData)> {
+        let assets = self.assets.read().unwrap();
+        let mut known: Vec<_> = assets
+            .iter()
+            .map(|(asset_id, asset_data)| (*asset_id, asset_data.clone()))
+            .collect();
+        known.sort_by_key(|(asset_id, _)| *asset_id);
+        known
+    }
+
+    /// The IDs of the known assets with the given ticker symbol (tickers are not
+    /// unique, so there may be several)
+    pub fn ids_by_ticker(&self, ticker: &str) -> Vec<AssetId> {
+        let known = self.known().into_iter();
+        known
+            .filter(|(_, asset_data)| asset_data.ticker_symbol == ticker)
+            .map(|(asset_id, _)| asset_id)
+            .collect()
+    }
+
+    /// The balances of all assets held by an account, by ticker (then ID)
+    pub async fn asset_balances(
+        &self,
+        account_id: &Address,
+    ) -> RpcWrapperResult<Vec<AssetBalance>> {
+        let balances = rpc_wrapper::get_asset_balances(&self.rpc_client, account_id).await?;
+        let mut result = Vec::with_capacity(balances.len());
+        for (asset_id, amount) in balances {
+            let asset_data = self.expect(&asset_id).await?;
+            result.push(AssetBalance::new(asset_id, &asset_data, amount));
+        }
+        result.sort_by(|a, b| (&a.ticker, a.asset_id).cmp(&(&b.ticker, b.asset_id)));
+        Ok(result)
+    }
+
+    /// The balance of an account in an asset
+    pub async fn asset_balance(
+        &self,
+        account_id: &Address,
+        asset_id: &AssetId,
+    ) -> RpcWrapperResult<AssetBalance> {
+        let asset_data = self.expect(asset_id).await?;
+        let amount = rpc_wrapper::get_asset_balance(&self.rpc_client, account_id, asset_id).await?;
+        Ok(AssetBalance::new(*asset_id, &asset_data, amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::core::RpcResult;
+    use jsonrpsee::http_client::HttpClient;
+    use jsonrpsee::server::ServerHandle;
+    use jsonrpsee::types::Params;
+
+    use super::*;
+    use crate::rpc_messages::CreateAssetMessage;
+    use crate::test_node;
+
+    fn asset_id(byte: u8) -> AssetId {
+        AssetId::repeat_byte(byte)
+    }
+
+    /// A stand-in node knowing assets 0x01 (`TCK`, 2 decimals) and 0x02 (`TCK`,
+    /// 6 decimals), and counting the metadata requests
+    async fn serve(lookups: Arc<AtomicUsize>) -> (HttpClient, ServerHandle) {
+        let mut module = RpcModule::new(lookups);
+        module
+            .register_method(
+                "vsl_getAssetById",
+                |params: Params, lookups, _| -> RpcResult<Option<CreateAssetMessage>> {
+                    lookups.fetch_add(1, Ordering::SeqCst);
+                    let requested: String = params.one()?;
+                    let decimals = match requested.parse::<AssetId>() {
+                        Ok(id) if id == asset_id(1) => 2,
+                        Ok(id) if id == asset_id(2) => 6,
+                        _ => return Ok(None),
+                    };
+                    Ok(Some(CreateAssetMessage {
+                        account_id: Address::ZERO.to_string(),
+                        nonce: "0".to_string(),
+                        ticker_symbol: "TCK".to_string(),
+                        decimals,
+                        total_supply: Amount::from_tokens(1000, decimals).to_hex_str(),
+                    }))
+                },
+            )
+            .unwrap();
+        module
+            .register_method(
+                "vsl_getAssetBalances",
+                |_, _, _| -> RpcResult<HashMap<String, String>> {
+                    Ok(HashMap::from([
+                        (asset_id(2).to_string(), "0x7a120".to_string()),
+                        (asset_id(1).to_string(), "0x4e2".to_string()),
+                    ]))
+                },
+            )
+            .unwrap();
+        module
+            .register_method("vsl_getAssetBalance", |_, _, _| -> RpcResult<String> {
+                Ok("0x4e2".to_string())
+            })
+            .unwrap();
+        test_node::serve(module).await
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let (client, _handle) = serve(lookups.clone()).await;
+        let registry = AssetRegistry::new(client);
+        assert!(registry.get(&asset_id(3)).await.unwrap().is_none());
+        assert!(registry.get(&asset_id(3)).await.unwrap().is_none());
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        let asset_data = registry.get(&asset_id(1)).await.unwrap().unwrap();
+        assert_eq!(asset_data.decimals, 2);
+        let clone = registry.clone();
+        assert_eq!(clone.expect(&asset_id(1)).await.unwrap(), asset_data);
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+        assert!(matches!(
+            registry.expect(&asset_id(3)).await,
+            Err(RpcWrapperError::NonExistentAsset)
+        ));
+
+        assert_eq!(registry.ids_by_ticker("TCK"), [asset_id(1)]);
+        registry.get(&asset_id(2)).await.unwrap();
+        assert_eq!(registry.ids_by_ticker("TCK"), [asset_id(1), asset_id(2)]);
+        assert!(registry.ids_by_ticker("OTHER").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_asset_balances() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let (client, _handle) = serve(lookups.clone()).await;
+        let registry = AssetRegistry::new(client);
+        let balances = registry.asset_balances(&Address::ZERO).await.unwrap();
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances[0].asset_id, asset_id(1));
+        assert_eq!(balances[0].amount, Amount::from_subunits(1250));
+        assert_eq!(balances[0].formatted, "12.5");
+        assert_eq!(balances[1].decimals, 6);
+        assert_eq!(balances[1].formatted, "0.5");
+        assert_eq!(
+            balances[1].token_amount().asset(),
+            Some(&balances[1].asset_id)
+        );
+
+        let balance = registry
+            .asset_balance(&Address::ZERO, &asset_id(1))
+            .await
+            .unwrap();
+        assert_eq!(balance, balances[0]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+}
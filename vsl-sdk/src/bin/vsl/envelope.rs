@@ -0,0 +1,208 @@
+//! The air-gapped signing workflow (see [vsl_sdk::envelope]):
+//!
+//! - online: any command sending a message, given `--unsigned FILE`, writes the
+//!   message to an unsigned envelope instead (signing with the `--from` account)
+//! - offline: `vsl envelope sign FILE --out SIGNED` signs it with the profile key
+//! - online: `vsl envelope broadcast SIGNED` sends it to the server
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Subcommand;
+use jsonrpsee::http_client::HttpClient;
+use serde::Serialize;
+use serde_json::json;
+use vsl_sdk::envelope::{
+    self, BroadcastReceipt, SignedEnvelope, UnsignedEnvelope, UnsignedMessage,
+};
+use vsl_sdk::rpc_wrapper::{self, RpcWrapperError};
+use vsl_sdk::{AmountParser, Clock, SystemClock, TokenAmount};
+
+use crate::profile::Profile;
+use crate::{Args, CliError, CliResult, Command, OutputFormat};
+
+#[derive(Subcommand, Debug)]
+pub enum EnvelopeCommand {
+    /// Signs an unsigned envelope with the profile key (no connection needed)
+    Sign {
+        /// The unsigned envelope
+        file: PathBuf,
+        /// Where to write the signed envelope (printed if not given)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Describes an (unsigned or signed) envelope and checks its consistency
+    Inspect { file: PathBuf },
+    /// Sends the message of a signed envelope to the server
+    Broadcast { file: PathBuf },
+}
+
+/// Either kind of envelope, as read from a file
+enum Envelope {
+    Unsigned(UnsignedEnvelope),
+    Signed(SignedEnvelope),
+}
+
+fn read_envelope(path: &Path) -> CliResult<Envelope> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| CliError(format!("cannot read {}: {e}", path.display())))?;
+    // signed envelopes have extra (signature) fields, so they are tried first
+    if let Ok(signed) = serde_json::from_str(&json) {
+        return Ok(Envelope::Signed(signed));
+    }
+    serde_json::from_str(&json)
+        .map(Envelope::Unsigned)
+        .map_err(|e| CliError(format!("{} is not an envelope: {e}", path.display())))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> CliResult<()> {
+    let json = serde_json::to_string_pretty(value).expect("envelopes are serializable");
+    std::fs::write(path, json + "\n")
+        .map_err(|e| CliError(format!("cannot write {}: {e}", path.display())))
+}
+
+impl EnvelopeCommand {
+    /// Runs the command, connecting to the server only for broadcasting
+    pub async fn run(&self, profile: &Profile, output: OutputFormat) -> CliResult<()> {
+        match self {
+            EnvelopeCommand::Sign { file, out } => {
+                let Envelope::Unsigned(unsigned) = read_envelope(file)? else {
+                    return Err(CliError(format!("{} is already signed", file.display())));
+                };
+                check_network(profile, &unsigned.network)?;
+                let signed = unsigned
+                    .sign(&profile.signer()?)
+                    .map_err(|e| CliError(e.to_string()))?;
+                match out {
+                    Some(out) => {
+                        write_json(out, &signed)?;
+                        print_envelope(output, &Envelope::Signed(signed));
+                    }
+                    None => println!("{}", serde_json::to_string_pretty(&signed).unwrap()),
+                }
+            }
+            EnvelopeCommand::Inspect { file } => print_envelope(output, &read_envelope(file)?),
+            EnvelopeCommand::Broadcast { file } => {
+                let Envelope::Signed(signed) = read_envelope(file)? else {
+                    return Err(CliError(format!("{} is not signed", file.display())));
+                };
+                check_network(profile, &signed.network)?;
+                let client = crate::http_client(profile)?;
+                let value = match envelope::broadcast(&client, &signed).await? {
+                    BroadcastReceipt::Claim(claim_id) => json!({ "claim_id": claim_id }),
+                    BroadcastReceipt::Asset { asset_id, claim_id } => {
+                        json!({ "asset_id": asset_id.to_string(), "claim_id": claim_id })
+                    }
+                };
+                output.print(&value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Refuses envelopes intended for a different network than the profile's
+fn check_network(profile: &Profile, network: &Option<String>) -> CliResult<()> {
+    match (&profile.network, network) {
+        (Some(expected), Some(found)) if expected != found => Err(CliError(format!(
+            "the envelope is intended for network {found}, not {expected}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn print_envelope(output: OutputFormat, envelope: &Envelope) {
+    match (output, envelope) {
+        (OutputFormat::Table, Envelope::Unsigned(unsigned)) => {
+            let status = match unsigned.verify() {
+                Ok(()) => String::new(),
+                Err(error) => format!(" (INVALID: {error})"),
+            };
+            println!("{unsigned}{status}");
+        }
+        (OutputFormat::Table, Envelope::Signed(signed)) => println!("{signed}"),
+        (OutputFormat::Json, Envelope::Unsigned(unsigned)) => {
+            let mut value = serde_json::to_value(unsigned).expect("serializable");
+            value["valid"] = json!(unsigned.verify().is_ok());
+            output.print(&value);
+        }
+        (OutputFormat::Json, Envelope::Signed(signed)) => {
+            let mut value = serde_json::to_value(signed).expect("serializable");
+            value["valid"] = json!(signed.verify().is_ok());
+            output.print(&value);
+        }
+    }
+}
+
+/// Writes the message the command would send to an unsigned envelope,
+/// to be signed by the `--from` account (the profile account by default)
+pub async fn create_unsigned(
+    args: &Args,
+    profile: &Profile,
+    client: &HttpClient,
+    path: &Path,
+) -> CliResult<()> {
+    let from = match args.from {
+        Some(from) => from,
+        None => profile.signer()?.address(),
+    };
+    let nonce = match args.nonce {
+        Some(nonce) => nonce,
+        None => rpc_wrapper::get_account_nonce(client, &from).await?,
+    };
+    let message = match &args.command {
+        Command::Pay { to, amount } => {
+            UnsignedMessage::pay(&from, nonce, to, &AmountParser::vsl().parse(amount)?)
+        }
+        Command::CreateAsset {
+            ticker_symbol,
+            decimals,
+            total_supply,
+        } => {
+            let total_supply =
+                AmountParser::for_asset(ticker_symbol, *decimals).parse(total_supply)?;
+            UnsignedMessage::create_asset(&from, nonce, ticker_symbol, *decimals, &total_supply)?
+        }
+        Command::TransferAsset {
+            asset_id,
+            to,
+            amount,
+        } => {
+            let Some(asset) = rpc_wrapper::get_asset_by_id(client, asset_id).await? else {
+                return Err(RpcWrapperError::NonExistentAsset.into());
+            };
+            let amount = TokenAmount::parse_asset(amount, *asset_id, &asset)?;
+            UnsignedMessage::transfer_asset(&from, nonce, asset_id, to, &amount.amount())
+        }
+        Command::SetState { state } => UnsignedMessage::set_state(&from, nonce, state),
+        Command::SubmitClaim {
+            claim,
+            claim_type,
+            proof,
+            verifiers,
+            quorum,
+            ttl,
+            fee,
+        } => UnsignedMessage::submit_claim(
+            &from,
+            nonce,
+            claim.clone(),
+            claim_type.clone(),
+            proof.clone(),
+            verifiers,
+            *quorum,
+            SystemClock.in_(Duration::from_secs(*ttl)),
+            &AmountParser::vsl().parse(fee)?,
+        ),
+        Command::SettleClaim { claim_id } => UnsignedMessage::settle_claim(&from, nonce, claim_id),
+        _ => {
+            return Err(CliError(
+                "--unsigned only applies to commands sending a message".to_string(),
+            ));
+        }
+    };
+    let unsigned = UnsignedEnvelope::new(message, profile.network.clone())
+        .map_err(|e| CliError(e.to_string()))?;
+    write_json(path, &unsigned)?;
+    print_envelope(args.output, &Envelope::Unsigned(unsigned));
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! Managing the private keys of accounts (stored as hex strings)
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use alloy::hex;
+use alloy::signers::local::PrivateKeySigner;
+use clap::Subcommand;
+use serde_json::{Value, json};
+
+use crate::profile::{Profile, expand_home, parse_private_key};
+use crate::{CliError, CliResult};
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCommand {
+    /// Generates a new random key (printed, unless saved with `--out`)
+    New {
+        /// The file to save the key to
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Saves an existing key (read from stdin if not given) to a file
+    Import {
+        /// The hex-encoded private key
+        private_key: Option<String>,
+        /// The file to save the key to
+        #[arg(long)]
+        out: PathBuf,
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Shows the address of the profile account
+    Address,
+}
+
+impl KeysCommand {
+    pub fn run(&self, profile: Profile) -> CliResult<Value> {
+        match self {
+            KeysCommand::New { out, force } => {
+                let signer = PrivateKeySigner::random();
+                match out {
+                    Some(out) => {
+                        save_key(&signer, out, *force)?;
+                        Ok(json!({ "address": signer.address(), "key_file": out }))
+                    }
+                    None => Ok(json!({
+                        "address": signer.address(),
+                        "private_key": hex::encode(signer.to_bytes()),
+                    })),
+                }
+            }
+            KeysCommand::Import {
+                private_key,
+                out,
+                force,
+            } => {
+                let private_key = match private_key {
+                    Some(private_key) => private_key.clone(),
+                    None => {
+                        let mut line = String::new();
+                        std::io::stdin().lock().read_line(&mut line)?;
+                        line
+                    }
+                };
+                let signer = parse_private_key(&private_key)?;
+                save_key(&signer, out, *force)?;
+                Ok(json!({ "address": signer.address(), "key_file": out }))
+            }
+            KeysCommand::Address => Ok(json!({ "address": profile.signer()?.address() })),
+        }
+    }
+}
+
+/// Writes the key to a new file, readable only by its owner (on Unix)
+fn save_key(signer: &PrivateKeySigner, path: &Path, force: bool) -> CliResult<()> {
+    let path = expand_home(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&path)
+        .map_err(|e| CliError(format!("cannot write key file {}: {e}", path.display())))?;
+    writeln!(file, "{}", hex::encode(signer.to_bytes()))?;
+    Ok(())
+}
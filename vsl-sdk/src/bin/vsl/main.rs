@@ -0,0 +1,545 @@
+//! `vsl`: a command-line client for the VSL RPC server, built on [vsl_sdk::rpc_wrapper]
+//!
+//! The endpoint and the account key are taken from a profile (see [profile]),
+//! and can be overridden by the `--endpoint` and `--key-file` options.
+mod envelope;
+mod keys;
+mod output;
+mod profile;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use alloy::consensus::Signed;
+use alloy_rlp::Encodable;
+use clap::{Parser, Subcommand, ValueEnum};
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::ws_client::WsClientBuilder;
+use serde::Serialize;
+use serde_json::{Value, json};
+use vsl_sdk::asset_registry::AssetRegistry;
+use vsl_sdk::payout::{Manifest, Payout, PayoutError, PayoutStatus};
+use vsl_sdk::rpc_messages::{AccountStateHash, Timestamped};
+use vsl_sdk::rpc_wrapper::{self, RpcWrapper, RpcWrapperError};
+use vsl_sdk::{
+    Address, AmountFormat, AmountParser, AssetId, B256, HasSender, ParseAmountError, Timestamp,
+    TokenAmount,
+};
+
+use crate::envelope::EnvelopeCommand;
+use crate::keys::KeysCommand;
+use crate::output::OutputFormat;
+use crate::profile::{KeySource, Profile};
+
+/// Command-line client for the Verifiable Settlement Layer
+#[derive(Parser, Debug)]
+#[command(name = "vsl", version, about, long_about = None)]
+struct Args {
+    /// Path to the configuration file holding the profiles [default: ~/.vsl/config.yml]
+    #[arg(long, global = true, env = "VSL_CONFIG")]
+    config: Option<PathBuf>,
+    /// The profile to use (the `default_profile` of the configuration by default)
+    #[arg(long, short, global = true, env = "VSL_PROFILE")]
+    profile: Option<String>,
+    /// The address of the VSL server, as `IP:PORT` or as a URL (overrides the profile)
+    #[arg(long, global = true, env = "VSL_ENDPOINT")]
+    endpoint: Option<String>,
+    /// A file containing the private key of the account (overrides the profile)
+    #[arg(long, global = true)]
+    key_file: Option<PathBuf>,
+    /// How to print the results
+    #[arg(long, short, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
+    /// Write the message to an unsigned envelope instead of signing and sending it
+    #[arg(long, global = true, value_name = "FILE")]
+    unsigned: Option<PathBuf>,
+    /// The account to sign the unsigned envelope (the profile account by default)
+    #[arg(long, global = true, requires = "unsigned")]
+    from: Option<Address>,
+    /// The nonce of the unsigned envelope (the current account nonce by default)
+    #[arg(long, global = true, requires = "unsigned")]
+    nonce: Option<u64>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generates, imports or shows account keys
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Signs messages offline: creates, signs, inspects or broadcasts envelopes
+    #[command(subcommand)]
+    Envelope(EnvelopeCommand),
+    /// Shows the VSL balance of an account (the profile account by default)
+    Balance { address: Option<Address> },
+    /// Shows the asset balances of an account (the profile account by default)
+    AssetBalances { address: Option<Address> },
+    /// Shows the nonce of an account (the profile account by default)
+    Nonce { address: Option<Address> },
+    /// Shows the state hash of an account (the profile account by default)
+    State { address: Option<Address> },
+    /// Pays VSL tokens (e.g. `1.5`, `1.5 VSL` or `1500 atto`) to an account
+    Pay { to: Address, amount: String },
+    /// Creates a new asset, owned by the profile account
+    CreateAsset {
+        ticker_symbol: String,
+        decimals: u8,
+        /// The total supply, in tokens (e.g. `1000000` or `1000000.5`)
+        total_supply: String,
+    },
+    /// Transfers an amount of an asset (in tokens, e.g. `12.5`) to an account
+    TransferAsset {
+        asset_id: AssetId,
+        to: Address,
+        amount: String,
+    },
+    /// Sets the state hash of the profile account
+    SetState { state: AccountStateHash },
+    /// Submits a claim for verification
+    SubmitClaim {
+        /// The claim to be verified
+        #[arg(long)]
+        claim: String,
+        /// The claim type
+        #[arg(long)]
+        claim_type: String,
+        /// The proof of the claim
+        #[arg(long, default_value = "")]
+        proof: String,
+        /// A verifier to receive the claim (can be repeated)
+        #[arg(long = "to", required = true)]
+        verifiers: Vec<Address>,
+        /// The minimum quorum of verifier signatures
+        #[arg(long, default_value_t = 1)]
+        quorum: u16,
+        /// Seconds after which the claim is dropped if not enough verifications are received
+        #[arg(long, default_value_t = 3600)]
+        ttl: u64,
+        /// The total fee for verification and validation, in VSL tokens
+        #[arg(long)]
+        fee: String,
+    },
+    /// Settles a claim, as one of its verifiers
+    SettleClaim { claim_id: B256 },
+    /// Shows a submitted (or, with `--settled`, a settled) claim
+    GetClaim {
+        claim_id: B256,
+        #[arg(long)]
+        settled: bool,
+    },
+    /// Shows the proof of a submitted claim
+    GetProof { claim_id: B256 },
+    /// Lists recent claims (at most 64)
+    List {
+        kind: ClaimKind,
+        /// Only claims sent by this account
+        #[arg(long, conflicts_with = "receiver")]
+        sender: Option<Address>,
+        /// Only claims received by this account
+        #[arg(long)]
+        receiver: Option<Address>,
+        /// Only claims recorded since this time (RFC 3339 or decimal seconds)
+        #[arg(long, value_parser = parse_timestamp, default_value = "0")]
+        since: Timestamp,
+    },
+    /// Pays the rows of a CSV (`address,amount[,asset]`) or JSON manifest
+    Payout {
+        /// The manifest (read as JSON if its extension is `.json`, as CSV otherwise)
+        manifest: PathBuf,
+        /// The receipt file mapping each row to its claim ID (the payout is resumed
+        /// from it if it exists)
+        #[arg(long)]
+        receipts: PathBuf,
+        /// The validation fee charged for each payment, in VSL tokens
+        #[arg(long)]
+        validation_fee: String,
+        /// Only validates the manifest and checks the balances, without paying
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prints claims as they are recorded, until interrupted
+    Subscribe {
+        kind: ClaimKind,
+        /// Only claims received by this account
+        #[arg(long)]
+        receiver: Option<Address>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ClaimKind {
+    /// Claims submitted for verification
+    Submitted,
+    /// Verified and settled claims
+    Settled,
+}
+
+#[derive(Debug)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<RpcWrapperError> for CliError {
+    fn from(value: RpcWrapperError) -> Self {
+        Self(format!("{value:?}"))
+    }
+}
+
+impl From<ParseAmountError> for CliError {
+    fn from(value: ParseAmountError) -> Self {
+        Self(format!("invalid amount: {value}"))
+    }
+}
+
+impl From<PayoutError> for CliError {
+    fn from(value: PayoutError) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(value: std::io::Error) -> Self {
+        Self(value.to_string())
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+fn parse_timestamp(s: &str) -> Result<Timestamp, String> {
+    Timestamp::from_rfc3339(s)
+        .or_else(|_| Timestamp::from_decimal_str(s))
+        .map_err(|_| "expected an RFC 3339 date-time or decimal seconds".to_string())
+}
+
+impl Args {
+    fn profile(&self) -> CliResult<Profile> {
+        let config_path = self
+            .config
+            .clone()
+            .unwrap_or_else(profile::default_config_path);
+        let mut profile = profile::load_profile(&config_path, self.profile.as_deref())?;
+        if let Some(endpoint) = &self.endpoint {
+            profile.endpoint = Some(endpoint.clone());
+        }
+        if let Some(key_file) = &self.key_file {
+            profile.key = Some(KeySource::File(key_file.clone()));
+        }
+        Ok(profile)
+    }
+}
+
+fn http_client(profile: &Profile) -> CliResult<HttpClient> {
+    let url = profile.http_url()?;
+    HttpClientBuilder::new()
+        .build(&url)
+        .map_err(|e| CliError(format!("cannot connect to {url}: {e}")))
+}
+
+async fn account(profile: &Profile, client: &HttpClient) -> CliResult<RpcWrapper<HttpClient>> {
+    let account = RpcWrapper::from_signer(profile.signer()?, None, client).await?;
+    Ok(match &profile.network {
+        Some(network) => account.with_network(network),
+        None => account,
+    })
+}
+
+/// The given address, or the address of the profile account
+fn address_or_own(address: Option<Address>, profile: &Profile) -> CliResult<Address> {
+    match address {
+        Some(address) => Ok(address),
+        None => Ok(profile.signer()?.address()),
+    }
+}
+
+/// A timestamped item as a flat JSON object: its id, timestamp and the fields of `data`
+fn row<T: Serialize>(id: &str, timestamp: &Timestamp, data: &T) -> Value {
+    let mut row = json!({ "id": id, "timestamp": timestamp.to_rfc3339() });
+    if let (Value::Object(row), Ok(Value::Object(fields))) = (&mut row, serde_json::to_value(data))
+    {
+        row.extend(fields);
+    }
+    row
+}
+
+/// Same as [row], adding the address recovered from the signature
+fn signed_row<T: Serialize + Encodable>(item: &Timestamped<Signed<T>>) -> Value {
+    let mut row = row(&item.id, &item.timestamp, item.data.tx());
+    row["signer"] = json!(item.data.sender());
+    row
+}
+
+fn rows<T>(items: &[T], row: impl Fn(&T) -> Value) -> Value {
+    Value::Array(items.iter().map(row).collect())
+}
+
+async fn run(args: &Args) -> CliResult<()> {
+    if let Command::Keys(command) = &args.command {
+        let value = command.run(args.profile()?)?;
+        args.output.print(&value);
+        return Ok(());
+    }
+    let profile = args.profile()?;
+    if let Command::Envelope(command) = &args.command {
+        return command.run(&profile, args.output).await;
+    }
+    let client = http_client(&profile)?;
+    if let Some(path) = &args.unsigned {
+        return envelope::create_unsigned(args, &profile, &client, path).await;
+    }
+    let value = match &args.command {
+        Command::Keys(_) | Command::Envelope(_) => unreachable!("handled above"),
+        Command::Balance { address } => {
+            let address = address_or_own(*address, &profile)?;
+            let balance = rpc_wrapper::get_balance(&client, &address).await?;
+            json!({
+                "address": address,
+                "balance": AmountFormat::vsl().format(&balance),
+                "subunits": balance.to_string(),
+            })
+        }
+        Command::AssetBalances { address } => {
+            let address = address_or_own(*address, &profile)?;
+            let balances = AssetRegistry::new(client.clone())
+                .asset_balances(&address)
+                .await?;
+            rows(&balances, |balance| {
+                json!({
+                    "asset_id": balance.asset_id.to_string(),
+                    "ticker": balance.ticker,
+                    "balance": balance.formatted,
+                    "subunits": balance.amount.to_string(),
+                })
+            })
+        }
+        Command::Nonce { address } => {
+            let address = address_or_own(*address, &profile)?;
+            let nonce = rpc_wrapper::get_account_nonce(&client, &address).await?;
+            json!({ "address": address, "nonce": nonce })
+        }
+        Command::State { address } => {
+            let address = address_or_own(*address, &profile)?;
+            let state = rpc_wrapper::get_account_state(&client, &address).await?;
+            json!({ "address": address, "state": state.map(|state| state.to_string()) })
+        }
+        Command::Pay { to, amount } => {
+            let amount = AmountParser::vsl().parse(amount)?;
+            let claim_id = account(&profile, &client).await?.pay(to, &amount).await?;
+            json!({ "claim_id": claim_id })
+        }
+        Command::CreateAsset {
+            ticker_symbol,
+            decimals,
+            total_supply,
+        } => {
+            let total_supply =
+                AmountParser::for_asset(ticker_symbol, *decimals).parse(total_supply)?;
+            let (asset_id, claim_id) = account(&profile, &client)
+                .await?
+                .create_asset(ticker_symbol, *decimals, &total_supply)
+                .await?;
+            json!({ "asset_id": asset_id.to_string(), "claim_id": claim_id })
+        }
+        Command::TransferAsset {
+            asset_id,
+            to,
+            amount,
+        } => {
+            let Some(asset) = rpc_wrapper::get_asset_by_id(&client, asset_id).await? else {
+                return Err(RpcWrapperError::NonExistentAsset.into());
+            };
+            let amount = TokenAmount::parse_asset(amount, *asset_id, &asset)?;
+            let claim_id = account(&profile, &client)
+                .await?
+                .transfer_tokens(to, &amount)
+                .await?;
+            json!({ "claim_id": claim_id })
+        }
+        Command::SetState { state } => {
+            let claim_id = account(&profile, &client)
+                .await?
+                .set_account_state(state)
+                .await?;
+            json!({ "claim_id": claim_id })
+        }
+        Command::SubmitClaim {
+            claim,
+            claim_type,
+            proof,
+            verifiers,
+            quorum,
+            ttl,
+            fee,
+        } => {
+            let fee = AmountParser::vsl().parse(fee)?;
+            let mut account = account(&profile, &client).await?;
+            let expires = account.expires_in(Duration::from_secs(*ttl));
+            let claim_id = account
+                .submit_claim(
+                    claim.clone(),
+                    claim_type.clone(),
+                    proof.clone(),
+                    verifiers.iter().collect(),
+                    *quorum,
+                    expires,
+                    fee,
+                )
+                .await?;
+            json!({ "claim_id": claim_id, "expires": expires.to_rfc3339() })
+        }
+        Command::SettleClaim { claim_id } => {
+            account(&profile, &client)
+                .await?
+                .settle_claim(claim_id)
+                .await?;
+            json!({ "claim_id": claim_id })
+        }
+        Command::GetClaim { claim_id, settled } => {
+            if *settled {
+                signed_row(&rpc_wrapper::get_settled_claim_by_id(&client, claim_id).await?)
+            } else {
+                signed_row(&rpc_wrapper::get_submitted_claim_by_id(&client, claim_id).await?)
+            }
+        }
+        Command::GetProof { claim_id } => {
+            let proof = rpc_wrapper::get_proof_by_id(&client, claim_id).await?;
+            json!({ "claim_id": claim_id, "proof": proof })
+        }
+        Command::List {
+            kind,
+            sender,
+            receiver,
+            since,
+        } => match (kind, sender, receiver) {
+            (ClaimKind::Submitted, None, None) => {
+                let items = rpc_wrapper::list_submitted_claims_metadata(&client, since).await?;
+                rows(&items, |item| row(&item.id, &item.timestamp, &item.data))
+            }
+            (ClaimKind::Submitted, Some(sender), _) => {
+                let items =
+                    rpc_wrapper::list_submitted_claims_for_sender(&client, Some(sender), since)
+                        .await?;
+                rows(&items, signed_row)
+            }
+            (ClaimKind::Submitted, None, Some(receiver)) => {
+                let items =
+                    rpc_wrapper::list_submitted_claims_for_receiver(&client, receiver, since)
+                        .await?;
+                rows(&items, signed_row)
+            }
+            (ClaimKind::Settled, None, None) => {
+                let items = rpc_wrapper::list_settled_claims_metadata(&client, since).await?;
+                rows(&items, |item| row(&item.id, &item.timestamp, &item.data))
+            }
+            (ClaimKind::Settled, Some(sender), _) => {
+                let items =
+                    rpc_wrapper::list_settled_claims_for_sender(&client, sender, since).await?;
+                rows(&items, signed_row)
+            }
+            (ClaimKind::Settled, None, Some(receiver)) => {
+                let items =
+                    rpc_wrapper::list_settled_claims_for_receiver(&client, Some(receiver), since)
+                        .await?;
+                rows(&items, signed_row)
+            }
+        },
+        Command::Payout {
+            manifest,
+            receipts,
+            validation_fee,
+            dry_run,
+        } => {
+            let validation_fee = AmountParser::vsl().parse(validation_fee)?;
+            let manifest = Manifest::read(manifest)?;
+            let mut payout = Payout::prepare(&client, &manifest, validation_fee).await?;
+            if let Some(network) = &profile.network {
+                payout = payout.with_network(network);
+            }
+            payout.resume(receipts)?;
+            let signer = profile.signer()?;
+            if *dry_run {
+                payout.check_balance(&client, &signer.address()).await?;
+            } else {
+                payout.run(&client, &signer, receipts).await?;
+            }
+            rows(payout.receipts(), |receipt| {
+                let (status, claim_id, error) = match &receipt.status {
+                    PayoutStatus::Unsent => ("unsent", None, None),
+                    PayoutStatus::Sent { .. } => ("sent", None, None),
+                    PayoutStatus::Settled { claim_id } => ("settled", Some(claim_id), None),
+                    PayoutStatus::Failed { error } => ("failed", None, Some(error)),
+                };
+                json!({
+                    "row": receipt.row,
+                    "to": receipt.to,
+                    "amount": receipt.amount.to_string(),
+                    "asset": receipt.amount.asset().map(|asset| asset.to_string()),
+                    "status": status,
+                    "claim_id": claim_id,
+                    "error": error,
+                })
+            })
+        }
+        Command::Subscribe { kind, receiver } => {
+            return subscribe(args.output, &profile, *kind, receiver.as_ref()).await;
+        }
+    };
+    args.output.print(&value);
+    Ok(())
+}
+
+async fn subscribe(
+    output: OutputFormat,
+    profile: &Profile,
+    kind: ClaimKind,
+    receiver: Option<&Address>,
+) -> CliResult<()> {
+    let url = profile.ws_url()?;
+    let ws_client = WsClientBuilder::new()
+        .build(&url)
+        .await
+        .map_err(|e| CliError(format!("cannot connect to {url}: {e}")))?;
+    // prints each notification until the subscription is closed
+    macro_rules! print_all {
+        ($subscription:expr, $row:expr) => {{
+            let mut subscription = $subscription.await?;
+            while let Some(item) = subscription.next().await {
+                let item = item.map_err(|e| CliError(format!("invalid notification: {e}")))?;
+                output.print_item(&$row(&item));
+            }
+        }};
+    }
+    match (kind, receiver) {
+        (ClaimKind::Submitted, None) => print_all!(
+            rpc_wrapper::subscribe_to_submitted_claims_metadata(&ws_client),
+            |item: &Timestamped<_>| row(&item.id, &item.timestamp, &item.data)
+        ),
+        (ClaimKind::Submitted, Some(receiver)) => print_all!(
+            rpc_wrapper::subscribe_to_submitted_claims_for_receiver(&ws_client, receiver),
+            signed_row
+        ),
+        (ClaimKind::Settled, None) => print_all!(
+            rpc_wrapper::subscribe_to_settled_claims_metadata(&ws_client),
+            |item: &Timestamped<_>| row(&item.id, &item.timestamp, &item.data)
+        ),
+        (ClaimKind::Settled, Some(receiver)) => print_all!(
+            rpc_wrapper::subscribe_to_settled_claims_for_receiver(&ws_client, Some(receiver)),
+            signed_row
+        ),
+    }
+    Err(CliError("subscription closed by the server".to_string()))
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args = Args::parse();
+    if let Err(error) = run(&args).await {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
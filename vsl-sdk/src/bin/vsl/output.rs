@@ -0,0 +1,94 @@
+//! Printing command results as JSON or as (plain text) tables.
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns (objects are printed as key/value pairs)
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+}
+
+impl OutputFormat {
+    pub fn print(&self, value: &Value) {
+        match self {
+            OutputFormat::Json => println!("{value:#}"),
+            OutputFormat::Table => print_table(value),
+        }
+    }
+
+    /// Prints one item of a stream (e.g., a subscription), as soon as it arrives
+    pub fn print_item(&self, value: &Value) {
+        match self {
+            OutputFormat::Json => println!("{value}"),
+            OutputFormat::Table => {
+                print_table(value);
+                println!();
+            }
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn print_table(value: &Value) {
+    match value {
+        Value::Object(record) => print_record(record),
+        Value::Array(rows) if rows.iter().all(Value::is_object) => print_rows(rows),
+        Value::Array(items) => items.iter().for_each(|item| println!("{}", cell(item))),
+        other => println!("{}", cell(other)),
+    }
+}
+
+fn print_record(record: &Map<String, Value>) {
+    let width = record.keys().map(String::len).max().unwrap_or_default();
+    for (key, value) in record {
+        println!("{key:<width$}  {}", cell(value));
+    }
+}
+
+fn print_rows(rows: &[Value]) {
+    let Some(Value::Object(first)) = rows.first() else {
+        return;
+    };
+    let columns: Vec<&String> = first.keys().collect();
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| cell(row.get(column.as_str()).unwrap_or(&Value::Null)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].len())
+                .chain([column.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |values: Vec<String>| {
+        let padded: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:<width$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(columns.iter().map(|column| column.to_uppercase()).collect());
+    cells.into_iter().for_each(line);
+}
@@ -0,0 +1,198 @@
+//! Profiles select the VSL endpoint and the account key used by the commands.
+//!
+//! They are read from a configuration file (YAML, TOML or JSON, by extension):
+//!
+//! ```yaml
+//! default_profile: local
+//! profiles:
+//!   local:
+//!     endpoint: 127.0.0.1:44444
+//!     key:
+//!       file: ~/.vsl/local.key
+//!   devnet:
+//!     network: vsl-devnet
+//!     endpoint: https://devnet.example.com
+//!     key:
+//!       env: VSL_DEVNET_KEY
+//! ```
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use alloy::signers::local::PrivateKeySigner;
+use config::Config;
+use serde::Deserialize;
+
+use crate::CliError;
+
+/// Where to read the private key (hex-encoded) of the account from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// A file containing the private key
+    File(PathBuf),
+    /// An environment variable containing the private key
+    Env(String),
+    /// The private key itself (not recommended outside of local testing)
+    PrivateKey(String),
+}
+
+impl KeySource {
+    pub fn load(&self) -> Result<PrivateKeySigner, CliError> {
+        let private_key = match self {
+            KeySource::File(path) => std::fs::read_to_string(expand_home(path))
+                .map_err(|e| CliError(format!("cannot read key file {}: {e}", path.display())))?,
+            KeySource::Env(var) => std::env::var(var)
+                .map_err(|_| CliError(format!("environment variable {var} is not set")))?,
+            KeySource::PrivateKey(private_key) => private_key.clone(),
+        };
+        parse_private_key(&private_key)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Profile {
+    /// The address of the VSL server, either as `IP:PORT` or as a URL
+    pub endpoint: Option<String>,
+    /// The key of the account acting on behalf of the user
+    pub key: Option<KeySource>,
+    /// The name of the network, recorded in (and checked against) envelopes
+    pub network: Option<String>,
+}
+
+impl Profile {
+    pub fn endpoint(&self) -> Result<&str, CliError> {
+        self.endpoint
+            .as_deref()
+            .ok_or_else(|| CliError("no endpoint configured (use --endpoint)".to_string()))
+    }
+
+    pub fn http_url(&self) -> Result<String, CliError> {
+        let endpoint = self.endpoint()?;
+        Ok(if endpoint.contains("://") {
+            endpoint.to_string()
+        } else {
+            format!("http://{endpoint}")
+        })
+    }
+
+    pub fn ws_url(&self) -> Result<String, CliError> {
+        let http_url = self.http_url()?;
+        Ok(match http_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{rest}"),
+            Some(("http", rest)) => format!("ws://{rest}"),
+            _ => http_url,
+        })
+    }
+
+    pub fn signer(&self) -> Result<PrivateKeySigner, CliError> {
+        self.key
+            .as_ref()
+            .ok_or_else(|| CliError("no key configured (use --key-file)".to_string()))?
+            .load()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Settings {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+/// The default location of the configuration file: `~/.vsl/config.yml`
+pub fn default_config_path() -> PathBuf {
+    expand_home(Path::new("~/.vsl/config.yml"))
+}
+
+/// Loads the named (or the default) profile from the configuration file.
+///
+/// A missing configuration file yields an empty profile, unless a profile was named.
+pub fn load_profile(config_path: &Path, name: Option<&str>) -> Result<Profile, CliError> {
+    if !config_path.exists() {
+        return match name {
+            Some(name) => Err(CliError(format!(
+                "cannot load profile {name}: {} does not exist",
+                config_path.display()
+            ))),
+            None => Ok(Profile::default()),
+        };
+    }
+    let settings: Settings = Config::builder()
+        .add_source(config::File::from(config_path))
+        .build()
+        .and_then(Config::try_deserialize)
+        .map_err(|e| CliError(format!("invalid config {}: {e}", config_path.display())))?;
+    let Some(name) = name.or(settings.default_profile.as_deref()) else {
+        return Ok(Profile::default());
+    };
+    settings
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CliError(format!("unknown profile {name}")))
+}
+
+pub fn parse_private_key(private_key: &str) -> Result<PrivateKeySigner, CliError> {
+    PrivateKeySigner::from_str(private_key.trim())
+        .map_err(|e| CliError(format!("invalid private key: {e}")))
+}
+
+/// Replaces a leading `~` with the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(endpoint: &str) -> Profile {
+        Profile {
+            endpoint: Some(endpoint.to_string()),
+            key: None,
+            network: None,
+        }
+    }
+
+    #[test]
+    fn test_endpoint_urls() {
+        let local = profile("127.0.0.1:44444");
+        assert_eq!(local.http_url().unwrap(), "http://127.0.0.1:44444");
+        assert_eq!(local.ws_url().unwrap(), "ws://127.0.0.1:44444");
+        let remote = profile("https://vsl.example.com/rpc");
+        assert_eq!(remote.http_url().unwrap(), "https://vsl.example.com/rpc");
+        assert_eq!(remote.ws_url().unwrap(), "wss://vsl.example.com/rpc");
+        assert!(Profile::default().http_url().is_err());
+    }
+
+    #[test]
+    fn test_load_profile() {
+        let dir = std::env::temp_dir().join(format!("vsl-cli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.yml");
+        std::fs::write(
+            &config_path,
+            "default_profile: local\n\
+             profiles:\n  \
+               local:\n    \
+                 endpoint: 127.0.0.1:44444\n    \
+                 key:\n      \
+                   private_key: 79eb4ee7c5061998e04cae9859485b51ba37e1865d11454c404991eea58acabf\n  \
+               remote:\n    \
+                 endpoint: vsl.example.com:443\n",
+        )
+        .unwrap();
+        let local = load_profile(&config_path, None).unwrap();
+        assert_eq!(local.endpoint.as_deref(), Some("127.0.0.1:44444"));
+        assert!(local.signer().is_ok());
+        let remote = load_profile(&config_path, Some("remote")).unwrap();
+        assert!(remote.signer().is_err());
+        assert!(load_profile(&config_path, Some("unknown")).is_err());
+        assert!(load_profile(&dir.join("missing.yml"), None).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+//! # Claim Cursor Module
+//!
+//! This module provides [ClaimCursor], a position in a sequence of claims
+//! ordered by timestamp and then by claim ID. Its fixed-width key encoding
+//! preserves that order in byte-ordered key-value stores (e.g. sled), so that
+//! local indexes can range-scan claims by time:
+//!
+//! ```
+//! use vsl_sdk::{ClaimCursor, Timestamp};
+//!
+//! let from = ClaimCursor::first_at(Timestamp::from_seconds(100)).to_key_bytes();
+//! let to = ClaimCursor::last_at(Timestamp::from_seconds(200)).to_key_bytes();
+//! // e.g. `db.range(from..=to)` visits the claims timestamped within [100, 200]
+//! assert!(from < to);
+//! ```
+//!
+use std::str::FromStr;
+
+use crate::rpc_messages::Timestamped;
+use crate::timestamp::InvalidKey;
+use crate::{B256, Timestamp};
+
+/// A claim position, ordered by timestamp and then by claim ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClaimCursor {
+    pub timestamp: Timestamp,
+    pub claim_id: B256,
+}
+
+impl ClaimCursor {
+    /// Length of the [ClaimCursor::to_key_bytes] encoding
+    pub const KEY_LEN: usize = Timestamp::KEY_LEN + 32;
+
+    pub fn new(timestamp: Timestamp, claim_id: B256) -> Self {
+        ClaimCursor {
+            timestamp,
+            claim_id,
+        }
+    }
+
+    /// The cursor of a timestamped claim, or `None` if its ID is not a valid claim ID
+    pub fn of<T>(claim: &Timestamped<T>) -> Option<Self> {
+        let claim_id = B256::from_str(&claim.id).ok()?;
+        Some(Self::new(claim.timestamp, claim_id))
+    }
+
+    /// The smallest cursor with the given timestamp
+    pub fn first_at(timestamp: Timestamp) -> Self {
+        Self::new(timestamp, B256::ZERO)
+    }
+
+    /// The largest cursor with the given timestamp
+    pub fn last_at(timestamp: Timestamp) -> Self {
+        Self::new(timestamp, B256::repeat_byte(0xff))
+    }
+
+    /// Encodes the timestamp key (see [Timestamp::to_key_bytes]) followed by the
+    /// claim ID, so that byte-wise comparison of encodings matches cursor ordering
+    pub fn to_key_bytes(&self) -> [u8; Self::KEY_LEN] {
+        let mut bytes = [0u8; Self::KEY_LEN];
+        bytes[..Timestamp::KEY_LEN].copy_from_slice(&self.timestamp.to_key_bytes());
+        bytes[Timestamp::KEY_LEN..].copy_from_slice(self.claim_id.as_slice());
+        bytes
+    }
+
+    /// Decodes a cursor encoded by [ClaimCursor::to_key_bytes]
+    pub fn from_key_bytes(bytes: &[u8]) -> Result<Self, InvalidKey> {
+        if bytes.len() != Self::KEY_LEN {
+            return Err(InvalidKey);
+        }
+        let (timestamp, claim_id) = bytes.split_at(Timestamp::KEY_LEN);
+        Ok(Self::new(
+            Timestamp::from_key_bytes(timestamp)?,
+            B256::from_slice(claim_id),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_order_matches_cursor_order() {
+        let id = |byte: u8| B256::repeat_byte(byte);
+        let mut cursors = [
+            ClaimCursor::new(Timestamp::from_parts(2, 0), id(0x00)),
+            ClaimCursor::new(Timestamp::from_parts(1, 5), id(0xff)),
+            ClaimCursor::new(Timestamp::from_parts(1, 5), id(0x01)),
+            ClaimCursor::new(Timestamp::from_parts(1, 0), id(0xaa)),
+            ClaimCursor::new(Timestamp::from_parts(256, 0), id(0x10)),
+        ];
+        cursors.sort();
+        let keys: Vec<_> = cursors.iter().map(ClaimCursor::to_key_bytes).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        for (cursor, key) in cursors.iter().zip(&keys) {
+            assert_eq!(ClaimCursor::from_key_bytes(key).unwrap(), *cursor);
+        }
+    }
+
+    #[test]
+    fn test_time_range_bounds() {
+        let ts = Timestamp::from_parts(7, 3);
+        let cursor = ClaimCursor::new(ts, B256::repeat_byte(0x42));
+        assert!(ClaimCursor::first_at(ts) <= cursor && cursor <= ClaimCursor::last_at(ts));
+        assert!(ClaimCursor::last_at(Timestamp::from_parts(7, 2)) < ClaimCursor::first_at(ts));
+    }
+
+    #[test]
+    fn test_from_timestamped() {
+        let claim = Timestamped {
+            id: B256::repeat_byte(1).to_string(),
+            data: (),
+            timestamp: Timestamp::from_seconds(9),
+        };
+        let cursor = ClaimCursor::of(&claim).unwrap();
+        assert_eq!(cursor.claim_id, B256::repeat_byte(1));
+        assert_eq!(cursor.timestamp, claim.timestamp);
+        let invalid = Timestamped {
+            id: "not an id".to_string(),
+            ..claim
+        };
+        assert_eq!(ClaimCursor::of(&invalid), None);
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert_eq!(ClaimCursor::from_key_bytes(&[0; 43]), Err(InvalidKey));
+        let mut key = ClaimCursor::first_at(Timestamp::from_seconds(1)).to_key_bytes();
+        key[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(ClaimCursor::from_key_bytes(&key), Err(InvalidKey));
+    }
+}
@@ -0,0 +1,478 @@
+//! # Claim Stream Module
+//!
+//! Adapters over streams of submitted claims: subscriptions (e.g.
+//! [subscribe_to_submitted_claims_for_receiver]) or listed pages turned into
+//! streams (with [futures_util::stream::iter]). Each adapter drops the claims not
+//! passing its check, and reports each of them to a callback with the reason:
+//!
+//! - [ClaimStreamExt::verified] checks the signatures (see
+//!   [IntoSigned::check_and_strip_signature]), and strips them;
+//! - [ClaimStreamExt::claim_type], [ClaimStreamExt::from] and
+//!   [ClaimStreamExt::min_fee] keep claims of a type, from a sender, or paying at
+//!   least a fee;
+//! - [ClaimStreamExt::not_expired] keeps claims not yet expired by a [Clock];
+//! - [ClaimStreamExt::dedup_by_id] keeps the first occurrence of each claim,
+//!   remembering a bounded number of claim IDs.
+//!
+//! Items which are errors (e.g., undecodable notifications) are passed through.
+//!
+//! ```no_run
+//! # async fn run() -> vsl_sdk::rpc_wrapper::RpcWrapperResult<()> {
+//! use futures_util::StreamExt;
+//! use jsonrpsee::ws_client::WsClientBuilder;
+//! use vsl_sdk::claim_stream::ClaimStreamExt;
+//! use vsl_sdk::rpc_wrapper::subscribe_to_submitted_claims_for_receiver;
+//! use vsl_sdk::{Address, Amount, SystemClock};
+//!
+//! let client = WsClientBuilder::new().build("ws://127.0.0.1:44444").await.unwrap();
+//! let verifier = Address::repeat_byte(0x01);
+//! let log = |dropped| eprintln!("dropped {dropped}");
+//! let mut claims = subscribe_to_submitted_claims_for_receiver(&client, &verifier)
+//!     .await?
+//!     .verified(log)
+//!     .claim_type("pow", log)
+//!     .min_fee(Amount::from_vsl_tokens(1), log)
+//!     .not_expired(SystemClock, log)
+//!     .dedup_by_id(1024, log);
+//! while let Some(claim) = claims.next().await {
+//!     println!("{:?}", claim);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [subscribe_to_submitted_claims_for_receiver]: crate::rpc_wrapper::subscribe_to_submitted_claims_for_receiver
+//!
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use alloy::consensus::Signed;
+use futures_util::Stream;
+
+use crate::rpc_messages::{SubmittedClaim, Timestamped};
+use crate::{Address, Amount, Clock, IntoSigned, Timestamp, VerificationError};
+
+/// Why a claim was dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DropReason {
+    /// The signature is not valid
+    InvalidSignature(VerificationError),
+    /// The claim has another type
+    ClaimType(String),
+    /// The claim is from another (or an invalid) sender
+    Sender(String),
+    /// The fee is lower than the minimum (or not a valid amount)
+    Fee(String),
+    /// The claim expired at the given time
+    Expired(Timestamp),
+    /// The claim was already seen
+    Duplicate,
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DropReason::InvalidSignature(error) => write!(f, "invalid signature: {error}"),
+            DropReason::ClaimType(claim_type) => write!(f, "claim type {claim_type}"),
+            DropReason::Sender(sender) => write!(f, "sender {sender}"),
+            DropReason::Fee(fee) => write!(f, "fee {fee}"),
+            DropReason::Expired(expires) => write!(f, "expired at {expires}"),
+            DropReason::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+/// A claim dropped by an adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dropped {
+    pub claim_id: String,
+    pub reason: DropReason,
+}
+
+impl fmt::Display for Dropped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "claim {} ({})", self.claim_id, self.reason)
+    }
+}
+
+/// An item of a stream of submitted claims, signed or not
+pub trait ClaimItem {
+    /// The ID and the claim of the item, or `None` if the item is an error
+    fn claim(&self) -> Option<(&str, &SubmittedClaim)>;
+}
+
+impl ClaimItem for Timestamped<Signed<SubmittedClaim>> {
+    fn claim(&self) -> Option<(&str, &SubmittedClaim)> {
+        Some((&self.id, self.data.tx()))
+    }
+}
+
+impl ClaimItem for Timestamped<SubmittedClaim> {
+    fn claim(&self) -> Option<(&str, &SubmittedClaim)> {
+        Some((&self.id, &self.data))
+    }
+}
+
+impl<T: ClaimItem, E> ClaimItem for Result<T, E> {
+    fn claim(&self) -> Option<(&str, &SubmittedClaim)> {
+        self.as_ref().ok()?.claim()
+    }
+}
+
+/// An item holding a signed claim
+pub trait SignedClaimItem {
+    /// The item with the claim stripped of its signature
+    type Verified;
+
+    /// Checks the signature of the claim, and strips it
+    fn verify(self) -> Result<Self::Verified, Dropped>;
+}
+
+impl SignedClaimItem for Timestamped<Signed<SubmittedClaim>> {
+    type Verified = Timestamped<SubmittedClaim>;
+
+    fn verify(self) -> Result<Self::Verified, Dropped> {
+        let Timestamped {
+            id,
+            data,
+            timestamp,
+        } = self;
+        match SubmittedClaim::check_and_strip_signature(data) {
+            Ok(claim) => Ok(Timestamped::new(id, timestamp, claim)),
+            Err(error) => Err(Dropped {
+                claim_id: id,
+                reason: DropReason::InvalidSignature(error),
+            }),
+        }
+    }
+}
+
+impl<T: SignedClaimItem, E> SignedClaimItem for Result<T, E> {
+    type Verified = Result<T::Verified, E>;
+
+    fn verify(self) -> Result<Self::Verified, Dropped> {
+        match self {
+            Ok(item) => item.verify().map(Ok),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+}
+
+/// The check of a [Filtered] stream
+enum ClaimFilter {
+    ClaimType(String),
+    From(Address),
+    MinFee(Amount),
+    NotExpired(Arc<dyn Clock>),
+    Dedup(SeenIds),
+}
+
+/// The most recent claim IDs seen, forgetting the oldest beyond the capacity
+struct SeenIds {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl SeenIds {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        SeenIds {
+            ids: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records the claim ID, and returns whether it was not seen (recently)
+    fn insert(&mut self, claim_id: &str) -> bool {
+        if self.ids.contains(claim_id) {
+            return false;
+        }
+        if self.order.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+        self.ids.insert(claim_id.to_string());
+        self.order.push_back(claim_id.to_string());
+        true
+    }
+}
+
+impl ClaimFilter {
+    fn check(&mut self, claim_id: &str, claim: &SubmittedClaim) -> Result<(), DropReason> {
+        match self {
+            ClaimFilter::ClaimType(claim_type) if claim.claim_type != *claim_type => {
+                Err(DropReason::ClaimType(claim.claim_type.clone()))
+            }
+            ClaimFilter::From(sender) if Address::from_str(&claim.from).ok() != Some(*sender) => {
+                Err(DropReason::Sender(claim.from.clone()))
+            }
+            ClaimFilter::MinFee(min_fee) => match Amount::from_hex_str(&claim.fee) {
+                Ok(fee) if fee >= *min_fee => Ok(()),
+                _ => Err(DropReason::Fee(claim.fee.clone())),
+            },
+            ClaimFilter::NotExpired(clock) if clock.is_expired(&claim.expires) => {
+                Err(DropReason::Expired(claim.expires))
+            }
+            ClaimFilter::Dedup(seen) => match seen.insert(claim_id) {
+                true => Ok(()),
+                false => Err(DropReason::Duplicate),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A stream keeping the claims passing a check (see [ClaimStreamExt])
+pub struct Filtered<S, R> {
+    inner: S,
+    filter: ClaimFilter,
+    on_drop: R,
+}
+
+impl<S, R> Stream for Filtered<S, R>
+where
+    S: Stream + Unpin,
+    S::Item: ClaimItem,
+    R: FnMut(Dropped) + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let Some(item) = std::task::ready!(Pin::new(&mut this.inner).poll_next(cx)) else {
+                return Poll::Ready(None);
+            };
+            let Some((claim_id, claim)) = item.claim() else {
+                return Poll::Ready(Some(item));
+            };
+            match this.filter.check(claim_id, claim) {
+                Ok(()) => return Poll::Ready(Some(item)),
+                Err(reason) => (this.on_drop)(Dropped {
+                    claim_id: claim_id.to_string(),
+                    reason,
+                }),
+            }
+        }
+    }
+}
+
+/// A stream keeping the claims with a valid signature, stripped of it (see
+/// [ClaimStreamExt::verified])
+pub struct Verified<S, R> {
+    inner: S,
+    on_drop: R,
+}
+
+impl<S, R> Stream for Verified<S, R>
+where
+    S: Stream + Unpin,
+    S::Item: SignedClaimItem,
+    R: FnMut(Dropped) + Unpin,
+{
+    type Item = <S::Item as SignedClaimItem>::Verified;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let Some(item) = std::task::ready!(Pin::new(&mut this.inner).poll_next(cx)) else {
+                return Poll::Ready(None);
+            };
+            match item.verify() {
+                Ok(verified) => return Poll::Ready(Some(verified)),
+                Err(dropped) => (this.on_drop)(dropped),
+            }
+        }
+    }
+}
+
+fn filtered<S, R>(inner: S, filter: ClaimFilter, on_drop: R) -> Filtered<S, R> {
+    Filtered {
+        inner,
+        filter,
+        on_drop,
+    }
+}
+
+/// Adapters over streams of submitted claims, each reporting the claims it drops
+/// to `on_drop`
+pub trait ClaimStreamExt: Stream + Sized {
+    /// Keeps the claims with a valid signature, stripped of it
+    fn verified<R: FnMut(Dropped)>(self, on_drop: R) -> Verified<Self, R>
+    where
+        Self::Item: SignedClaimItem,
+    {
+        Verified {
+            inner: self,
+            on_drop,
+        }
+    }
+
+    /// Keeps the claims of the given type
+    fn claim_type<R: FnMut(Dropped)>(self, claim_type: &str, on_drop: R) -> Filtered<Self, R> {
+        filtered(
+            self,
+            ClaimFilter::ClaimType(claim_type.to_string()),
+            on_drop,
+        )
+    }
+
+    /// Keeps the claims from the given sender
+    fn from<R: FnMut(Dropped)>(self, sender: Address, on_drop: R) -> Filtered<Self, R> {
+        filtered(self, ClaimFilter::From(sender), on_drop)
+    }
+
+    /// Keeps the claims paying at least the given fee
+    fn min_fee<R: FnMut(Dropped)>(self, min_fee: Amount, on_drop: R) -> Filtered<Self, R> {
+        filtered(self, ClaimFilter::MinFee(min_fee), on_drop)
+    }
+
+    /// Keeps the claims not expired by the time they are polled
+    fn not_expired<C, R>(self, clock: C, on_drop: R) -> Filtered<Self, R>
+    where
+        C: Clock + 'static,
+        R: FnMut(Dropped),
+    {
+        filtered(self, ClaimFilter::NotExpired(Arc::new(clock)), on_drop)
+    }
+
+    /// Keeps the first occurrence of each claim (by claim ID), among the last
+    /// `capacity` distinct claims (older IDs are forgotten)
+    fn dedup_by_id<R: FnMut(Dropped)>(self, capacity: usize, on_drop: R) -> Filtered<Self, R> {
+        filtered(self, ClaimFilter::Dedup(SeenIds::new(capacity)), on_drop)
+    }
+}
+
+impl<S: Stream> ClaimStreamExt for S {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use alloy::signers::local::PrivateKeySigner;
+    use futures_util::{StreamExt, stream};
+
+    use super::*;
+    use crate::FixedClock;
+
+    fn claim(signer: &PrivateKeySigner, nonce: u64, claim_type: &str, fee: u128) -> SubmittedClaim {
+        SubmittedClaim {
+            claim: "claim".to_string(),
+            claim_type: claim_type.to_string(),
+            proof: String::new(),
+            nonce: nonce.to_string(),
+            to: vec![Address::ZERO.to_string()],
+            quorum: 1,
+            from: signer.address().to_string(),
+            expires: Timestamp::from_seconds(100 + nonce),
+            fee: Amount::from_subunits(fee).to_hex_str(),
+        }
+    }
+
+    fn timestamped<T>(nonce: u64, data: T) -> Timestamped<T> {
+        Timestamped::new(
+            format!("claim-{nonce}"),
+            Timestamp::from_seconds(nonce),
+            data,
+        )
+    }
+
+    fn ids<T: ClaimItem>(items: &[T]) -> Vec<&str> {
+        items
+            .iter()
+            .filter_map(|item| Some(item.claim()?.0))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_verified() {
+        let signer = PrivateKeySigner::random();
+        let valid = claim(&signer, 0, "pow", 1).into_signed(&signer).unwrap();
+        let forged = Signed::new_unchecked(
+            claim(&signer, 1, "pow", 1),
+            *valid.signature(),
+            *valid.hash(),
+        );
+        let items: Vec<Result<_, String>> = vec![
+            Ok(timestamped(0, valid)),
+            Err("undecodable".to_string()),
+            Ok(timestamped(1, forged)),
+        ];
+        let dropped = RefCell::new(Vec::new());
+        let verified: Vec<_> = stream::iter(items)
+            .verified(|item| dropped.borrow_mut().push(item))
+            .collect()
+            .await;
+        assert_eq!(verified.len(), 2);
+        assert_eq!(verified[0].as_ref().unwrap().data.nonce, "0");
+        assert!(verified[1].is_err());
+        let dropped = dropped.into_inner();
+        assert_eq!(dropped[0].claim_id, "claim-1");
+        assert!(matches!(dropped[0].reason, DropReason::InvalidSignature(_)));
+    }
+
+    #[tokio::test]
+    async fn test_filters() {
+        let signer = PrivateKeySigner::random();
+        let other = PrivateKeySigner::random();
+        let items = vec![
+            timestamped(0, claim(&signer, 0, "pow", 10)),
+            timestamped(1, claim(&signer, 1, "other", 10)),
+            timestamped(2, claim(&other, 2, "pow", 10)),
+            timestamped(3, claim(&signer, 3, "pow", 1)),
+            timestamped(0, claim(&signer, 0, "pow", 10)),
+            timestamped(50, claim(&signer, 50, "pow", 10)),
+        ];
+        let dropped = RefCell::new(Vec::new());
+        let log = |item: Dropped| dropped.borrow_mut().push(item.reason);
+        let clock = FixedClock(Timestamp::from_seconds(100) + Duration::from_secs(10));
+        let kept: Vec<_> = stream::iter(items)
+            .claim_type("pow", log)
+            .from(signer.address(), log)
+            .min_fee(Amount::from_subunits(5), log)
+            .not_expired(clock, log)
+            .dedup_by_id(16, log)
+            .collect()
+            .await;
+        assert_eq!(ids(&kept), ["claim-50"]);
+        assert_eq!(
+            dropped.into_inner(),
+            [
+                DropReason::Expired(Timestamp::from_seconds(100)),
+                DropReason::ClaimType("other".to_string()),
+                DropReason::Sender(other.address().to_string()),
+                DropReason::Fee(Amount::from_subunits(1).to_hex_str()),
+                DropReason::Expired(Timestamp::from_seconds(100)),
+            ]
+        );
+
+        // duplicates are dropped once seen, in any order
+        let items = vec![
+            timestamped(0, claim(&signer, 0, "pow", 10)),
+            timestamped(1, claim(&signer, 1, "pow", 10)),
+            timestamped(0, claim(&signer, 0, "pow", 10)),
+        ];
+        let mut duplicates = 0;
+        let kept: Vec<_> = stream::iter(items.clone())
+            .dedup_by_id(2, |_| duplicates += 1)
+            .collect()
+            .await;
+        assert_eq!(ids(&kept), ["claim-0", "claim-1"]);
+        assert_eq!(duplicates, 1);
+        // beyond the capacity, the oldest IDs are forgotten
+        let kept: Vec<_> = stream::iter(items)
+            .dedup_by_id(1, |_| duplicates += 1)
+            .collect()
+            .await;
+        assert_eq!(ids(&kept), ["claim-0", "claim-1", "claim-0"]);
+        assert_eq!(duplicates, 1);
+    }
+}
@@ -0,0 +1,212 @@
+//! # Clock Skew Module
+//!
+//! VSL drops claims once their `expires` timestamp has passed by the *server's*
+//! clock. This module estimates the offset between the local clock and the
+//! server's clock from samples pairing a server timestamp (e.g., the timestamp
+//! recorded for a freshly submitted claim) with the local times at which the
+//! request was sent and its response received.
+//!
+//! Like NTP, the estimate uses the sample with the shortest round trip, assuming
+//! the server timestamp lies halfway between sending and receiving; half the
+//! round trip is the uncertainty of the estimate.
+//!
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use crate::Timestamp;
+
+/// Default number of samples kept by a [SkewEstimator]
+pub const DEFAULT_SKEW_SAMPLES: usize = 16;
+
+/// A server timestamp observed during a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewSample {
+    /// Local time when the request was sent
+    pub local_sent: Timestamp,
+    /// Server time recorded while handling the request
+    pub server: Timestamp,
+    /// Local time when the response was received
+    pub local_received: Timestamp,
+}
+
+impl SkewSample {
+    pub fn round_trip(&self) -> Duration {
+        self.local_received
+            .saturating_duration_since(&self.local_sent)
+    }
+
+    /// Server time minus local time (in nanoseconds), assuming the server
+    /// timestamp lies halfway through the round trip
+    pub fn offset_nanos(&self) -> i128 {
+        let midpoint = self.local_sent.as_duration() + self.round_trip() / 2;
+        self.server.as_duration().as_nanos() as i128 - midpoint.as_nanos() as i128
+    }
+}
+
+/// An estimate of how far the server's clock is ahead of the local clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// Server time minus local time, in nanoseconds (negative if the server is behind)
+    pub offset_nanos: i128,
+    /// How far the actual offset may be from the estimated one
+    pub uncertainty: Duration,
+}
+
+impl ClockSkew {
+    fn abs_offset(&self) -> Duration {
+        Duration::from_nanos(self.offset_nanos.unsigned_abs().min(u64::MAX as u128) as u64)
+    }
+
+    /// The largest possible difference between the two clocks
+    pub fn margin(&self) -> Duration {
+        self.abs_offset().saturating_add(self.uncertainty)
+    }
+
+    /// The estimated server time at local time `local`
+    pub fn server_time(&self, local: &Timestamp) -> Timestamp {
+        if self.offset_nanos >= 0 {
+            local.saturating_add(self.abs_offset())
+        } else {
+            local.saturating_sub(self.abs_offset())
+        }
+    }
+
+    /// Whether something expiring at `expires` may be expired on the server, or
+    /// expire within the estimate's uncertainty, at local time `now`. A server
+    /// clock that is behind only makes things last longer there.
+    pub fn within_margin(&self, expires: &Timestamp, now: &Timestamp) -> bool {
+        *expires < self.server_time(now).saturating_add(self.uncertainty)
+    }
+}
+
+/// Keeps the most recent [SkewSample]s and estimates the [ClockSkew] from them
+#[derive(Debug, Clone)]
+pub struct SkewEstimator {
+    samples: VecDeque<SkewSample>,
+    capacity: usize,
+}
+
+impl Default for SkewEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SKEW_SAMPLES)
+    }
+}
+
+impl SkewEstimator {
+    pub fn new(capacity: usize) -> Self {
+        SkewEstimator {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records a sample, discarding the oldest one if at capacity
+    pub fn record(&mut self, sample: SkewSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &SkewSample> {
+        self.samples.iter()
+    }
+
+    /// The estimate from the sample with the shortest round trip, if any
+    pub fn estimate(&self) -> Option<ClockSkew> {
+        let best = self
+            .samples
+            .iter()
+            .min_by_key(|sample| sample.round_trip())?;
+        Some(ClockSkew {
+            offset_nanos: best.offset_nanos(),
+            uncertainty: best.round_trip() / 2,
+        })
+    }
+}
+
+/// What to do when submitting a claim whose `expires` falls within the skew margin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SkewPolicy {
+    /// Neither measure the skew nor check expiration times against it
+    #[default]
+    Ignore,
+    /// Measure the skew and record a [SkewWarning], but submit the claim
+    Warn,
+    /// Measure the skew and refuse to submit the claim
+    Refuse,
+}
+
+/// A claim was submitted although it expires within the skew margin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewWarning {
+    pub expires: Timestamp,
+    pub skew: ClockSkew,
+}
+
+impl fmt::Display for SkewWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "claim expiring at {} is within the server clock skew margin of {:?}",
+            self.expires.to_rfc3339(),
+            self.skew.margin()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sent: u64, server: u64, received: u64) -> SkewSample {
+        SkewSample {
+            local_sent: Timestamp::from_seconds(sent),
+            server: Timestamp::from_seconds(server),
+            local_received: Timestamp::from_seconds(received),
+        }
+    }
+
+    #[test]
+    fn test_sample_offset() {
+        assert_eq!(sample(100, 111, 102).offset_nanos(), 10_000_000_000);
+        assert_eq!(sample(100, 91, 102).offset_nanos(), -10_000_000_000);
+        assert_eq!(sample(100, 101, 102).round_trip(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_estimate_uses_shortest_round_trip() {
+        let mut estimator = SkewEstimator::new(2);
+        assert_eq!(estimator.estimate(), None);
+        estimator.record(sample(100, 130, 110));
+        estimator.record(sample(200, 211, 202));
+        let skew = estimator.estimate().unwrap();
+        assert_eq!(skew.offset_nanos, 10_000_000_000);
+        assert_eq!(skew.uncertainty, Duration::from_secs(1));
+        assert_eq!(skew.margin(), Duration::from_secs(11));
+        // the oldest sample is dropped
+        estimator.record(sample(300, 330, 310));
+        assert_eq!(estimator.samples().count(), 2);
+    }
+
+    #[test]
+    fn test_server_time_and_margin() {
+        let ahead = ClockSkew {
+            offset_nanos: 5_000_000_000,
+            uncertainty: Duration::from_secs(1),
+        };
+        let behind = ClockSkew {
+            offset_nanos: -5_000_000_000,
+            ..ahead
+        };
+        let now = Timestamp::from_seconds(1_000);
+        assert_eq!(ahead.server_time(&now), Timestamp::from_seconds(1_005));
+        assert_eq!(behind.server_time(&now), Timestamp::from_seconds(995));
+        assert!(ahead.within_margin(&Timestamp::from_seconds(1_005), &now));
+        assert!(!ahead.within_margin(&Timestamp::from_seconds(1_006), &now));
+        assert!(behind.within_margin(&Timestamp::from_seconds(995), &now));
+        assert!(!behind.within_margin(&Timestamp::from_seconds(996), &now));
+        assert!(!behind.within_margin(&Timestamp::from_seconds(1_005), &now));
+    }
+}
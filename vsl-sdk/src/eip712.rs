@@ -0,0 +1,579 @@
+//! # EIP-712 Module
+//!
+//! By default, messages are signed as opaque RLP bytes (see `docs/signing.md`),
+//! which wallets can only display as a hash. This module defines an alternative
+//! signing scheme following [EIP-712](https://eips.ethereum.org/EIPS/eip-712):
+//! each message is mapped to a typed structure (e.g. [Pay]), signed together with
+//! the VSL [domain], so that wallets can show the fields being signed.
+//!
+//! The typed data of a message (see [Eip712Message::typed_data]) is the JSON object
+//! expected by the `eth_signTypedData_v4` method of Ethereum wallets.
+//!
+//! Signed messages carry the [SignatureScheme] used in their `signatureScheme`
+//! field (see [VersionedSigned]); messages without it are signed with EIP-191.
+//!
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::consensus::Signed;
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{U256, keccak256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::{Error as SignError, Signature, SignerSync};
+use alloy::sol;
+use alloy::sol_types::{Eip712Domain, SolStruct};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::verify_signature;
+use crate::rpc_messages::{
+    CreateAssetMessage, PayMessage, SetStateMessage, SettleClaimMessage, SubmittedClaim,
+    TransferAssetMessage,
+};
+use crate::{Address, Amount, AssetId, B256, IntoSigned, VerificationError};
+
+/// The `name` of the VSL EIP-712 domain
+pub const DOMAIN_NAME: &str = "VSL";
+
+/// The `version` of the VSL EIP-712 domain
+pub const DOMAIN_VERSION: &str = "1";
+
+sol! {
+    /// The typed data of a [PayMessage]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct Pay {
+        address from;
+        address to;
+        uint256 amount;
+        uint64 nonce;
+    }
+
+    /// The typed data of a [TransferAssetMessage]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct TransferAsset {
+        address from;
+        uint64 nonce;
+        bytes32 assetId;
+        address to;
+        uint256 amount;
+    }
+
+    /// The typed data of a [CreateAssetMessage]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct CreateAsset {
+        address accountId;
+        uint64 nonce;
+        string tickerSymbol;
+        uint8 decimals;
+        uint256 totalSupply;
+    }
+
+    /// The typed data of a [SetStateMessage]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct SetState {
+        address from;
+        uint64 nonce;
+        bytes32 state;
+    }
+
+    /// The typed data of a [SettleClaimMessage]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct SettleClaim {
+        address from;
+        uint64 nonce;
+        bytes32 targetClaimId;
+    }
+
+    /// The typed data of a [SubmittedClaim]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct SubmitClaim {
+        string claim;
+        string claimType;
+        string proof;
+        uint64 nonce;
+        address[] to;
+        uint16 quorum;
+        address from;
+        uint64 expiresSeconds;
+        uint32 expiresNanos;
+        uint256 fee;
+    }
+}
+
+/// The VSL EIP-712 domain, separating signatures by network (if given) through its `salt`
+pub fn domain(network: Option<&str>) -> Eip712Domain {
+    Eip712Domain::new(
+        Some(Cow::Borrowed(DOMAIN_NAME)),
+        Some(Cow::Borrowed(DOMAIN_VERSION)),
+        None,
+        None,
+        network.map(|network| keccak256(network.as_bytes())),
+    )
+}
+
+/// The scheme used for signing a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// The EIP-191 signature of the RLP encoding (see [IntoSigned])
+    #[default]
+    Eip191,
+    /// The EIP-712 signature of the typed data (see [Eip712Message])
+    Eip712,
+}
+
+#[derive(Debug)]
+pub enum Eip712Error {
+    /// A field of the message cannot be represented with its EIP-712 type
+    InvalidField(&'static str),
+    SignError(SignError),
+}
+
+impl fmt::Display for Eip712Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Eip712Error::InvalidField(field) => write!(f, "invalid field {field}"),
+            Eip712Error::SignError(error) => write!(f, "cannot sign: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Eip712Error {}
+
+impl From<SignError> for Eip712Error {
+    fn from(error: SignError) -> Self {
+        Eip712Error::SignError(error)
+    }
+}
+
+// The fields are parsed strictly: the signature must bind the exact strings the
+// node hashes into the claim ID, so each one must be in the canonical form the
+// SDK renders (checksummed addresses, minimal hex amounts, decimal nonces,
+// lowercase hashes, and unprefixed asset IDs).
+
+fn address(field: &'static str, s: &str) -> Result<Address, Eip712Error> {
+    Address::from_str(s)
+        .ok()
+        .filter(|address| address.to_string() == s)
+        .ok_or(Eip712Error::InvalidField(field))
+}
+
+fn amount(field: &'static str, s: &str) -> Result<U256, Eip712Error> {
+    let amount = Amount::from_hex_str(s)
+        .ok()
+        .filter(|amount| amount.to_hex_str() == s)
+        .ok_or(Eip712Error::InvalidField(field))?;
+    Ok(U256::from(amount.subunits()))
+}
+
+fn nonce(s: &str) -> Result<u64, Eip712Error> {
+    s.parse::<u64>()
+        .ok()
+        .filter(|nonce| nonce.to_string() == s)
+        .ok_or(Eip712Error::InvalidField("nonce"))
+}
+
+fn hash(field: &'static str, s: &str) -> Result<B256, Eip712Error> {
+    B256::from_str(s)
+        .ok()
+        .filter(|hash| hash.to_string() == s)
+        .ok_or(Eip712Error::InvalidField(field))
+}
+
+fn asset_id(s: &str) -> Result<B256, Eip712Error> {
+    let asset_id = AssetId::from_str(s)
+        .ok()
+        .filter(|asset_id| asset_id.to_string() == s)
+        .ok_or(Eip712Error::InvalidField("asset_id"))?;
+    Ok(B256::from(asset_id.0))
+}
+
+/// Messages which can be signed as EIP-712 typed data
+pub trait Eip712Message: IntoSigned {
+    type Typed: SolStruct + Serialize;
+
+    /// The typed data of the message (failing if a field is malformed)
+    fn to_typed(&self) -> Result<Self::Typed, Eip712Error>;
+
+    /// The hash to be signed, for the given network
+    fn eip712_signing_hash(&self, network: Option<&str>) -> Result<B256, Eip712Error> {
+        Ok(self.to_typed()?.eip712_signing_hash(&domain(network)))
+    }
+
+    /// The `eth_signTypedData_v4` payload of the message, for the given network
+    fn typed_data(&self, network: Option<&str>) -> Result<TypedData, Eip712Error> {
+        Ok(TypedData::from_struct(
+            &self.to_typed()?,
+            Some(domain(network)),
+        ))
+    }
+
+    fn into_signed_eip712(
+        self,
+        signer: &PrivateKeySigner,
+        network: Option<&str>,
+    ) -> Result<Signed<Self>, Eip712Error> {
+        let hash = self.eip712_signing_hash(network)?;
+        let sig = signer.sign_hash_sync(&hash)?;
+        Ok(Signed::new_unchecked(self, sig, hash))
+    }
+
+    fn recover_address_eip712(
+        &self,
+        sig: &Signature,
+        network: Option<&str>,
+    ) -> Result<Address, Eip712Error> {
+        let hash = self.eip712_signing_hash(network)?;
+        sig.recover_address_from_prehash(&hash)
+            .map_err(|e| Eip712Error::SignError(e.into()))
+    }
+
+    /// Whether the message is signed (with EIP-712) by its sender, for the given network
+    fn check_eip712(signed: &Signed<Self>, network: Option<&str>) -> bool {
+        Self::verify_eip712(signed, network).is_ok()
+    }
+
+    /// Strictly verifies the message signed with EIP-712, for the given network
+    /// (see [IntoSigned::verify_for])
+    fn verify_eip712(
+        signed: &Signed<Self>,
+        network: Option<&str>,
+    ) -> Result<Address, VerificationError> {
+        let data = signed.tx();
+        let sender = data.sender().ok_or(VerificationError::InvalidSender)?;
+        let expected = data
+            .eip712_signing_hash(network)
+            .map_err(|_| VerificationError::InvalidPayload)?;
+        verify_signature(sender, expected, signed.hash(), signed.signature())
+    }
+}
+
+impl Eip712Message for PayMessage {
+    type Typed = Pay;
+
+    fn to_typed(&self) -> Result<Pay, Eip712Error> {
+        Ok(Pay {
+            from: address("from", &self.from)?,
+            to: address("to", &self.to)?,
+            amount: amount("amount", &self.amount)?,
+            nonce: nonce(&self.nonce)?,
+        })
+    }
+}
+
+impl Eip712Message for TransferAssetMessage {
+    type Typed = TransferAsset;
+
+    fn to_typed(&self) -> Result<TransferAsset, Eip712Error> {
+        Ok(TransferAsset {
+            from: address("from", &self.from)?,
+            nonce: nonce(&self.nonce)?,
+            assetId: asset_id(&self.asset_id)?,
+            to: address("to", &self.to)?,
+            amount: amount("amount", &self.amount)?,
+        })
+    }
+}
+
+impl Eip712Message for CreateAssetMessage {
+    type Typed = CreateAsset;
+
+    fn to_typed(&self) -> Result<CreateAsset, Eip712Error> {
+        Ok(CreateAsset {
+            accountId: address("account_id", &self.account_id)?,
+            nonce: nonce(&self.nonce)?,
+            tickerSymbol: self.ticker_symbol.clone(),
+            decimals: self.decimals,
+            totalSupply: amount("total_supply", &self.total_supply)?,
+        })
+    }
+}
+
+impl Eip712Message for SetStateMessage {
+    type Typed = SetState;
+
+    fn to_typed(&self) -> Result<SetState, Eip712Error> {
+        Ok(SetState {
+            from: address("from", &self.from)?,
+            nonce: nonce(&self.nonce)?,
+            state: hash("state", &self.state)?,
+        })
+    }
+}
+
+impl Eip712Message for SettleClaimMessage {
+    type Typed = SettleClaim;
+
+    fn to_typed(&self) -> Result<SettleClaim, Eip712Error> {
+        Ok(SettleClaim {
+            from: address("from", &self.from)?,
+            nonce: nonce(&self.nonce)?,
+            targetClaimId: hash("target_claim_id", &self.target_claim_id)?,
+        })
+    }
+}
+
+impl Eip712Message for SubmittedClaim {
+    type Typed = SubmitClaim;
+
+    fn to_typed(&self) -> Result<SubmitClaim, Eip712Error> {
+        Ok(SubmitClaim {
+            claim: self.claim.clone(),
+            claimType: self.claim_type.clone(),
+            proof: self.proof.clone(),
+            nonce: nonce(&self.nonce)?,
+            to: self
+                .to
+                .iter()
+                .map(|to| address("to", to))
+                .collect::<Result<_, _>>()?,
+            quorum: self.quorum,
+            from: address("from", &self.from)?,
+            expiresSeconds: self.expires.seconds(),
+            expiresNanos: self.expires.nanos(),
+            fee: amount("fee", &self.fee)?,
+        })
+    }
+}
+
+/// A signed message, along with the scheme of its signature.
+///
+/// It is (de)serialized as the [Signed] message, with an extra `signatureScheme`
+/// field (`eip191` if missing).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Signed<T>: Serialize",
+    deserialize = "Signed<T>: serde::de::DeserializeOwned"
+))]
+pub struct VersionedSigned<T> {
+    #[serde(rename = "signatureScheme", default)]
+    pub scheme: SignatureScheme,
+    #[serde(flatten)]
+    pub signed: Signed<T>,
+}
+
+impl<T: Eip712Message> VersionedSigned<T> {
+    /// Signs the message with the given scheme, for the given network
+    pub fn sign(
+        message: T,
+        signer: &PrivateKeySigner,
+        scheme: SignatureScheme,
+        network: Option<&str>,
+    ) -> Result<Self, Eip712Error> {
+        let signed = match scheme {
+            SignatureScheme::Eip191 => message.into_signed_for(signer, network)?,
+            SignatureScheme::Eip712 => message.into_signed_eip712(signer, network)?,
+        };
+        Ok(VersionedSigned { scheme, signed })
+    }
+
+    /// The address recovered from the signature, if valid
+    pub fn recover_signer(&self, network: Option<&str>) -> Option<Address> {
+        let message = self.signed.tx();
+        let signature = self.signed.signature();
+        match self.scheme {
+            SignatureScheme::Eip191 => message.recover_address_for(signature, network).ok(),
+            SignatureScheme::Eip712 => message.recover_address_eip712(signature, network).ok(),
+        }
+    }
+
+    /// Whether the message is signed by its sender, for the given network
+    pub fn check(&self, network: Option<&str>) -> bool {
+        self.verify(network).is_ok()
+    }
+
+    /// Strictly verifies the message according to its scheme, for the given network
+    pub fn verify(&self, network: Option<&str>) -> Result<Address, VerificationError> {
+        match self.scheme {
+            SignatureScheme::Eip191 => T::verify_for(&self.signed, network),
+            SignatureScheme::Eip712 => T::verify_eip712(&self.signed, network),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timestamp;
+
+    fn payment(signer: &PrivateKeySigner) -> PayMessage {
+        PayMessage {
+            from: signer.address().to_string(),
+            to: Address::repeat_byte(0xff).to_string(),
+            amount: "0xa".to_string(),
+            nonce: "100".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_wallet_payload() {
+        let signer = PrivateKeySigner::random();
+        let claim = SubmittedClaim {
+            claim: "claim".to_string(),
+            claim_type: "type".to_string(),
+            proof: "proof".to_string(),
+            nonce: "1".to_string(),
+            to: vec![Address::repeat_byte(0xee).to_string()],
+            quorum: 1,
+            from: signer.address().to_string(),
+            expires: Timestamp::from_parts(1_700_000_000, 5),
+            fee: "0x1".to_string(),
+        };
+        // wallets compute the hash from the JSON payload: it must match ours
+        let json = serde_json::to_string(&claim.typed_data(Some("vsl-devnet")).unwrap()).unwrap();
+        let typed_data: TypedData = serde_json::from_str(&json).unwrap();
+        assert_eq!(typed_data.primary_type, "SubmitClaim");
+        assert_eq!(
+            typed_data.eip712_signing_hash().unwrap(),
+            claim.eip712_signing_hash(Some("vsl-devnet")).unwrap()
+        );
+        let hash = typed_data.eip712_signing_hash().unwrap();
+        let signature = signer.sign_hash_sync(&hash).unwrap();
+        let signed = Signed::new_unchecked(claim, signature, hash);
+        assert!(SubmittedClaim::check_eip712(&signed, Some("vsl-devnet")));
+        assert!(!SubmittedClaim::check_eip712(&signed, None));
+    }
+
+    #[test]
+    fn test_versioned_signatures() {
+        let signer = PrivateKeySigner::random();
+        for scheme in [SignatureScheme::Eip191, SignatureScheme::Eip712] {
+            let signed = VersionedSigned::sign(payment(&signer), &signer, scheme, None).unwrap();
+            assert!(signed.check(None));
+            assert_eq!(signed.recover_signer(None), Some(signer.address()));
+            let json = serde_json::to_value(&signed).unwrap();
+            assert_eq!(
+                json["signatureScheme"],
+                serde_json::to_value(scheme).unwrap()
+            );
+            let parsed: VersionedSigned<PayMessage> = serde_json::from_value(json).unwrap();
+            assert_eq!(parsed.scheme, scheme);
+            assert!(parsed.check(None));
+        }
+
+        // an EIP-712 signature does not verify as EIP-191 (and vice versa)
+        let mut signed =
+            VersionedSigned::sign(payment(&signer), &signer, SignatureScheme::Eip712, None)
+                .unwrap();
+        signed.scheme = SignatureScheme::Eip191;
+        assert!(matches!(
+            signed.verify(None),
+            Err(VerificationError::HashMismatch { .. })
+        ));
+
+        // messages without scheme are EIP-191 signed
+        let plain = serde_json::to_value(payment(&signer).into_signed(&signer).unwrap()).unwrap();
+        let parsed: VersionedSigned<PayMessage> = serde_json::from_value(plain).unwrap();
+        assert_eq!(parsed.scheme, SignatureScheme::Eip191);
+        assert!(parsed.check(None));
+    }
+
+    #[test]
+    fn test_invalid_fields() {
+        let signer = PrivateKeySigner::random();
+        let mut payment = payment(&signer);
+        payment.amount = "10".to_string();
+        assert!(matches!(
+            payment.into_signed_eip712(&signer, None),
+            Err(Eip712Error::InvalidField("amount"))
+        ));
+    }
+
+    fn assert_invalid<T: Eip712Message>(message: &T, field: &str) {
+        assert!(
+            matches!(message.to_typed(), Err(Eip712Error::InvalidField(f)) if f == field),
+            "{field} should be refused"
+        );
+    }
+
+    #[test]
+    fn test_non_canonical_fields() {
+        let signer = PrivateKeySigner::random();
+        let hash = B256::repeat_byte(0xab).to_string();
+        let upper = |s: &str| format!("0x{}", s[2..].to_uppercase());
+
+        let payment = payment(&signer);
+        assert!(payment.to_typed().is_ok());
+        for (field, value) in [
+            ("from", payment.from.to_lowercase()),
+            ("to", payment.to.to_lowercase()),
+            ("amount", "0x0a".to_string()),
+            ("amount", "0xA".to_string()),
+            ("nonce", "0100".to_string()),
+            ("nonce", "+100".to_string()),
+        ] {
+            let mut message = payment.clone();
+            match field {
+                "from" => message.from = value,
+                "to" => message.to = value,
+                "amount" => message.amount = value,
+                _ => message.nonce = value,
+            }
+            assert_invalid(&message, field);
+        }
+
+        let transfer = TransferAssetMessage {
+            from: payment.from.clone(),
+            nonce: "1".to_string(),
+            asset_id: AssetId::repeat_byte(0xab).to_string(),
+            to: payment.to.clone(),
+            amount: "0x1".to_string(),
+        };
+        assert!(transfer.to_typed().is_ok());
+        let mut message = transfer.clone();
+        message.asset_id = transfer.asset_id.to_uppercase();
+        assert_invalid(&message, "asset_id");
+        let mut message = transfer;
+        message.asset_id = hash.clone();
+        assert_invalid(&message, "asset_id");
+
+        let create = CreateAssetMessage {
+            account_id: payment.from.clone(),
+            nonce: "1".to_string(),
+            ticker_symbol: "TKN".to_string(),
+            decimals: 2,
+            total_supply: "0x64".to_string(),
+        };
+        assert!(create.to_typed().is_ok());
+        let mut message = create.clone();
+        message.account_id = create.account_id.to_lowercase();
+        assert_invalid(&message, "account_id");
+        let mut message = create;
+        message.total_supply = "0x064".to_string();
+        assert_invalid(&message, "total_supply");
+
+        let set_state = SetStateMessage {
+            from: payment.from.clone(),
+            nonce: "1".to_string(),
+            state: upper(&hash),
+        };
+        assert_invalid(&set_state, "state");
+
+        let settle = SettleClaimMessage {
+            from: payment.from.clone(),
+            nonce: "1".to_string(),
+            target_claim_id: upper(&hash),
+        };
+        assert_invalid(&settle, "target_claim_id");
+
+        let claim = SubmittedClaim {
+            claim: "claim".to_string(),
+            claim_type: "type".to_string(),
+            proof: "proof".to_string(),
+            nonce: "1".to_string(),
+            to: vec![payment.to.clone()],
+            quorum: 1,
+            from: payment.from.clone(),
+            expires: Timestamp::from_parts(1_700_000_000, 5),
+            fee: "0x1".to_string(),
+        };
+        assert!(claim.to_typed().is_ok());
+        let mut message = claim.clone();
+        message.fee = "0x01".to_string();
+        assert_invalid(&message, "fee");
+        let mut message = claim;
+        message.to = vec![payment.to.to_lowercase()];
+        assert_invalid(&message, "to");
+    }
+}
@@ -0,0 +1,678 @@
+//! # Envelope Module
+//!
+//! Portable files for signing messages on an offline (air-gapped) machine:
+//!
+//! 1. an online machine creates an [UnsignedEnvelope], holding the message to be
+//!    signed (with its nonce), its RLP encoding and the EIP-191 hash to be signed
+//!    (with the network as signing domain, see [crate::network]);
+//! 2. the offline machine checks and signs it, producing a [SignedEnvelope], whose
+//!    `message` is the signed message exactly as sent to the RPC server
+//!    (including `hash`, `r`, `s` and `v`, see `docs/signing.md`);
+//! 3. an online machine [broadcast]s the signed message to the matching RPC method.
+//!
+//! Both envelopes are (de)serialized as JSON, e.g.:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "network": "vsl-devnet",
+//!   "signer": "0x661403e07d8d910e45c21f3dd9303957a5d080c7",
+//!   "nonce": 100,
+//!   "kind": "pay",
+//!   "message": { "from": "0x6614...", "to": "0xffff...", "amount": "0xa", "nonce": "100" },
+//!   "rlp": "0xf8...",
+//!   "hash": "0x0745..."
+//! }
+//! ```
+//!
+use std::fmt;
+
+use alloy::consensus::Signed;
+use alloy::primitives::{Bytes, eip191_hash_message};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::{Error as SignError, Signature};
+use alloy_rlp::Encodable;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::rpc_params;
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_messages::{
+    AccountStateHash, CreateAssetMessage, CreateAssetResult, IdentifiableClaim, PayMessage,
+    SetStateMessage, SettleClaimMessage, SubmittedClaim, TransferAssetMessage,
+};
+use crate::rpc_wrapper::{AssetData, RpcWrapperResult};
+use crate::{
+    Address, Amount, AssetId, B256, HasSender, IntoSigned, Timestamp, VerificationError,
+    signing_preimage,
+};
+
+/// The version of the envelope format
+pub const ENVELOPE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+    /// The envelope was produced by an incompatible version of the format
+    UnsupportedVersion(u32),
+    /// The sender of the message cannot be parsed
+    InvalidSender,
+    /// The nonce of the message cannot be parsed
+    InvalidNonce,
+    /// The envelope fields disagree with the message
+    SignerMismatch {
+        expected: Address,
+        found: Address,
+    },
+    NonceMismatch {
+        expected: u64,
+        found: u64,
+    },
+    /// The recorded RLP encoding or hash do not match the message
+    EncodingMismatch,
+    /// The signature was not produced by the sender of the message
+    InvalidSignature,
+    SignError(SignError),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnvelopeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported envelope version {version}")
+            }
+            EnvelopeError::InvalidSender => write!(f, "invalid message sender"),
+            EnvelopeError::InvalidNonce => write!(f, "invalid message nonce"),
+            EnvelopeError::SignerMismatch { expected, found } => {
+                write!(f, "expected signer {expected}, found {found}")
+            }
+            EnvelopeError::NonceMismatch { expected, found } => {
+                write!(f, "expected nonce {expected}, found {found}")
+            }
+            EnvelopeError::EncodingMismatch => {
+                write!(f, "the recorded encoding or hash do not match the message")
+            }
+            EnvelopeError::InvalidSignature => {
+                write!(f, "the message is not signed by its sender")
+            }
+            EnvelopeError::SignError(error) => write!(f, "cannot sign the message: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+impl From<SignError> for EnvelopeError {
+    fn from(value: SignError) -> Self {
+        Self::SignError(value)
+    }
+}
+
+/// A message to be signed, tagged by its kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum UnsignedMessage {
+    SubmitClaim(SubmittedClaim),
+    SettleClaim(SettleClaimMessage),
+    Pay(PayMessage),
+    CreateAsset(CreateAssetMessage),
+    TransferAsset(TransferAssetMessage),
+    SetState(SetStateMessage),
+}
+
+/// A signed message, tagged by its kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum SignedMessage {
+    SubmitClaim(Signed<SubmittedClaim>),
+    SettleClaim(Signed<SettleClaimMessage>),
+    Pay(Signed<PayMessage>),
+    CreateAsset(Signed<CreateAssetMessage>),
+    TransferAsset(Signed<TransferAssetMessage>),
+    SetState(Signed<SetStateMessage>),
+}
+
+/// Applies `$body` to the message of any kind, bound to `$message`
+macro_rules! with_message {
+    ($enum:ident, $value:expr, $message:ident => $body:expr) => {
+        match $value {
+            $enum::SubmitClaim($message) => $body,
+            $enum::SettleClaim($message) => $body,
+            $enum::Pay($message) => $body,
+            $enum::CreateAsset($message) => $body,
+            $enum::TransferAsset($message) => $body,
+            $enum::SetState($message) => $body,
+        }
+    };
+}
+
+impl UnsignedMessage {
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit_claim(
+        from: &Address,
+        nonce: u64,
+        claim: String,
+        claim_type: String,
+        proof: String,
+        to: &[Address],
+        quorum: u16,
+        expires: Timestamp,
+        fee: &Amount,
+    ) -> Self {
+        Self::SubmitClaim(SubmittedClaim {
+            claim,
+            claim_type,
+            proof,
+            nonce: nonce.to_string(),
+            to: to.iter().map(ToString::to_string).collect(),
+            quorum,
+            from: from.to_string(),
+            expires,
+            fee: fee.to_hex_str(),
+        })
+    }
+
+    pub fn settle_claim(from: &Address, nonce: u64, claim_id: &B256) -> Self {
+        Self::SettleClaim(SettleClaimMessage {
+            from: from.to_string(),
+            nonce: nonce.to_string(),
+            target_claim_id: claim_id.to_string(),
+        })
+    }
+
+    pub fn pay(from: &Address, nonce: u64, to: &Address, amount: &Amount) -> Self {
+        Self::Pay(PayMessage {
+            from: from.to_string(),
+            nonce: nonce.to_string(),
+            to: to.to_string(),
+            amount: amount.to_hex_str(),
+        })
+    }
+
+    pub fn create_asset(
+        from: &Address,
+        nonce: u64,
+        ticker_symbol: &str,
+        decimals: u8,
+        total_supply: &Amount,
+    ) -> RpcWrapperResult<Self> {
+        let message = AssetData {
+            account_id: *from,
+            nonce,
+            ticker_symbol: ticker_symbol.to_string(),
+            decimals,
+            total_supply: *total_supply,
+        }
+        .try_into()?;
+        Ok(Self::CreateAsset(message))
+    }
+
+    pub fn transfer_asset(
+        from: &Address,
+        nonce: u64,
+        asset_id: &AssetId,
+        to: &Address,
+        amount: &Amount,
+    ) -> Self {
+        Self::TransferAsset(TransferAssetMessage {
+            from: from.to_string(),
+            nonce: nonce.to_string(),
+            to: to.to_string(),
+            amount: amount.to_hex_str(),
+            asset_id: asset_id.to_string(),
+        })
+    }
+
+    pub fn set_state(from: &Address, nonce: u64, state: &AccountStateHash) -> Self {
+        Self::SetState(SetStateMessage {
+            from: from.to_string(),
+            nonce: nonce.to_string(),
+            state: state.to_string(),
+        })
+    }
+
+    /// The kind of the message, as serialized in the envelopes
+    pub fn kind(&self) -> &'static str {
+        match self {
+            UnsignedMessage::SubmitClaim(_) => "submit_claim",
+            UnsignedMessage::SettleClaim(_) => "settle_claim",
+            UnsignedMessage::Pay(_) => "pay",
+            UnsignedMessage::CreateAsset(_) => "create_asset",
+            UnsignedMessage::TransferAsset(_) => "transfer_asset",
+            UnsignedMessage::SetState(_) => "set_state",
+        }
+    }
+
+    pub fn sender(&self) -> Option<Address> {
+        with_message!(UnsignedMessage, self, message => message.sender())
+    }
+
+    pub fn nonce(&self) -> Option<u64> {
+        let nonce = with_message!(UnsignedMessage, self, message => &message.nonce);
+        nonce.parse().ok()
+    }
+
+    /// The RLP encoding of the message
+    pub fn rlp(&self) -> Bytes {
+        let mut buf = Vec::new();
+        with_message!(UnsignedMessage, self, message => message.encode(&mut buf));
+        buf.into()
+    }
+
+    /// Signs the message in the given domain (network), without checking the
+    /// signer against the sender
+    pub fn sign(
+        self,
+        signer: &PrivateKeySigner,
+        domain: Option<&str>,
+    ) -> Result<SignedMessage, SignError> {
+        Ok(match self {
+            UnsignedMessage::SubmitClaim(message) => {
+                SignedMessage::SubmitClaim(message.into_signed_for(signer, domain)?)
+            }
+            UnsignedMessage::SettleClaim(message) => {
+                SignedMessage::SettleClaim(message.into_signed_for(signer, domain)?)
+            }
+            UnsignedMessage::Pay(message) => {
+                SignedMessage::Pay(message.into_signed_for(signer, domain)?)
+            }
+            UnsignedMessage::CreateAsset(message) => {
+                SignedMessage::CreateAsset(message.into_signed_for(signer, domain)?)
+            }
+            UnsignedMessage::TransferAsset(message) => {
+                SignedMessage::TransferAsset(message.into_signed_for(signer, domain)?)
+            }
+            UnsignedMessage::SetState(message) => {
+                SignedMessage::SetState(message.into_signed_for(signer, domain)?)
+            }
+        })
+    }
+}
+
+impl SignedMessage {
+    /// The RPC method receiving this kind of message
+    pub fn method(&self) -> &'static str {
+        match self {
+            SignedMessage::SubmitClaim(_) => "vsl_submitClaim",
+            SignedMessage::SettleClaim(_) => "vsl_settleClaim",
+            SignedMessage::Pay(_) => "vsl_pay",
+            SignedMessage::CreateAsset(_) => "vsl_createAsset",
+            SignedMessage::TransferAsset(_) => "vsl_transferAsset",
+            SignedMessage::SetState(_) => "vsl_setAccountState",
+        }
+    }
+
+    pub fn hash(&self) -> B256 {
+        with_message!(SignedMessage, self, signed => *signed.hash())
+    }
+
+    pub fn signature(&self) -> Signature {
+        with_message!(SignedMessage, self, signed => *signed.signature())
+    }
+
+    /// The address recovered from the signature in the given domain, if valid
+    pub fn recover_signer(&self, domain: Option<&str>) -> Option<Address> {
+        with_message!(SignedMessage, self, signed => {
+            signed.tx().recover_address_for(signed.signature(), domain).ok()
+        })
+    }
+
+    /// Strictly verifies the signed message in the given domain (see [IntoSigned::verify_for])
+    pub fn verify(&self, domain: Option<&str>) -> Result<Address, VerificationError> {
+        with_message!(SignedMessage, self, signed => IntoSigned::verify_for(signed, domain))
+    }
+
+    /// The ID of the claim recording the message, if it can be computed locally
+    /// (it cannot for claim settlements and state updates)
+    pub fn claim_id(&self) -> Option<B256> {
+        match self {
+            SignedMessage::SubmitClaim(signed) => Some(signed.tx().claim_id()),
+            SignedMessage::Pay(signed) => Some(signed.tx().claim_id()),
+            SignedMessage::CreateAsset(signed) => Some(signed.tx().claim_id()),
+            SignedMessage::TransferAsset(signed) => Some(signed.tx().claim_id()),
+            SignedMessage::SettleClaim(_) | SignedMessage::SetState(_) => None,
+        }
+    }
+
+    /// The message without its signature
+    pub fn unsigned(&self) -> UnsignedMessage {
+        match self {
+            SignedMessage::SubmitClaim(signed) => UnsignedMessage::SubmitClaim(signed.tx().clone()),
+            SignedMessage::SettleClaim(signed) => UnsignedMessage::SettleClaim(signed.tx().clone()),
+            SignedMessage::Pay(signed) => UnsignedMessage::Pay(signed.tx().clone()),
+            SignedMessage::CreateAsset(signed) => UnsignedMessage::CreateAsset(signed.tx().clone()),
+            SignedMessage::TransferAsset(signed) => {
+                UnsignedMessage::TransferAsset(signed.tx().clone())
+            }
+            SignedMessage::SetState(signed) => UnsignedMessage::SetState(signed.tx().clone()),
+        }
+    }
+}
+
+/// A message waiting to be signed (offline)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsignedEnvelope {
+    pub version: u32,
+    /// The network the message is intended for
+    pub network: Option<String>,
+    /// The account expected to sign the message
+    pub signer: Address,
+    /// The nonce of the signer the message is valid for
+    pub nonce: u64,
+    #[serde(flatten)]
+    pub message: UnsignedMessage,
+    /// The RLP encoding of the message
+    pub rlp: Bytes,
+    /// The EIP-191 hash of the encoding (prefixed with the network, if any), to be signed
+    pub hash: B256,
+}
+
+/// A message signed (offline), ready to be broadcast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    pub version: u32,
+    /// The network the message is intended for
+    pub network: Option<String>,
+    /// The account having signed the message
+    pub signer: Address,
+    /// The nonce of the signer the message is valid for
+    pub nonce: u64,
+    #[serde(flatten)]
+    pub message: SignedMessage,
+    /// The RLP encoding of the message
+    pub rlp: Bytes,
+}
+
+/// The result of broadcasting a signed message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BroadcastReceipt {
+    /// The ID of the submitted or settled claim
+    Claim(B256),
+    /// The ID of the created asset, and of the settled claim creating it
+    Asset { asset_id: AssetId, claim_id: B256 },
+}
+
+impl UnsignedEnvelope {
+    pub fn new(message: UnsignedMessage, network: Option<String>) -> Result<Self, EnvelopeError> {
+        let signer = message.sender().ok_or(EnvelopeError::InvalidSender)?;
+        let nonce = message.nonce().ok_or(EnvelopeError::InvalidNonce)?;
+        let rlp = message.rlp();
+        let hash = eip191_hash_message(signing_preimage(&rlp, network.as_deref()));
+        Ok(UnsignedEnvelope {
+            version: ENVELOPE_VERSION,
+            network,
+            signer,
+            nonce,
+            hash,
+            rlp,
+            message,
+        })
+    }
+
+    /// Checks that the envelope fields are consistent with the message
+    pub fn verify(&self) -> Result<(), EnvelopeError> {
+        let expected = Self::new(self.message.clone(), self.network.clone())?;
+        check_fields(self.version, &self.signer, self.nonce, &expected)?;
+        if self.rlp != expected.rlp || self.hash != expected.hash {
+            return Err(EnvelopeError::EncodingMismatch);
+        }
+        Ok(())
+    }
+
+    /// Verifies the envelope and signs its message with the key of the expected signer
+    pub fn sign(&self, signer: &PrivateKeySigner) -> Result<SignedEnvelope, EnvelopeError> {
+        self.verify()?;
+        if signer.address() != self.signer {
+            return Err(EnvelopeError::SignerMismatch {
+                expected: self.signer,
+                found: signer.address(),
+            });
+        }
+        Ok(SignedEnvelope {
+            version: ENVELOPE_VERSION,
+            network: self.network.clone(),
+            signer: self.signer,
+            nonce: self.nonce,
+            message: self.message.clone().sign(signer, self.network.as_deref())?,
+            rlp: self.rlp.clone(),
+        })
+    }
+}
+
+impl SignedEnvelope {
+    /// Checks that the envelope fields are consistent with the message,
+    /// and that the message is signed by its sender
+    pub fn verify(&self) -> Result<(), EnvelopeError> {
+        let expected = UnsignedEnvelope::new(self.message.unsigned(), self.network.clone())?;
+        check_fields(self.version, &self.signer, self.nonce, &expected)?;
+        if self.rlp != expected.rlp || self.message.hash() != expected.hash {
+            return Err(EnvelopeError::EncodingMismatch);
+        }
+        if self.message.recover_signer(self.network.as_deref()) != Some(self.signer) {
+            return Err(EnvelopeError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+fn check_fields(
+    version: u32,
+    signer: &Address,
+    nonce: u64,
+    expected: &UnsignedEnvelope,
+) -> Result<(), EnvelopeError> {
+    if version != ENVELOPE_VERSION {
+        return Err(EnvelopeError::UnsupportedVersion(version));
+    }
+    if *signer != expected.signer {
+        return Err(EnvelopeError::SignerMismatch {
+            expected: expected.signer,
+            found: *signer,
+        });
+    }
+    if nonce != expected.nonce {
+        return Err(EnvelopeError::NonceMismatch {
+            expected: expected.nonce,
+            found: nonce,
+        });
+    }
+    Ok(())
+}
+
+/// A human-readable description of the message
+impl fmt::Display for UnsignedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vsl = |amount: &str| match Amount::from_hex_str(amount) {
+            Ok(amount) => format!("{} VSL", amount.to_str_with_decimals(crate::VSL_DECIMALS)),
+            Err(_) => format!("{amount} (invalid amount)"),
+        };
+        match self {
+            UnsignedMessage::SubmitClaim(message) => {
+                writeln!(f, "submit claim of type {:?}", message.claim_type)?;
+                writeln!(f, "  claim:     {}", message.claim)?;
+                writeln!(f, "  proof:     {}", message.proof)?;
+                writeln!(f, "  verifiers: {}", message.to.join(", "))?;
+                writeln!(f, "  quorum:    {}", message.quorum)?;
+                writeln!(f, "  expires:   {}", message.expires.to_rfc3339())?;
+                write!(f, "  fee:       {}", vsl(&message.fee))
+            }
+            UnsignedMessage::SettleClaim(message) => {
+                write!(f, "settle claim {}", message.target_claim_id)
+            }
+            UnsignedMessage::Pay(message) => {
+                write!(f, "pay {} to {}", vsl(&message.amount), message.to)
+            }
+            UnsignedMessage::CreateAsset(message) => {
+                let total_supply = match Amount::from_hex_str(&message.total_supply) {
+                    Ok(amount) => amount.to_str_with_decimals(message.decimals),
+                    Err(_) => format!("{} (invalid amount)", message.total_supply),
+                };
+                write!(
+                    f,
+                    "create asset {} with {} decimals and a total supply of {}",
+                    message.ticker_symbol, message.decimals, total_supply
+                )
+            }
+            UnsignedMessage::TransferAsset(message) => write!(
+                f,
+                "transfer {} subunits of asset {} to {}",
+                Amount::from_hex_str(&message.amount)
+                    .map(|amount| amount.to_string())
+                    .unwrap_or_else(|_| format!("{} (invalid amount)", message.amount)),
+                message.asset_id,
+                message.to
+            ),
+            UnsignedMessage::SetState(message) => write!(f, "set state to {}", message.state),
+        }
+    }
+}
+
+/// A human-readable description of the envelope, for checking it before signing
+impl fmt::Display for UnsignedEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "unsigned {} message", self.message.kind())?;
+        writeln!(f, "network: {}", self.network.as_deref().unwrap_or("-"))?;
+        writeln!(f, "signer:  {}", self.signer)?;
+        writeln!(f, "nonce:   {}", self.nonce)?;
+        writeln!(f, "hash:    {}", self.hash)?;
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A human-readable description of the envelope, for checking it before broadcasting
+impl fmt::Display for SignedEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.verify() {
+            Ok(()) => "valid".to_string(),
+            Err(error) => format!("INVALID: {error}"),
+        };
+        writeln!(
+            f,
+            "signed {} message ({status})",
+            self.message.unsigned().kind()
+        )?;
+        writeln!(f, "network: {}", self.network.as_deref().unwrap_or("-"))?;
+        writeln!(f, "signer:  {}", self.signer)?;
+        writeln!(f, "nonce:   {}", self.nonce)?;
+        writeln!(f, "hash:    {}", self.message.hash())?;
+        write!(f, "{}", self.message.unsigned())
+    }
+}
+
+/// Verifies the signed envelope and sends its message to the matching RPC method
+pub async fn broadcast<T: ClientT>(
+    rpc_client: &T,
+    envelope: &SignedEnvelope,
+) -> RpcWrapperResult<BroadcastReceipt> {
+    envelope.verify()?;
+    let method = envelope.message.method();
+    let response: String = match &envelope.message {
+        SignedMessage::CreateAsset(signed) => {
+            let response: CreateAssetResult =
+                rpc_client.request(method, rpc_params![signed]).await?;
+            return Ok(BroadcastReceipt::Asset {
+                asset_id: response.asset_id.parse()?,
+                claim_id: response.claim_id.parse()?,
+            });
+        }
+        message => {
+            with_message!(SignedMessage, message, signed => rpc_client.request(method, rpc_params![signed]).await?)
+        }
+    };
+    Ok(BroadcastReceipt::Claim(response.parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{DEVNET, LOCAL};
+
+    fn envelope(signer: &PrivateKeySigner) -> UnsignedEnvelope {
+        let message = UnsignedMessage::pay(
+            &signer.address(),
+            7,
+            &Address::repeat_byte(0xff),
+            &Amount::from_vsl_tokens(2),
+        );
+        UnsignedEnvelope::new(message, Some(DEVNET.name.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_sign_round_trip() {
+        let signer = PrivateKeySigner::random();
+        let unsigned = envelope(&signer);
+        assert_eq!(unsigned.nonce, 7);
+        assert_eq!(unsigned.signer, signer.address());
+        // the envelope survives the trip to the offline machine
+        let json = serde_json::to_string(&unsigned).unwrap();
+        let unsigned: UnsignedEnvelope = serde_json::from_str(&json).unwrap();
+        unsigned.verify().unwrap();
+        let signed = unsigned.sign(&signer).unwrap();
+        assert_eq!(signed.message.hash(), unsigned.hash);
+        // ... and back
+        let json = serde_json::to_value(&signed).unwrap();
+        assert_eq!(json["kind"], "pay");
+        assert_eq!(json["message"]["hash"], unsigned.hash.to_string());
+        let signed: SignedEnvelope = serde_json::from_value(json).unwrap();
+        signed.verify().unwrap();
+        assert_eq!(signed.message.method(), "vsl_pay");
+        let SignedMessage::Pay(pay) = &signed.message else {
+            panic!("expected a pay message");
+        };
+        assert!(PayMessage::check_for(pay, Some(DEVNET.domain())));
+        assert!(!PayMessage::check(pay));
+    }
+
+    #[test]
+    fn test_sign_with_wrong_key() {
+        let unsigned = envelope(&PrivateKeySigner::random());
+        assert!(matches!(
+            unsigned.sign(&PrivateKeySigner::random()),
+            Err(EnvelopeError::SignerMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tampered_envelopes() {
+        let signer = PrivateKeySigner::random();
+        let mut unsigned = envelope(&signer);
+        unsigned.nonce = 8;
+        assert!(matches!(
+            unsigned.verify(),
+            Err(EnvelopeError::NonceMismatch { .. })
+        ));
+        let mut unsigned = envelope(&signer);
+        let UnsignedMessage::Pay(pay) = &mut unsigned.message else {
+            panic!("expected a pay message");
+        };
+        pay.amount = Amount::from_vsl_tokens(2_000).to_hex_str();
+        assert!(matches!(
+            unsigned.verify(),
+            Err(EnvelopeError::EncodingMismatch)
+        ));
+
+        let signed = envelope(&signer).sign(&signer).unwrap();
+        let mut json = serde_json::to_value(&signed).unwrap();
+        json["message"]["to"] = serde_json::json!(Address::repeat_byte(0xee).to_string());
+        let tampered: SignedEnvelope = serde_json::from_value(json).unwrap();
+        assert!(tampered.verify().is_err());
+
+        // the signature is only valid for the network of the envelope
+        let mut replayed = envelope(&signer).sign(&signer).unwrap();
+        replayed.network = Some(LOCAL.name.to_string());
+        assert!(matches!(
+            replayed.verify(),
+            Err(EnvelopeError::EncodingMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let signer = PrivateKeySigner::random();
+        let unsigned = envelope(&signer);
+        let text = unsigned.to_string();
+        assert!(text.contains("unsigned pay message"));
+        assert!(text.contains("pay 2 VSL to 0xFFfF"));
+        let signed = unsigned.sign(&signer).unwrap();
+        assert!(signed.to_string().starts_with("signed pay message (valid)"));
+    }
+}
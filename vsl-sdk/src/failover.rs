@@ -0,0 +1,547 @@
+//! # Failover Module
+//!
+//! A JSON-RPC client over several VSL endpoints. [FailoverClient] implements
+//! [ClientT] (hence [crate::rpc_service::ClaimRpcClient], and can be used with
+//! [crate::rpc_wrapper::RpcWrapper]):
+//!
+//! - reads are sent to the endpoints in [Selection] order, healthy endpoints first,
+//!   failing over to the next one when an endpoint cannot be reached;
+//! - writes (the [WRITE_METHODS]) are sent to the primary endpoint only, the first
+//!   healthy one in configured order (whatever the [Selection]), so that consecutive
+//!   writes do not race between nodes which may not be in sync: if it cannot be
+//!   reached, the write may still have been applied, and resending it to another
+//!   node would fail with a misleading nonce error;
+//! - in quorum mode ([FailoverClient::with_quorum]), reads are sent to several
+//!   endpoints at once, and fail unless all of them return the same result.
+//!
+//! The health of the endpoints is updated by the requests, and checked with
+//! `vsl_getHealth` by [FailoverClient::check_health] (which also measures their
+//! latency).
+//!
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::{BatchResponse, ClientT, Subscription, SubscriptionClientT};
+use jsonrpsee::core::params::BatchRequestBuilder;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::tokio;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+
+/// The methods changing the ledger state
+pub const WRITE_METHODS: [&str; 6] = [
+    "vsl_submitClaim",
+    "vsl_settleClaim",
+    "vsl_pay",
+    "vsl_createAsset",
+    "vsl_transferAsset",
+    "vsl_setAccountState",
+];
+
+/// Whether the method changes the ledger state
+pub fn is_write_method(method: &str) -> bool {
+    WRITE_METHODS.contains(&method)
+}
+
+/// Whether the error means that the endpoint could not be reached (as opposed
+/// to an answer of the endpoint)
+pub fn is_unavailable(error: &ClientError) -> bool {
+    matches!(
+        error,
+        ClientError::Transport(_)
+            | ClientError::RestartNeeded(_)
+            | ClientError::RequestTimeout
+            | ClientError::ServiceDisconnect
+    )
+}
+
+/// How the endpoint of a read is selected (among the healthy endpoints)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// Each read starts with the endpoint after the one of the previous read
+    #[default]
+    RoundRobin,
+    /// Reads start with the endpoint with the lowest measured latency
+    LowestLatency,
+}
+
+/// The last known status of an endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub name: String,
+    pub healthy: bool,
+    /// The latency of the last health check, if any succeeded
+    pub latency: Option<Duration>,
+}
+
+struct Endpoint<C> {
+    client: C,
+    status: Mutex<EndpointStatus>,
+}
+
+/// Already serialized parameters, which can be sent to several endpoints
+#[derive(Clone)]
+struct RawParams(Option<Box<RawValue>>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
+
+/// A client over several endpoints, with health-based failover
+pub struct FailoverClient<C> {
+    endpoints: Arc<[Endpoint<C>]>,
+    selection: Selection,
+    quorum: Option<usize>,
+    next: Arc<AtomicUsize>,
+}
+
+impl<C> Clone for FailoverClient<C> {
+    fn clone(&self) -> Self {
+        FailoverClient {
+            endpoints: self.endpoints.clone(),
+            selection: self.selection,
+            quorum: self.quorum,
+            next: self.next.clone(),
+        }
+    }
+}
+
+impl FailoverClient<HttpClient> {
+    /// A client over HTTP endpoints (named by their URL)
+    pub fn http<S: AsRef<str>>(urls: &[S]) -> Result<Self, ClientError> {
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                let client = HttpClientBuilder::new().build(url.as_ref())?;
+                Ok((url.as_ref().to_string(), client))
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+        Ok(FailoverClient::new(endpoints))
+    }
+}
+
+impl<C> FailoverClient<C> {
+    /// A client over the given (named) endpoints, in order of preference;
+    /// all of them are deemed healthy until proven otherwise
+    pub fn new<I: IntoIterator<Item = (String, C)>>(endpoints: I) -> Self {
+        let endpoints = endpoints
+            .into_iter()
+            .map(|(name, client)| Endpoint {
+                client,
+                status: Mutex::new(EndpointStatus {
+                    name,
+                    healthy: true,
+                    latency: None,
+                }),
+            })
+            .collect();
+        FailoverClient {
+            endpoints,
+            selection: Selection::default(),
+            quorum: None,
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn with_selection(self, selection: Selection) -> Self {
+        FailoverClient { selection, ..self }
+    }
+
+    /// Sends the reads to `size` endpoints, and fails if their results differ
+    /// (or if any of them fails)
+    pub fn with_quorum(self, size: usize) -> Self {
+        FailoverClient {
+            quorum: Some(size.max(1)),
+            ..self
+        }
+    }
+
+    /// The last known status of the endpoints
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.status.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// The indexes of the endpoints, in the order they should be tried
+    fn order(&self) -> Vec<usize> {
+        let count = self.endpoints.len();
+        if count == 0 {
+            return vec![];
+        }
+        let status = self.status();
+        let mut order: Vec<usize> = match self.selection {
+            Selection::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % count;
+                (0..count).map(|i| (start + i) % count).collect()
+            }
+            Selection::LowestLatency => {
+                let mut order: Vec<usize> = (0..count).collect();
+                order.sort_by_key(|&i| status[i].latency.unwrap_or(Duration::MAX));
+                order
+            }
+        };
+        // stable: the order is kept among healthy (and unhealthy) endpoints
+        order.sort_by_key(|&i| !status[i].healthy);
+        order
+    }
+
+    fn record<T>(&self, index: usize, result: &Result<T, ClientError>) {
+        let healthy = !matches!(result, Err(error) if is_unavailable(error));
+        self.endpoints[index].status.lock().unwrap().healthy = healthy;
+    }
+
+    fn name(&self, index: usize) -> String {
+        self.endpoints[index].status.lock().unwrap().name.clone()
+    }
+}
+
+fn no_endpoint() -> ClientError {
+    ClientError::Custom("no endpoint available".to_string())
+}
+
+impl<C: ClientT + Send + Sync + 'static> FailoverClient<C> {
+    /// Checks the health of all the endpoints (with `vsl_getHealth`), and returns
+    /// the number of healthy endpoints
+    pub async fn check_health(&self) -> usize {
+        let mut checks = tokio::task::JoinSet::new();
+        for index in 0..self.endpoints.len() {
+            let endpoints = self.endpoints.clone();
+            checks.spawn(async move {
+                let start = Instant::now();
+                let health = endpoints[index]
+                    .client
+                    .request::<String, _>("vsl_getHealth", RawParams(None))
+                    .await;
+                (index, health.map(|_| start.elapsed()))
+            });
+        }
+        let mut healthy = 0;
+        while let Some(Ok((index, latency))) = checks.join_next().await {
+            let mut status = self.endpoints[index].status.lock().unwrap();
+            status.healthy = latency.is_ok();
+            if let Ok(latency) = latency {
+                status.latency = Some(latency);
+                healthy += 1;
+            }
+        }
+        healthy
+    }
+
+    /// Checks the health of the endpoints periodically (never returns)
+    pub async fn monitor_health(&self, period: Duration) {
+        loop {
+            self.check_health().await;
+            tokio::time::sleep(period).await;
+        }
+    }
+
+    /// Records the result of the endpoint, and returns it unless the endpoint
+    /// could not be reached (in which case the next endpoint should be tried)
+    fn failover<T>(
+        &self,
+        index: usize,
+        result: Result<T, ClientError>,
+        last_error: &mut ClientError,
+    ) -> Option<Result<T, ClientError>> {
+        self.record(index, &result);
+        match result {
+            Err(error) if is_unavailable(&error) => {
+                *last_error = error;
+                None
+            }
+            result => Some(result),
+        }
+    }
+
+    /// The index of the endpoint of the requests which must not be resent: the
+    /// first healthy one in configured order (or the first one if none is)
+    fn primary(&self) -> Result<usize, ClientError> {
+        if self.endpoints.is_empty() {
+            return Err(no_endpoint());
+        }
+        let status = self.status();
+        Ok(status.iter().position(|status| status.healthy).unwrap_or(0))
+    }
+
+    async fn quorum_request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: RawParams,
+        size: usize,
+    ) -> Result<R, ClientError> {
+        let order = self.order();
+        if order.len() < size {
+            return Err(ClientError::Custom(format!(
+                "quorum of {size} needs more than {} endpoints",
+                order.len()
+            )));
+        }
+        let mut requests = tokio::task::JoinSet::new();
+        for index in order.into_iter().take(size) {
+            let endpoints = self.endpoints.clone();
+            let (method, params) = (method.to_string(), params.clone());
+            requests.spawn(async move {
+                let client = &endpoints[index].client;
+                let result = client
+                    .request::<serde_json::Value, _>(&method, params)
+                    .await;
+                (index, result)
+            });
+        }
+        let mut agreed: Option<(usize, serde_json::Value)> = None;
+        while let Some(joined) = requests.join_next().await {
+            let (index, result) = joined.map_err(|e| ClientError::Custom(e.to_string()))?;
+            self.record(index, &result);
+            let value = result?;
+            match &agreed {
+                None => agreed = Some((index, value)),
+                Some((first, agreed)) if *agreed != value => {
+                    return Err(ClientError::Custom(format!(
+                        "quorum read of {method} failed: {} and {} disagree",
+                        self.name(*first),
+                        self.name(index)
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        let (_, value) = agreed.ok_or_else(no_endpoint)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl<C: ClientT + Send + Sync + 'static> ClientT for FailoverClient<C> {
+    /// Notifications are sent to the primary endpoint only
+    async fn notification<Params>(&self, method: &str, params: Params) -> Result<(), ClientError>
+    where
+        Params: ToRpcParams + Send,
+    {
+        let index = self.primary()?;
+        self.endpoints[index]
+            .client
+            .notification(method, params)
+            .await
+    }
+
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        if is_write_method(method) {
+            let index = self.primary()?;
+            let result = self.endpoints[index].client.request(method, params).await;
+            self.record(index, &result);
+            return result;
+        }
+        let params = RawParams(params.to_rpc_params()?);
+        if let Some(size) = self.quorum {
+            return self.quorum_request(method, params, size).await;
+        }
+        let mut last_error = no_endpoint();
+        for index in self.order() {
+            let client = &self.endpoints[index].client;
+            let result = client.request(method, params.clone()).await;
+            if let Some(result) = self.failover(index, result, &mut last_error) {
+                return result;
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Batches may contain writes: they are sent to the primary endpoint only
+    async fn batch_request<'a, R>(
+        &self,
+        batch: BatchRequestBuilder<'a>,
+    ) -> Result<BatchResponse<'a, R>, ClientError>
+    where
+        R: DeserializeOwned + std::fmt::Debug + 'a,
+    {
+        let index = self.primary()?;
+        let result = self.endpoints[index].client.batch_request(batch).await;
+        self.record(index, &result);
+        result
+    }
+}
+
+impl<C: SubscriptionClientT + Send + Sync + 'static> SubscriptionClientT for FailoverClient<C> {
+    async fn subscribe<'a, Notif, Params>(
+        &self,
+        subscribe_method: &'a str,
+        params: Params,
+        unsubscribe_method: &'a str,
+    ) -> Result<Subscription<Notif>, ClientError>
+    where
+        Params: ToRpcParams + Send,
+        Notif: DeserializeOwned,
+    {
+        let params = RawParams(params.to_rpc_params()?);
+        let mut last_error = no_endpoint();
+        for index in self.order() {
+            let client = &self.endpoints[index].client;
+            let result = client
+                .subscribe(subscribe_method, params.clone(), unsubscribe_method)
+                .await;
+            if let Some(result) = self.failover(index, result, &mut last_error) {
+                return result;
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Method subscriptions are registered with the primary endpoint only
+    async fn subscribe_to_method<Notif>(
+        &self,
+        method: &str,
+    ) -> Result<Subscription<Notif>, ClientError>
+    where
+        Notif: DeserializeOwned,
+    {
+        let index = self.primary()?;
+        self.endpoints[index]
+            .client
+            .subscribe_to_method(method)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::core::RpcResult;
+    use jsonrpsee::server::ServerHandle;
+
+    use super::*;
+    use crate::rpc_service::ClaimRpcClient;
+    use crate::test_node;
+
+    /// A stand-in node answering `vsl_getBalance` (and `vsl_pay`) with the given balance
+    async fn serve(balance: &'static str) -> (String, ServerHandle) {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("vsl_getHealth", |_, _, _| -> RpcResult<String> {
+                Ok("ok".to_string())
+            })
+            .unwrap();
+        module
+            .register_method("vsl_getBalance", move |_, _, _| -> RpcResult<String> {
+                Ok(balance.to_string())
+            })
+            .unwrap();
+        module
+            .register_method("vsl_pay", move |_, _, _| -> RpcResult<String> {
+                Ok(balance.to_string())
+            })
+            .unwrap();
+        let (address, handle) = test_node::start(module).await;
+        (format!("http://{address}"), handle)
+    }
+
+    async fn stop(handle: ServerHandle) {
+        handle.stop().unwrap();
+        handle.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_and_failover() {
+        let (first, first_handle) = serve("0x1").await;
+        let (second, _second_handle) = serve("0x2").await;
+        let client = FailoverClient::http(&[first, second]).unwrap();
+        let account = "0x0000000000000000000000000000000000000000".to_string();
+
+        let mut balances = vec![];
+        for _ in 0..4 {
+            balances.push(client.get_balance(account.clone()).await.unwrap());
+        }
+        assert_eq!(balances, ["0x1", "0x2", "0x1", "0x2"]);
+
+        stop(first_handle).await;
+        for _ in 0..2 {
+            assert_eq!(client.get_balance(account.clone()).await.unwrap(), "0x2");
+        }
+        let status = client.status();
+        assert!(!status[0].healthy);
+        assert!(status[1].healthy);
+        assert_eq!(client.check_health().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_writes_do_not_fail_over() {
+        let (first, first_handle) = serve("0x1").await;
+        let (second, _second_handle) = serve("0x1").await;
+        let client = FailoverClient::http(&[first, second]).unwrap();
+        stop(first_handle).await;
+        let error = client
+            .request::<String, _>("vsl_pay", RawParams(None))
+            .await
+            .unwrap_err();
+        assert!(is_unavailable(&error));
+        // the next write goes to the endpoint which is still healthy
+        let id: String = client.request("vsl_pay", RawParams(None)).await.unwrap();
+        assert_eq!(id, "0x1");
+    }
+
+    #[tokio::test]
+    async fn test_writes_pinned_to_primary() {
+        let (first, _first_handle) = serve("0x1").await;
+        let (second, _second_handle) = serve("0x2").await;
+        let client = FailoverClient::http(&[first, second]).unwrap();
+        assert_eq!(client.selection, Selection::RoundRobin);
+        let account = "0x0000000000000000000000000000000000000000".to_string();
+        // the reads rotate, the writes stay on the first endpoint
+        let mut ids = vec![];
+        for _ in 0..4 {
+            client.get_balance(account.clone()).await.unwrap();
+            ids.push(
+                client
+                    .request::<String, _>("vsl_pay", RawParams(None))
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(ids, ["0x1"; 4]);
+    }
+
+    #[tokio::test]
+    async fn test_lowest_latency() {
+        let (first, _first_handle) = serve("0x1").await;
+        let (second, _second_handle) = serve("0x2").await;
+        let client = FailoverClient::http(&[first, second])
+            .unwrap()
+            .with_selection(Selection::LowestLatency);
+        assert_eq!(client.check_health().await, 2);
+        client.endpoints[0].status.lock().unwrap().latency = Some(Duration::from_secs(1));
+        client.endpoints[1].status.lock().unwrap().latency = Some(Duration::from_millis(1));
+        assert_eq!(client.order(), [1, 0]);
+        client.endpoints[1].status.lock().unwrap().healthy = false;
+        assert_eq!(client.order(), [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_quorum_read() {
+        let (first, _first_handle) = serve("0x1").await;
+        let (second, _second_handle) = serve("0x1").await;
+        let (third, _third_handle) = serve("0x2").await;
+        let account = "0x0000000000000000000000000000000000000000".to_string();
+
+        let agreeing = FailoverClient::http(&[first.clone(), second]).unwrap();
+        let agreeing = agreeing.with_quorum(2);
+        assert_eq!(agreeing.get_balance(account.clone()).await.unwrap(), "0x1");
+
+        let disagreeing = FailoverClient::http(&[first, third]).unwrap();
+        let error = disagreeing
+            .with_quorum(2)
+            .get_balance(account.clone())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("disagree"), "{error}");
+        assert!(agreeing.with_quorum(3).get_balance(account).await.is_err());
+    }
+}
@@ -0,0 +1,1277 @@
+//! # Gateway Module
+//!
+//! A JSON-RPC gateway exposing a VSL node to third parties. [Gateway] implements
+//! [ClaimRpcServer] by proxying to an upstream [ClaimRpcClient], and:
+//!
+//! - limits the rate of the signed requests of each sender address, charging only
+//!   the requests actually signed by their sender;
+//! - caches the immutable reads (`vsl_getSettledClaimById`, `vsl_getAssetById`);
+//! - shares a single upstream subscription per topic among its subscribers.
+//!
+//! Clients authenticate with an API key, sent in the `x-api-key` (or as bearer token
+//! in the `authorization`) HTTP header: the [ApiKeyLayer] HTTP middleware extracts it,
+//! and the [GatewayLayer] RPC middleware rejects the calls with an unknown key, to a
+//! method the key is not allowed to call, or above the rate limit of the key.
+//! Rejections use the VSL error codes below (see `docs/api/rpc.md`).
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use jsonrpsee::core::middleware::RpcServiceBuilder;
+//! use jsonrpsee::server::Server;
+//! use jsonrpsee::ws_client::WsClientBuilder;
+//! use vsl_sdk::gateway::{ApiKeyLayer, Gateway, GatewayConfig, KeyPolicy, RateLimit};
+//! use vsl_sdk::rpc_service::ClaimRpcServer;
+//! use vsl_sdk::validation::Validating;
+//!
+//! let upstream = WsClientBuilder::new().build("ws://127.0.0.1:44444").await?;
+//! let config = GatewayConfig::default()
+//!     .with_key("partner-key", KeyPolicy::default().with_rate_limit(RateLimit::per_second(10)))
+//!     .with_address_rate_limit(RateLimit::per_second(1));
+//! let server = Server::builder()
+//!     .set_http_middleware(tower::ServiceBuilder::new().layer(ApiKeyLayer))
+//!     .set_rpc_middleware(RpcServiceBuilder::new().layer(config.rpc_layer()))
+//!     .build("0.0.0.0:8545")
+//!     .await?;
+//! // requests are validated before reaching the gateway (see [crate::validation])
+//! let handle = server.start(Validating::new(Gateway::new(upstream, &config)).into_rpc());
+//! handle.stopped().await;
+//! # Ok(())
+//! # }
+//! ```
+//!
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use alloy::consensus::Signed;
+use jsonrpsee::PendingSubscriptionSink;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::Subscription;
+use jsonrpsee::core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT};
+use jsonrpsee::core::{RpcResult, SubscriptionResult, async_trait};
+use jsonrpsee::server::{HttpRequest, MethodResponse};
+use jsonrpsee::tokio;
+use jsonrpsee::tokio::sync::broadcast::{self, error::RecvError};
+use jsonrpsee::types::{ErrorObjectOwned, Request};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::rpc_messages::{
+    CreateAssetMessage, CreateAssetResult, PayMessage, SetStateMessage, SettleClaimMessage,
+    SettledClaimData, SettledVerifiedClaim, SubmittedClaim, SubmittedClaimData, Timestamped,
+    TransferAssetMessage,
+};
+use crate::rpc_service::{ClaimRpcClient, ClaimRpcServer};
+use crate::{Address, B256, Clock, IntoSigned, SystemClock, Timestamp};
+
+/// No (or an unknown) API key was given
+pub const UNAUTHORIZED_CODE: i32 = -32030;
+/// The API key is not allowed to call the method
+pub const METHOD_NOT_ALLOWED_CODE: i32 = -32031;
+/// The rate limit of the API key, or of the sender address, is exceeded
+pub const RATE_LIMITED_CODE: i32 = -32032;
+/// The upstream node could not be reached
+pub const UPSTREAM_ERROR_CODE: i32 = -32033;
+
+/// The default number of entries of each cache
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// The default number of items buffered for each subscriber
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// The HTTP header holding the API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A token-bucket rate limit: up to `burst` requests at once, refilled at
+/// `per_second` requests per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    /// A limit of `n` requests per second (and in a burst)
+    pub fn per_second(n: u32) -> Self {
+        RateLimit {
+            burst: n,
+            per_second: n as f64,
+        }
+    }
+
+    pub fn with_burst(self, burst: u32) -> Self {
+        RateLimit { burst, ..self }
+    }
+}
+
+/// What an API key is allowed to do
+#[derive(Debug, Clone, Default)]
+pub struct KeyPolicy {
+    /// The methods the key may call (all methods if `None`)
+    pub allowed_methods: Option<HashSet<String>>,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl KeyPolicy {
+    /// Restricts the key to the given methods (unsubscribing is always allowed)
+    pub fn allow<I: IntoIterator<Item = S>, S: Into<String>>(self, methods: I) -> Self {
+        KeyPolicy {
+            allowed_methods: Some(methods.into_iter().map(Into::into).collect()),
+            ..self
+        }
+    }
+
+    pub fn with_rate_limit(self, rate_limit: RateLimit) -> Self {
+        KeyPolicy {
+            rate_limit: Some(rate_limit),
+            ..self
+        }
+    }
+
+    fn allows(&self, method: &str) -> bool {
+        match &self.allowed_methods {
+            None => true,
+            Some(methods) => methods.contains(method) || method.starts_with("vsl_unsubscribe"),
+        }
+    }
+}
+
+/// The configuration of the gateway and of its middleware
+#[derive(Clone)]
+pub struct GatewayConfig {
+    pub keys: HashMap<String, KeyPolicy>,
+    /// The rate limit of the signed requests of each sender address
+    pub address_rate_limit: Option<RateLimit>,
+    /// The signing domain of the signed requests (see [crate::network]), used to
+    /// recover their signer
+    pub network: Option<String>,
+    pub cache_capacity: usize,
+    pub subscription_capacity: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        GatewayConfig {
+            keys: HashMap::new(),
+            address_rate_limit: None,
+            network: None,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            subscription_capacity: DEFAULT_SUBSCRIPTION_CAPACITY,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl GatewayConfig {
+    pub fn with_key(mut self, key: &str, policy: KeyPolicy) -> Self {
+        self.keys.insert(key.to_string(), policy);
+        self
+    }
+
+    pub fn with_address_rate_limit(self, rate_limit: RateLimit) -> Self {
+        GatewayConfig {
+            address_rate_limit: Some(rate_limit),
+            ..self
+        }
+    }
+
+    /// Recovers the signer of the signed requests in the given network (see [crate::network])
+    pub fn with_network(self, network: &str) -> Self {
+        GatewayConfig {
+            network: Some(network.to_string()),
+            ..self
+        }
+    }
+
+    /// Uses the given clock for the rate limits (e.g. a [crate::ManualClock] in tests)
+    pub fn with_clock<C: Clock + 'static>(self, clock: C) -> Self {
+        GatewayConfig {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// The RPC middleware authenticating the calls (see [GatewayLayer])
+    pub fn rpc_layer(&self) -> GatewayLayer {
+        GatewayLayer {
+            auth: Arc::new(KeyAuth {
+                keys: self.keys.clone(),
+                limiter: RateLimiter::new(self.clock.clone()),
+            }),
+        }
+    }
+}
+
+/// A token bucket, with the limit it was last used with
+struct Bucket {
+    tokens: f64,
+    since: Timestamp,
+    limit: RateLimit,
+}
+
+impl Bucket {
+    /// The tokens available at `now`
+    fn refill(&self, now: &Timestamp) -> f64 {
+        let elapsed = now.saturating_duration_since(&self.since).as_secs_f64();
+        (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst as f64)
+    }
+}
+
+/// Token buckets, by key
+struct RateLimiter<K> {
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    /// The number of buckets above which the full buckets are dropped
+    const MAX_BUCKETS: usize = 65_536;
+
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        RateLimiter {
+            clock,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from the bucket of the key, if any is left
+    fn try_acquire(&self, key: K, limit: &RateLimit) -> bool {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= Self::MAX_BUCKETS {
+            // a full bucket is the same as a new one, under its own limit
+            buckets.retain(|_, bucket| bucket.refill(&now) < bucket.limit.burst as f64);
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            since: now,
+            limit: *limit,
+        });
+        bucket.limit = *limit;
+        let tokens = bucket.refill(&now);
+        if tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens = tokens - 1.0;
+        bucket.since = now;
+        true
+    }
+}
+
+/// The API key of a request, as extracted by the [ApiKeyLayer]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey(pub String);
+
+/// HTTP middleware extracting the [ApiKey] of the requests (from the `x-api-key`
+/// header, or from an `authorization: Bearer` header)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiKeyLayer;
+
+impl<S> tower::Layer<S> for ApiKeyLayer {
+    type Service = ApiKeyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyService<S> {
+    inner: S,
+}
+
+impl<S, B> tower::Service<HttpRequest<B>> for ApiKeyService<S>
+where
+    S: tower::Service<HttpRequest<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: HttpRequest<B>) -> Self::Future {
+        let headers = request.headers();
+        let key = match headers.get(API_KEY_HEADER) {
+            Some(key) => key.to_str().ok(),
+            None => headers
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer ")),
+        };
+        if let Some(key) = key.map(|key| ApiKey(key.trim().to_string())) {
+            request.extensions_mut().insert(key);
+        }
+        self.inner.call(request)
+    }
+}
+
+struct KeyAuth {
+    keys: HashMap<String, KeyPolicy>,
+    limiter: RateLimiter<String>,
+}
+
+impl KeyAuth {
+    fn check(&self, request: &Request<'_>) -> Result<(), ErrorObjectOwned> {
+        let method = request.method_name();
+        let Some(ApiKey(key)) = request.extensions().get::<ApiKey>() else {
+            return Err(error(UNAUTHORIZED_CODE, "missing API key"));
+        };
+        let Some(policy) = self.keys.get(key) else {
+            return Err(error(UNAUTHORIZED_CODE, "unknown API key"));
+        };
+        if !policy.allows(method) {
+            return Err(error(
+                METHOD_NOT_ALLOWED_CODE,
+                format!("{method} is not allowed for this API key"),
+            ));
+        }
+        if let Some(limit) = &policy.rate_limit
+            && !self.limiter.try_acquire(key.clone(), limit)
+        {
+            return Err(error(
+                RATE_LIMITED_CODE,
+                "rate limit of the API key exceeded",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn error(code: i32, message: impl Into<String>) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(code, message, None::<()>)
+}
+
+/// RPC middleware authenticating the calls by their [ApiKey]
+/// (see [GatewayConfig::rpc_layer])
+#[derive(Clone)]
+pub struct GatewayLayer {
+    auth: Arc<KeyAuth>,
+}
+
+impl<S> tower::Layer<S> for GatewayLayer {
+    type Service = GatewayMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GatewayMiddleware {
+            inner,
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GatewayMiddleware<S> {
+    inner: S,
+    auth: Arc<KeyAuth>,
+}
+
+impl<S> RpcServiceT for GatewayMiddleware<S>
+where
+    S: RpcServiceT<
+            MethodResponse = MethodResponse,
+            BatchResponse = MethodResponse,
+            NotificationResponse = MethodResponse,
+        > + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = MethodResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, request: Request<'a>) -> impl Future<Output = MethodResponse> + Send + 'a {
+        let checked = self.auth.check(&request);
+        let inner = self.inner.clone();
+        async move {
+            match checked {
+                Ok(()) => inner.call(request).await,
+                Err(error) => MethodResponse::error(request.id(), error),
+            }
+        }
+    }
+
+    fn batch<'a>(&self, mut batch: Batch<'a>) -> impl Future<Output = MethodResponse> + Send + 'a {
+        for entry in batch.iter_mut() {
+            if let Ok(BatchEntry::Call(request)) = entry
+                && let Err(error) = self.auth.check(request)
+            {
+                *entry = Err(BatchEntryErr::new(request.id(), error));
+            }
+        }
+        self.inner.batch(batch)
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = MethodResponse> + Send + 'a {
+        self.inner.notification(notification)
+    }
+}
+
+/// A bounded cache, evicting the oldest entries first
+struct Cache<V> {
+    capacity: usize,
+    entries: Mutex<(HashMap<String, V>, VecDeque<String>)>,
+}
+
+impl<V: Clone> Cache<V> {
+    fn new(capacity: usize) -> Self {
+        Cache {
+            capacity,
+            entries: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<V> {
+        self.entries.lock().unwrap().0.get(key).cloned()
+    }
+
+    fn insert(&self, key: String, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let (entries, order) = &mut *self.entries.lock().unwrap();
+        if entries.insert(key.clone(), value).is_none() {
+            order.push_back(key);
+        }
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// The canonical form of a 256 bit ID, for use as cache key
+fn id_key(id: &str) -> String {
+    B256::from_str(id).map_or_else(|_| id.to_string(), |id| id.to_string())
+}
+
+/// Shares one upstream subscription per topic among downstream subscribers.
+///
+/// The upstream subscription is dropped when an item arrives and no one is
+/// subscribed anymore.
+struct FanOut<T> {
+    capacity: usize,
+    topics: tokio::sync::Mutex<HashMap<Option<String>, broadcast::WeakSender<T>>>,
+}
+
+impl<T: Clone + Send + DeserializeOwned + 'static> FanOut<T> {
+    fn new(capacity: usize) -> Self {
+        FanOut {
+            capacity,
+            topics: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn subscribe<F>(
+        &self,
+        topic: Option<String>,
+        upstream: F,
+    ) -> Result<broadcast::Receiver<T>, ClientError>
+    where
+        F: Future<Output = Result<Subscription<T>, ClientError>>,
+    {
+        let mut topics = self.topics.lock().await;
+        if let Some(sender) = topics.get(&topic).and_then(broadcast::WeakSender::upgrade) {
+            return Ok(sender.subscribe());
+        }
+        let mut subscription = upstream.await?;
+        let (sender, receiver) = broadcast::channel(self.capacity);
+        topics.insert(topic, sender.downgrade());
+        tokio::spawn(async move {
+            while let Some(item) = subscription.next().await {
+                let Ok(item) = item else {
+                    continue;
+                };
+                if sender.send(item).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+/// Forwards the items of a shared subscription to a downstream subscriber
+async fn forward<T: Clone + Serialize>(
+    pending: PendingSubscriptionSink,
+    receiver: Result<broadcast::Receiver<T>, ClientError>,
+) -> SubscriptionResult {
+    let mut receiver = match receiver {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            pending.reject(upstream_error(e)).await;
+            return Ok(());
+        }
+    };
+    let sink = pending.accept().await?;
+    loop {
+        let item = tokio::select! {
+            item = receiver.recv() => item,
+            _ = sink.closed() => break,
+        };
+        match item {
+            Ok(item) => sink.send(serde_json::value::to_raw_value(&item)?).await?,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Errors returned by the upstream node are passed on as they are
+fn upstream_error(error: ClientError) -> ErrorObjectOwned {
+    match error {
+        ClientError::Call(error) => error,
+        error => ErrorObjectOwned::owned(
+            UPSTREAM_ERROR_CODE,
+            format!("upstream error: {error}"),
+            None::<()>,
+        ),
+    }
+}
+
+/// A [ClaimRpcServer] proxying the calls to an upstream node
+pub struct Gateway<C> {
+    upstream: C,
+    address_rate_limit: Option<RateLimit>,
+    address_limiter: RateLimiter<Address>,
+    network: Option<String>,
+    settled_claims: Cache<Timestamped<Signed<SettledVerifiedClaim>>>,
+    assets: Cache<CreateAssetMessage>,
+    settled_metadata: FanOut<Timestamped<SettledClaimData>>,
+    submitted_metadata: FanOut<Timestamped<SubmittedClaimData>>,
+    settled_for_receiver: FanOut<Timestamped<Signed<SettledVerifiedClaim>>>,
+    submitted_for_receiver: FanOut<Timestamped<Signed<SubmittedClaim>>>,
+}
+
+impl<C> Gateway<C> {
+    pub fn new(upstream: C, config: &GatewayConfig) -> Self {
+        let capacity = config.subscription_capacity;
+        Gateway {
+            upstream,
+            address_rate_limit: config.address_rate_limit,
+            address_limiter: RateLimiter::new(config.clock.clone()),
+            network: config.network.clone(),
+            settled_claims: Cache::new(config.cache_capacity),
+            assets: Cache::new(config.cache_capacity),
+            settled_metadata: FanOut::new(capacity),
+            submitted_metadata: FanOut::new(capacity),
+            settled_for_receiver: FanOut::new(capacity),
+            submitted_for_receiver: FanOut::new(capacity),
+        }
+    }
+
+    /// Enforces the rate limit of the sender of a signed request. Only requests
+    /// signed by their sender are charged, so that a forged sender cannot use up
+    /// the budget of another address (the others are left for the upstream node
+    /// to reject).
+    fn limit_sender<T: IntoSigned>(&self, signed: &Signed<T>) -> RpcResult<()> {
+        let Some(limit) = &self.address_rate_limit else {
+            return Ok(());
+        };
+        if let Ok(sender) = T::verify_for(signed, self.network.as_deref())
+            && !self.address_limiter.try_acquire(sender, limit)
+        {
+            return Err(error(
+                RATE_LIMITED_CODE,
+                format!("rate limit of {sender} exceeded"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: ClaimRpcClient + Send + Sync + 'static> ClaimRpcServer for Gateway<C> {
+    async fn submit_claim(&self, claim: Signed<SubmittedClaim>) -> RpcResult<String> {
+        self.limit_sender(&claim)?;
+        self.upstream
+            .submit_claim(claim)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn settle_claim(&self, settled_claim: Signed<SettleClaimMessage>) -> RpcResult<String> {
+        self.limit_sender(&settled_claim)?;
+        self.upstream
+            .settle_claim(settled_claim)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_settled_claims_metadata(
+        &self,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<SettledClaimData>>> {
+        self.upstream
+            .list_settled_claims_metadata(since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_submitted_claims_metadata(
+        &self,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<SubmittedClaimData>>> {
+        self.upstream
+            .list_submitted_claims_metadata(since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_settled_claims_for_receiver(
+        &self,
+        address: Option<String>,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+        self.upstream
+            .list_settled_claims_for_receiver(address, since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_submitted_claims_for_receiver(
+        &self,
+        address: String,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+        self.upstream
+            .list_submitted_claims_for_receiver(address, since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_settled_claims_for_sender(
+        &self,
+        address: String,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+        self.upstream
+            .list_settled_claims_for_sender(address, since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn list_submitted_claims_for_sender(
+        &self,
+        address: Option<String>,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+        self.upstream
+            .list_submitted_claims_for_sender(address, since)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_claim_data_by_id(&self, claim_id: String) -> RpcResult<String> {
+        self.upstream
+            .get_claim_data_by_id(claim_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_proof_by_id(&self, claim_id: String) -> RpcResult<String> {
+        self.upstream
+            .get_proof_by_id(claim_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_submitted_claim_by_id(
+        &self,
+        claim_id: String,
+    ) -> RpcResult<Timestamped<Signed<SubmittedClaim>>> {
+        self.upstream
+            .get_submitted_claim_by_id(claim_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_settled_claim_by_id(
+        &self,
+        claim_id: String,
+    ) -> RpcResult<Timestamped<Signed<SettledVerifiedClaim>>> {
+        let key = id_key(&claim_id);
+        if let Some(claim) = self.settled_claims.get(&key) {
+            return Ok(claim);
+        }
+        let claim = self
+            .upstream
+            .get_settled_claim_by_id(claim_id)
+            .await
+            .map_err(upstream_error)?;
+        self.settled_claims.insert(key, claim.clone());
+        Ok(claim)
+    }
+
+    async fn pay(&self, payment: Signed<PayMessage>) -> RpcResult<String> {
+        self.limit_sender(&payment)?;
+        self.upstream.pay(payment).await.map_err(upstream_error)
+    }
+
+    async fn get_account(&self, account_id: String) -> RpcResult<String> {
+        self.upstream
+            .get_account(account_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_balance(&self, account_id: String) -> RpcResult<String> {
+        self.upstream
+            .get_balance(account_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_asset_balance(&self, account_id: String, asset_id: String) -> RpcResult<String> {
+        self.upstream
+            .get_asset_balance(account_id, asset_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_asset_balances(&self, account_id: String) -> RpcResult<HashMap<String, String>> {
+        self.upstream
+            .get_asset_balances(account_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn create_asset(
+        &self,
+        asset_data: Signed<CreateAssetMessage>,
+    ) -> RpcResult<CreateAssetResult> {
+        self.limit_sender(&asset_data)?;
+        self.upstream
+            .create_asset(asset_data)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn transfer_asset(
+        &self,
+        transfer_asset: Signed<TransferAssetMessage>,
+    ) -> RpcResult<String> {
+        self.limit_sender(&transfer_asset)?;
+        self.upstream
+            .transfer_asset(transfer_asset)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_asset_by_id(&self, asset_id: String) -> RpcResult<Option<CreateAssetMessage>> {
+        let key = id_key(&asset_id);
+        if let Some(asset) = self.assets.get(&key) {
+            return Ok(Some(asset));
+        }
+        let asset = self
+            .upstream
+            .get_asset_by_id(asset_id)
+            .await
+            .map_err(upstream_error)?;
+        // unknown assets may be created later
+        if let Some(asset) = &asset {
+            self.assets.insert(key, asset.clone());
+        }
+        Ok(asset)
+    }
+
+    async fn get_state(&self, account_id: String) -> RpcResult<Option<String>> {
+        self.upstream
+            .get_state(account_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn set_state(&self, state: Signed<SetStateMessage>) -> RpcResult<String> {
+        self.limit_sender(&state)?;
+        self.upstream.set_state(state).await.map_err(upstream_error)
+    }
+
+    async fn get_nonce(&self, account_id: String) -> RpcResult<u64> {
+        self.upstream
+            .get_nonce(account_id)
+            .await
+            .map_err(upstream_error)
+    }
+
+    async fn get_health(&self) -> RpcResult<String> {
+        self.upstream.get_health().await.map_err(upstream_error)
+    }
+
+    async fn get_network(&self) -> RpcResult<Option<String>> {
+        self.upstream.get_network().await.map_err(upstream_error)
+    }
+
+    async fn subscribe_to_settled_claims_metadata(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let upstream = self.upstream.subscribe_to_settled_claims_metadata();
+        forward(
+            pending,
+            self.settled_metadata.subscribe(None, upstream).await,
+        )
+        .await
+    }
+
+    async fn subscribe_to_submitted_claims_metadata(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let upstream = self.upstream.subscribe_to_submitted_claims_metadata();
+        forward(
+            pending,
+            self.submitted_metadata.subscribe(None, upstream).await,
+        )
+        .await
+    }
+
+    async fn subscribe_to_settled_claims_for_receiver(
+        &self,
+        pending: PendingSubscriptionSink,
+        address: Option<String>,
+    ) -> SubscriptionResult {
+        let topic = address.as_ref().map(|address| address.to_lowercase());
+        let upstream = self
+            .upstream
+            .subscribe_to_settled_claims_for_receiver(address);
+        let receiver = self.settled_for_receiver.subscribe(topic, upstream).await;
+        forward(pending, receiver).await
+    }
+
+    async fn subscribe_to_submitted_claims_for_receiver(
+        &self,
+        pending: PendingSubscriptionSink,
+        address: String,
+    ) -> SubscriptionResult {
+        let topic = Some(address.to_lowercase());
+        let upstream = self
+            .upstream
+            .subscribe_to_submitted_claims_for_receiver(address);
+        let receiver = self.submitted_for_receiver.subscribe(topic, upstream).await;
+        forward(pending, receiver).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use alloy::signers::local::PrivateKeySigner;
+    use jsonrpsee::core::middleware::RpcServiceBuilder;
+    use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder};
+    use jsonrpsee::server::{Server, ServerHandle};
+    use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+
+    use super::*;
+    use crate::network::DEVNET;
+    use crate::test_node;
+    use crate::{IntoSigned, ManualClock};
+
+    /// A stand-in for a VSL node, counting the calls of interest
+    struct Node {
+        asset_calls: AtomicUsize,
+        subscriptions: AtomicUsize,
+        settled: broadcast::Sender<Timestamped<SettledClaimData>>,
+    }
+
+    fn unsupported<T>() -> RpcResult<T> {
+        Err(error(-32601, "not supported by the stand-in node"))
+    }
+
+    #[async_trait]
+    impl ClaimRpcServer for Arc<Node> {
+        async fn submit_claim(&self, _: Signed<SubmittedClaim>) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn settle_claim(&self, _: Signed<SettleClaimMessage>) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn list_settled_claims_metadata(
+            &self,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<SettledClaimData>>> {
+            unsupported()
+        }
+        async fn list_submitted_claims_metadata(
+            &self,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<SubmittedClaimData>>> {
+            unsupported()
+        }
+        async fn list_settled_claims_for_receiver(
+            &self,
+            _: Option<String>,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+            unsupported()
+        }
+        async fn list_submitted_claims_for_receiver(
+            &self,
+            _: String,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+            unsupported()
+        }
+        async fn list_settled_claims_for_sender(
+            &self,
+            _: String,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+            unsupported()
+        }
+        async fn list_submitted_claims_for_sender(
+            &self,
+            _: Option<String>,
+            _: Timestamp,
+        ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+            unsupported()
+        }
+        async fn get_claim_data_by_id(&self, _: String) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_proof_by_id(&self, _: String) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_submitted_claim_by_id(
+            &self,
+            _: String,
+        ) -> RpcResult<Timestamped<Signed<SubmittedClaim>>> {
+            unsupported()
+        }
+        async fn get_settled_claim_by_id(
+            &self,
+            _: String,
+        ) -> RpcResult<Timestamped<Signed<SettledVerifiedClaim>>> {
+            unsupported()
+        }
+        async fn pay(&self, payment: Signed<PayMessage>) -> RpcResult<String> {
+            Ok(payment.hash().to_string())
+        }
+        async fn get_account(&self, _: String) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_balance(&self, _: String) -> RpcResult<String> {
+            Ok("0x1".to_string())
+        }
+        async fn get_asset_balance(&self, _: String, _: String) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_asset_balances(&self, _: String) -> RpcResult<HashMap<String, String>> {
+            unsupported()
+        }
+        async fn create_asset(
+            &self,
+            _: Signed<CreateAssetMessage>,
+        ) -> RpcResult<CreateAssetResult> {
+            unsupported()
+        }
+        async fn transfer_asset(&self, _: Signed<TransferAssetMessage>) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_asset_by_id(&self, _: String) -> RpcResult<Option<CreateAssetMessage>> {
+            self.asset_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(CreateAssetMessage {
+                account_id: Address::repeat_byte(0x01).to_string(),
+                nonce: "0".to_string(),
+                ticker_symbol: "TST".to_string(),
+                decimals: 6,
+                total_supply: "0x1000".to_string(),
+            }))
+        }
+        async fn get_state(&self, _: String) -> RpcResult<Option<String>> {
+            unsupported()
+        }
+        async fn set_state(&self, _: Signed<SetStateMessage>) -> RpcResult<String> {
+            unsupported()
+        }
+        async fn get_nonce(&self, _: String) -> RpcResult<u64> {
+            unsupported()
+        }
+        async fn get_health(&self) -> RpcResult<String> {
+            Ok("ok".to_string())
+        }
+        async fn get_network(&self) -> RpcResult<Option<String>> {
+            Ok(Some(DEVNET.name.to_string()))
+        }
+        async fn subscribe_to_settled_claims_metadata(
+            &self,
+            pending: PendingSubscriptionSink,
+        ) -> SubscriptionResult {
+            self.subscriptions.fetch_add(1, Ordering::SeqCst);
+            let mut receiver = self.settled.subscribe();
+            let sink = pending.accept().await?;
+            while let Ok(item) = receiver.recv().await {
+                sink.send(serde_json::value::to_raw_value(&item)?).await?;
+            }
+            Ok(())
+        }
+        async fn subscribe_to_submitted_claims_metadata(
+            &self,
+            pending: PendingSubscriptionSink,
+        ) -> SubscriptionResult {
+            pending.reject(error(-32601, "not supported")).await;
+            Ok(())
+        }
+        async fn subscribe_to_settled_claims_for_receiver(
+            &self,
+            pending: PendingSubscriptionSink,
+            _: Option<String>,
+        ) -> SubscriptionResult {
+            pending.reject(error(-32601, "not supported")).await;
+            Ok(())
+        }
+        async fn subscribe_to_submitted_claims_for_receiver(
+            &self,
+            pending: PendingSubscriptionSink,
+            _: String,
+        ) -> SubscriptionResult {
+            pending.reject(error(-32601, "not supported")).await;
+            Ok(())
+        }
+    }
+
+    struct Setup {
+        node: Arc<Node>,
+        address: std::net::SocketAddr,
+        _handles: (ServerHandle, ServerHandle),
+    }
+
+    impl Setup {
+        async fn new(config: GatewayConfig) -> Self {
+            let node = Arc::new(Node {
+                asset_calls: AtomicUsize::new(0),
+                subscriptions: AtomicUsize::new(0),
+                settled: broadcast::channel(16).0,
+            });
+            let (node_address, node_handle) = test_node::start(node.clone().into_rpc()).await;
+
+            let upstream = WsClientBuilder::new()
+                .build(format!("ws://{node_address}"))
+                .await
+                .unwrap();
+            let server = Server::builder()
+                .set_http_middleware(tower::ServiceBuilder::new().layer(ApiKeyLayer))
+                .set_rpc_middleware(RpcServiceBuilder::new().layer(config.rpc_layer()))
+                .build("127.0.0.1:0")
+                .await
+                .unwrap();
+            let address = server.local_addr().unwrap();
+            let handle = server.start(Gateway::new(upstream, &config).into_rpc());
+            Setup {
+                node,
+                address,
+                _handles: (node_handle, handle),
+            }
+        }
+
+        fn http_client(&self, key: Option<&str>) -> HttpClient {
+            HttpClientBuilder::new()
+                .set_headers(headers(key))
+                .build(format!("http://{}", self.address))
+                .unwrap()
+        }
+
+        async fn ws_client(&self, key: &str) -> WsClient {
+            WsClientBuilder::new()
+                .set_headers(headers(Some(key)))
+                .build(format!("ws://{}", self.address))
+                .await
+                .unwrap()
+        }
+    }
+
+    fn headers(key: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = key {
+            headers.insert(API_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        }
+        headers
+    }
+
+    fn error_code<T: std::fmt::Debug>(result: Result<T, ClientError>) -> i32 {
+        match result {
+            Err(ClientError::Call(error)) => error.code(),
+            result => panic!("expected a call error, got {result:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let config = GatewayConfig::default()
+            .with_key("full", KeyPolicy::default())
+            .with_key("health", KeyPolicy::default().allow(["vsl_getHealth"]));
+        let setup = Setup::new(config).await;
+        let address = Address::ZERO.to_string();
+
+        let anonymous = setup.http_client(None);
+        assert_eq!(error_code(anonymous.get_health().await), UNAUTHORIZED_CODE);
+        let unknown = setup.http_client(Some("unknown"));
+        assert_eq!(error_code(unknown.get_health().await), UNAUTHORIZED_CODE);
+
+        let health = setup.http_client(Some("health"));
+        assert_eq!(health.get_health().await.unwrap(), "ok");
+        assert_eq!(
+            error_code(health.get_balance(address.clone()).await),
+            METHOD_NOT_ALLOWED_CODE
+        );
+        let full = setup.http_client(Some("full"));
+        assert_eq!(full.get_balance(address).await.unwrap(), "0x1");
+        let network = crate::rpc_wrapper::get_network(&full).await.unwrap();
+        assert_eq!(network.as_deref(), Some(DEVNET.name));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits() {
+        let clock = ManualClock::new(Timestamp::from_seconds(1_750_000_000));
+        let config = GatewayConfig::default()
+            .with_clock(clock.clone())
+            .with_key(
+                "key",
+                KeyPolicy::default().with_rate_limit(RateLimit::per_second(1).with_burst(3)),
+            )
+            .with_key("other", KeyPolicy::default())
+            .with_address_rate_limit(RateLimit::per_second(1));
+        let setup = Setup::new(config).await;
+
+        let client = setup.http_client(Some("key"));
+        for _ in 0..3 {
+            client.get_health().await.unwrap();
+        }
+        assert_eq!(error_code(client.get_health().await), RATE_LIMITED_CODE);
+        clock.advance(Duration::from_secs(1));
+        client.get_health().await.unwrap();
+        assert_eq!(error_code(client.get_health().await), RATE_LIMITED_CODE);
+
+        // the limit of a sender applies whatever the key
+        let signer = PrivateKeySigner::random();
+        let payment = |nonce: u64| {
+            PayMessage {
+                from: signer.address().to_string(),
+                to: Address::repeat_byte(0x02).to_string(),
+                amount: "0x1".to_string(),
+                nonce: nonce.to_string(),
+            }
+            .into_signed(&signer)
+            .unwrap()
+        };
+        let other = setup.http_client(Some("other"));
+        other.pay(payment(0)).await.unwrap();
+        assert_eq!(error_code(other.pay(payment(1)).await), RATE_LIMITED_CODE);
+        clock.advance(Duration::from_secs(1));
+        other.pay(payment(1)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spoofed_sender_not_charged() {
+        let clock = ManualClock::new(Timestamp::from_seconds(1_750_000_000));
+        let config = GatewayConfig::default()
+            .with_clock(clock)
+            .with_key("key", KeyPolicy::default())
+            .with_address_rate_limit(RateLimit::per_second(1));
+        let setup = Setup::new(config).await;
+        let client = setup.http_client(Some("key"));
+
+        let victim = PrivateKeySigner::random();
+        let attacker = PrivateKeySigner::random();
+        let payment = |signer: &PrivateKeySigner, nonce: u64| {
+            PayMessage {
+                from: victim.address().to_string(),
+                to: Address::repeat_byte(0x02).to_string(),
+                amount: "0x1".to_string(),
+                nonce: nonce.to_string(),
+            }
+            .into_signed(signer)
+            .unwrap()
+        };
+        // requests claiming to be from the victim, signed by someone else, do
+        // not use up the budget of the victim
+        for nonce in 0..3 {
+            client.pay(payment(&attacker, nonce)).await.unwrap();
+        }
+        client.pay(payment(&victim, 0)).await.unwrap();
+        assert_eq!(
+            error_code(client.pay(payment(&victim, 1)).await),
+            RATE_LIMITED_CODE
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_eviction() {
+        let clock = ManualClock::new(Timestamp::from_seconds(1_750_000_000));
+        let limiter = RateLimiter::new(Arc::new(clock));
+        let generous = RateLimit::per_second(1).with_burst(100);
+        let strict = RateLimit::per_second(1).with_burst(1);
+        assert!(limiter.try_acquire(0, &generous));
+        for key in 1..RateLimiter::<usize>::MAX_BUCKETS {
+            assert!(limiter.try_acquire(key, &strict));
+        }
+        // the eviction does not take the partly used bucket for a full one
+        assert!(limiter.try_acquire(usize::MAX, &strict));
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets[&0].tokens, 99.0);
+    }
+
+    #[tokio::test]
+    async fn test_asset_cache() {
+        let setup =
+            Setup::new(GatewayConfig::default().with_key("key", KeyPolicy::default())).await;
+        let client = setup.http_client(Some("key"));
+        let asset_id = B256::repeat_byte(0xab);
+        let asset = client.get_asset_by_id(asset_id.to_string()).await.unwrap();
+        assert_eq!(asset.unwrap().ticker_symbol, "TST");
+        // the same ID, spelled differently
+        let spelling = format!("0x{}", alloy::hex::encode_upper(asset_id));
+        assert!(client.get_asset_by_id(spelling).await.unwrap().is_some());
+        assert_eq!(setup.node.asset_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_fan_out() {
+        let setup =
+            Setup::new(GatewayConfig::default().with_key("key", KeyPolicy::default())).await;
+        let first = setup.ws_client("key").await;
+        let second = setup.ws_client("key").await;
+        let mut subscriptions = Vec::new();
+        for client in [&first, &second] {
+            subscriptions.push(client.subscribe_to_settled_claims_metadata().await.unwrap());
+        }
+        assert_eq!(setup.node.subscriptions.load(Ordering::SeqCst), 1);
+
+        let item = Timestamped::new(
+            "0x01".to_string(),
+            Timestamp::from_seconds(1),
+            SettledClaimData {
+                claim_type: "test".to_string(),
+                claim_owner: Address::repeat_byte(0x01).to_string(),
+                verifiers: vec![],
+            },
+        );
+        setup.node.settled.send(item).unwrap();
+        for subscription in &mut subscriptions {
+            let received = subscription.next().await.unwrap().unwrap();
+            assert_eq!(received.id, "0x01");
+        }
+
+        // subscribing requires an API key too
+        let anonymous = WsClientBuilder::new()
+            .build(format!("ws://{}", setup.address))
+            .await
+            .unwrap();
+        let result = anonymous.subscribe_to_settled_claims_metadata().await;
+        assert_eq!(error_code(result), UNAUTHORIZED_CODE);
+    }
+}
@@ -1,5 +1,8 @@
+use std::fmt;
+
 use alloy::consensus::Signed;
-use alloy::primitives::{Address, SignatureError, eip191_hash_message};
+use alloy::consensus::transaction::RlpEcdsaEncodableTx;
+use alloy::primitives::{Address, B256, SignatureError, eip191_hash_message};
 use alloy::signers::local::PrivateKeySigner;
 use alloy::signers::{Error, Signature, SignerSync};
 use alloy_rlp::Encodable;
@@ -23,46 +26,162 @@ where
     }
 }
 
+/// The bytes signed for a message with the given RLP encoding: the encoding itself,
+/// or, if a domain (e.g., a network name) is given, the RLP encoding of the domain
+/// followed by that of the message, so that the signature is only valid in that domain
+pub fn signing_preimage(encoded: &[u8], domain: Option<&str>) -> Vec<u8> {
+    let mut preimage = Vec::with_capacity(encoded.len());
+    if let Some(domain) = domain {
+        domain.encode(&mut preimage);
+    }
+    preimage.extend_from_slice(encoded);
+    preimage
+}
+
 /// Trait to turn an [Encodable] object into a [Signed] one.
 ///
 /// The object is encoded intro a stream of bytes, then prefixed according to
 /// the [EIP-191](https://eips.ethereum.org/EIPS/eip-191) standard
 /// (to allow using the [Signed] methods for extracting the address)
-pub trait IntoSigned: Sized + Encodable + HasSender {
+///
+/// The `_for` variants of the methods take an optional domain separating the
+/// signatures (see [signing_preimage]); the other methods use no domain.
+pub trait IntoSigned: Sized + Encodable + HasSender + RlpEcdsaEncodableTx {
     fn into_signed(self, signer: &PrivateKeySigner) -> Result<Signed<Self>, Error> {
-        let mut buf: Vec<u8> = Vec::new();
-        self.encode(&mut buf);
-        let hash = eip191_hash_message(buf);
+        self.into_signed_for(signer, None)
+    }
+
+    fn into_signed_for(
+        self,
+        signer: &PrivateKeySigner,
+        domain: Option<&str>,
+    ) -> Result<Signed<Self>, Error> {
+        let hash = eip191_hash_message(self.signing_preimage(domain));
         let sig = signer.sign_hash_sync(&hash)?;
         Ok(Signed::new_unchecked(self, sig, hash))
     }
 
+    /// The bytes signed for this message in the given domain
+    fn signing_preimage(&self, domain: Option<&str>) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        self.encode(&mut buf);
+        signing_preimage(&buf, domain)
+    }
+
     fn recover_address(&self, sig: &Signature) -> Result<Address, SignatureError> {
-        let mut msg: Vec<u8> = Vec::new();
-        self.encode(&mut msg);
-        sig.recover_address_from_msg(&msg)
+        self.recover_address_for(sig, None)
+    }
+
+    fn recover_address_for(
+        &self,
+        sig: &Signature,
+        domain: Option<&str>,
+    ) -> Result<Address, SignatureError> {
+        sig.recover_address_from_msg(self.signing_preimage(domain))
     }
 
+    /// Whether the message is signed by its sender (see [IntoSigned::verify])
     fn check(signed: &Signed<Self>) -> bool {
+        Self::check_for(signed, None)
+    }
+
+    /// Whether the message is signed by its sender, in the given domain
+    fn check_for(signed: &Signed<Self>, domain: Option<&str>) -> bool {
+        Self::verify_for(signed, domain).is_ok()
+    }
+
+    /// Strictly verifies the signed message, returning its signer (and sender)
+    fn verify(signed: &Signed<Self>) -> Result<Address, VerificationError> {
+        Self::verify_for(signed, None)
+    }
+
+    /// Strictly verifies the signed message in the given domain: its sender must
+    /// be valid, its hash must be the EIP-191 hash of its payload, and its
+    /// signature must be in low-`s` form and by the sender
+    fn verify_for(
+        signed: &Signed<Self>,
+        domain: Option<&str>,
+    ) -> Result<Address, VerificationError> {
         let data = signed.tx();
-        let signer = match data.recover_address(signed.signature()) {
-            Ok(address) => address,
-            Err(_) => return false,
-        };
-        let Some(address) = data.sender() else {
-            return false;
-        };
-        return signer == address;
-    }
-
-    fn check_and_strip_signature(signed: Signed<Self>) -> Option<Self> {
-        if !Self::check(&signed) {
-            return None;
-        };
-        Some(signed.strip_signature())
+        let sender = data.sender().ok_or(VerificationError::InvalidSender)?;
+        let expected = eip191_hash_message(data.signing_preimage(domain));
+        verify_signature(sender, expected, signed.hash(), signed.signature())
+    }
+
+    fn check_and_strip_signature(signed: Signed<Self>) -> Result<Self, VerificationError> {
+        Self::check_and_strip_signature_for(signed, None)
+    }
+
+    fn check_and_strip_signature_for(
+        signed: Signed<Self>,
+        domain: Option<&str>,
+    ) -> Result<Self, VerificationError> {
+        Self::verify_for(&signed, domain)?;
+        Ok(signed.strip_signature())
     }
 }
 
+/// Why a signed message failed (strict) verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The sender (e.g., `from`) of the message is not a valid address
+    InvalidSender,
+    /// The message cannot be hashed (e.g., a field has no EIP-712 representation)
+    InvalidPayload,
+    /// The `hash` of the signed message is not the hash of its payload
+    HashMismatch { expected: B256, found: B256 },
+    /// The `s` value of the signature is not in low-`s` form (malleable signature)
+    HighS,
+    /// No address can be recovered from the signature
+    InvalidSignature,
+    /// The message is signed by another account than its sender
+    SignerMismatch { sender: Address, signer: Address },
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationError::InvalidSender => write!(f, "invalid sender address"),
+            VerificationError::InvalidPayload => write!(f, "the payload cannot be hashed"),
+            VerificationError::HashMismatch { expected, found } => {
+                write!(f, "hash {found} does not match the payload hash {expected}")
+            }
+            VerificationError::HighS => write!(f, "signature is not in low-s form"),
+            VerificationError::InvalidSignature => write!(f, "invalid signature"),
+            VerificationError::SignerMismatch { sender, signer } => {
+                write!(f, "signed by {signer} instead of the sender {sender}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Checks that a signature of the given (expected) hash is valid and by the sender
+pub(crate) fn verify_signature(
+    sender: Address,
+    expected: B256,
+    found: &B256,
+    signature: &Signature,
+) -> Result<Address, VerificationError> {
+    if *found != expected {
+        return Err(VerificationError::HashMismatch {
+            expected,
+            found: *found,
+        });
+    }
+    if signature.normalize_s().is_some() {
+        return Err(VerificationError::HighS);
+    }
+    let signer = signature
+        .recover_address_from_prehash(&expected)
+        .map_err(|_| VerificationError::InvalidSignature)?;
+    if signer != sender {
+        return Err(VerificationError::SignerMismatch { sender, signer });
+    }
+    Ok(signer)
+}
+
 /// Implement the (undocumented) [RlpEcdsaDecodableTx] and [RlpEcdsaEncodableTx] traits
 /// needed for some uses of [Signed], by making a dummy implementation of [Typed2718] with
 /// a code of 0u8, and otherwise forwarding to [alloy_rlp::Decodable] and [alloy_rlp::Encodable].
@@ -95,3 +214,69 @@ macro_rules! impl_rlp_ecdsa_glue {
         impl IntoSigned for $type {}
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::*;
+    use crate::rpc_messages::PayMessage;
+
+    fn payment(from: &Address) -> PayMessage {
+        PayMessage {
+            from: from.to_string(),
+            to: "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF".to_string(),
+            amount: "0xa".to_string(),
+            nonce: "100".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_strict_verification() {
+        let signer = PrivateKeySigner::random();
+        let signed = payment(&signer.address()).into_signed(&signer).unwrap();
+        assert_eq!(PayMessage::verify(&signed), Ok(signer.address()));
+
+        let (message, signature, hash) = signed.clone().into_parts();
+        let wrong_hash = Signed::new_unchecked(message.clone(), signature, B256::ZERO);
+        assert_eq!(
+            PayMessage::verify(&wrong_hash),
+            Err(VerificationError::HashMismatch {
+                expected: hash,
+                found: B256::ZERO
+            })
+        );
+
+        // the "high-s" twin of a valid signature recovers the same signer
+        let order = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let high_s = Signature::new(signature.r(), order - signature.s(), !signature.v());
+        let malleated = Signed::new_unchecked(message, high_s, hash);
+        assert_eq!(
+            PayMessage::verify(&malleated),
+            Err(VerificationError::HighS)
+        );
+
+        let other = PrivateKeySigner::random();
+        let impersonated = payment(&other.address()).into_signed(&signer).unwrap();
+        assert_eq!(
+            PayMessage::check_and_strip_signature(impersonated),
+            Err(VerificationError::SignerMismatch {
+                sender: other.address(),
+                signer: signer.address()
+            })
+        );
+
+        let mut invalid = payment(&signer.address());
+        invalid.from = "nobody".to_string();
+        let invalid = invalid.into_signed(&signer).unwrap();
+        assert_eq!(
+            PayMessage::verify(&invalid),
+            Err(VerificationError::InvalidSender)
+        );
+        assert!(PayMessage::check_and_strip_signature(signed).is_ok());
+    }
+}
@@ -0,0 +1,87 @@
+//! # Network Module
+//!
+//! Signed messages carry no indication of the VSL network they are intended for,
+//! so a message signed for one network would be valid (and replayable) on any
+//! other network where the signer's nonce matches. Signing with the network name
+//! as domain (see [crate::signing_preimage]) makes the signature valid only on
+//! that network.
+//!
+//! This module lists the profiles of the known networks.
+//!
+
+/// A VSL network, identified by its name (used as signing domain)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Network {
+    /// The name of the network, as advertised by its servers
+    pub name: &'static str,
+    /// The default address of the RPC server, in the form `IP:PORT` (if any)
+    pub endpoint: Option<&'static str>,
+}
+
+impl Network {
+    /// The signing domain of the network
+    pub const fn domain(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A local instance of VSL (see `docs/quick_start.md`)
+pub const LOCAL: Network = Network {
+    name: "vsl-local",
+    endpoint: Some("127.0.0.1:44444"),
+};
+
+/// The public VSL development network
+pub const DEVNET: Network = Network {
+    name: "vsl-devnet",
+    endpoint: None,
+};
+
+/// All known networks
+pub const NETWORKS: &[Network] = &[LOCAL, DEVNET];
+
+/// The known network with the given name, if any
+pub fn by_name(name: &str) -> Option<&'static Network> {
+    NETWORKS.iter().find(|network| network.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::IntoSigned;
+    use crate::rpc_messages::PayMessage;
+
+    fn payment(signer: &PrivateKeySigner) -> PayMessage {
+        PayMessage {
+            from: signer.address().to_string(),
+            to: "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF".to_string(),
+            amount: "0xa".to_string(),
+            nonce: "100".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert_eq!(by_name("vsl-devnet"), Some(&DEVNET));
+        assert_eq!(by_name("vsl-unknown"), None);
+    }
+
+    #[test]
+    fn test_signatures_are_domain_separated() {
+        let signer = PrivateKeySigner::random();
+        let devnet = payment(&signer)
+            .into_signed_for(&signer, Some(DEVNET.domain()))
+            .unwrap();
+        assert!(PayMessage::check_for(&devnet, Some(DEVNET.domain())));
+        assert!(!PayMessage::check_for(&devnet, Some(LOCAL.domain())));
+        assert!(!PayMessage::check(&devnet));
+
+        // without a domain, signatures are unchanged
+        let plain = payment(&signer).into_signed(&signer).unwrap();
+        assert!(PayMessage::check(&plain));
+        assert!(!PayMessage::check_for(&plain, Some(DEVNET.domain())));
+        assert_ne!(devnet.hash(), plain.hash());
+    }
+}
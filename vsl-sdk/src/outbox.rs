@@ -0,0 +1,603 @@
+//! # Outbox Module
+//!
+//! A durable (sled) outbox for sending signed messages exactly once across
+//! crashes. Each message is recorded, as a [SignedEnvelope] holding its nonce and
+//! signed bytes, before being submitted; its [EntryStatus] is updated once the
+//! outcome is known.
+//!
+//! On restart, [Outbox::reconcile] checks each pending entry against the node:
+//!
+//! - an entry whose claim (see [SignedMessage::claim_id]) is known to the node,
+//!   or whose nonce has been consumed, is marked done;
+//! - an entry whose claim is unknown to the node, while its nonce was consumed
+//!   by another message, is marked failed;
+//! - the other entries are submitted again.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::signers::local::PrivateKeySigner;
+//! use jsonrpsee::http_client::HttpClientBuilder;
+//! use vsl_sdk::envelope::{UnsignedEnvelope, UnsignedMessage};
+//! use vsl_sdk::outbox::Outbox;
+//! use vsl_sdk::{Address, Amount};
+//!
+//! let client = HttpClientBuilder::new().build("http://127.0.0.1:44444")?;
+//! let signer = PrivateKeySigner::random();
+//! let outbox = Outbox::open("payouts.db")?;
+//! // resubmit what was pending when the service stopped
+//! outbox.reconcile(&client).await?;
+//!
+//! let nonce = outbox.next_nonce(&signer.address())?.unwrap_or_default();
+//! let payment = UnsignedMessage::pay(
+//!     &signer.address(),
+//!     nonce,
+//!     &Address::repeat_byte(0xff),
+//!     &Amount::from_vsl_tokens(1),
+//! );
+//! let envelope = UnsignedEnvelope::new(payment, None)?.sign(&signer)?;
+//! let entry = outbox.send(&client, envelope).await?;
+//! println!("{:?}", entry.status);
+//! # Ok(())
+//! # }
+//! ```
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use serde::{Deserialize, Serialize};
+
+use crate::Address;
+use crate::envelope::{BroadcastReceipt, EnvelopeError, SignedEnvelope, SignedMessage, broadcast};
+use crate::rpc_wrapper::{
+    ClaimNotFound, ClaimStage, RpcWrapperError, claim_exists, get_account_nonce, is_node_rejection,
+};
+
+/// The name of the sled tree holding the entries
+pub const OUTBOX_TREE: &str = "outbox";
+
+#[derive(Debug)]
+pub enum OutboxError {
+    Storage(sled::Error),
+    Encoding(serde_json::Error),
+    /// The envelope to be recorded is not valid
+    Envelope(EnvelopeError),
+    /// The node could not be reached, or returned an unexpected answer
+    Rpc(RpcWrapperError),
+    /// A different message is already recorded for the signer and nonce
+    Conflict {
+        signer: Address,
+        nonce: u64,
+    },
+}
+
+impl fmt::Display for OutboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutboxError::Storage(error) => write!(f, "outbox storage error: {error}"),
+            OutboxError::Encoding(error) => write!(f, "invalid outbox entry: {error}"),
+            OutboxError::Envelope(error) => write!(f, "invalid envelope: {error}"),
+            OutboxError::Rpc(error) => write!(f, "RPC error: {error}"),
+            OutboxError::Conflict { signer, nonce } => {
+                write!(
+                    f,
+                    "another message is recorded for {signer} with nonce {nonce}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutboxError {}
+
+impl From<sled::Error> for OutboxError {
+    fn from(error: sled::Error) -> Self {
+        OutboxError::Storage(error)
+    }
+}
+
+impl From<serde_json::Error> for OutboxError {
+    fn from(error: serde_json::Error) -> Self {
+        OutboxError::Encoding(error)
+    }
+}
+
+impl From<EnvelopeError> for OutboxError {
+    fn from(error: EnvelopeError) -> Self {
+        OutboxError::Envelope(error)
+    }
+}
+
+impl From<RpcWrapperError> for OutboxError {
+    fn from(error: RpcWrapperError) -> Self {
+        OutboxError::Rpc(error)
+    }
+}
+
+impl From<ClientError> for OutboxError {
+    fn from(error: ClientError) -> Self {
+        OutboxError::Rpc(RpcWrapperError::RpcError(error))
+    }
+}
+
+pub type OutboxResult<T> = Result<T, OutboxError>;
+
+/// The status of a recorded message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EntryStatus {
+    /// Not known to have been applied (yet)
+    Pending,
+    /// Applied by the node, with the receipt if known (when reconciled, only the
+    /// claim ID is known)
+    Done { receipt: Option<BroadcastReceipt> },
+    /// Rejected by the node
+    Failed { error: String },
+}
+
+/// A recorded message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub envelope: SignedEnvelope,
+    #[serde(flatten)]
+    pub status: EntryStatus,
+}
+
+impl OutboxEntry {
+    pub fn signer(&self) -> Address {
+        self.envelope.signer
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.envelope.nonce
+    }
+}
+
+/// The key of an entry: the signer followed by the (big-endian) nonce, so that
+/// entries are ordered by nonce for each signer
+fn key(signer: &Address, nonce: u64) -> [u8; 28] {
+    let mut key = [0u8; 28];
+    key[..20].copy_from_slice(signer.as_slice());
+    key[20..].copy_from_slice(&nonce.to_be_bytes());
+    key
+}
+
+/// The claims the claim of the message is looked up among
+fn claim_stage(message: &SignedMessage) -> ClaimStage {
+    match message {
+        SignedMessage::SubmitClaim(_) => ClaimStage::Submitted,
+        _ => ClaimStage::Settled,
+    }
+}
+
+/// A durable outbox of signed messages
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    tree: sled::Tree,
+    claim_not_found: ClaimNotFound,
+}
+
+impl Outbox {
+    /// Opens (or creates) the outbox in the sled database at the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> OutboxResult<Self> {
+        Ok(Outbox::new(sled::open(path)?.open_tree(OUTBOX_TREE)?))
+    }
+
+    /// An outbox stored in the given tree
+    pub fn new(tree: sled::Tree) -> Self {
+        Outbox {
+            tree,
+            claim_not_found: ClaimNotFound::default(),
+        }
+    }
+
+    /// Only takes the given answer of the node for an unknown claim, when
+    /// reconciling the entries
+    pub fn with_claim_not_found(self, claim_not_found: ClaimNotFound) -> Self {
+        Outbox {
+            claim_not_found,
+            ..self
+        }
+    }
+
+    fn read(&self, bytes: &[u8]) -> OutboxResult<OutboxEntry> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn write(&self, entry: &OutboxEntry) -> OutboxResult<()> {
+        let key = key(&entry.signer(), entry.nonce());
+        self.tree.insert(key, serde_json::to_vec(entry)?)?;
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    /// Records a signed message (durably), unless already recorded; returns the
+    /// entry, and whether it was new
+    ///
+    /// A failed message did not consume its nonce, so it is replaced.
+    pub fn record(&self, envelope: SignedEnvelope) -> OutboxResult<(OutboxEntry, bool)> {
+        envelope.verify()?;
+        let (signer, nonce) = (envelope.signer, envelope.nonce);
+        let entry = OutboxEntry {
+            envelope,
+            status: EntryStatus::Pending,
+        };
+        let bytes = serde_json::to_vec(&entry)?;
+        let key = key(&signer, nonce);
+        let mut expected = None;
+        loop {
+            match self
+                .tree
+                .compare_and_swap(key, expected.as_ref(), Some(bytes.as_slice()))?
+            {
+                Ok(()) => {
+                    self.tree.flush()?;
+                    return Ok((entry, true));
+                }
+                Err(swap) => {
+                    let Some(current) = swap.current else {
+                        expected = None;
+                        continue;
+                    };
+                    let recorded = self.read(&current)?;
+                    if !matches!(recorded.status, EntryStatus::Failed { .. }) {
+                        if recorded.envelope.message.hash() != entry.envelope.message.hash() {
+                            return Err(OutboxError::Conflict { signer, nonce });
+                        }
+                        return Ok((recorded, false));
+                    }
+                    expected = Some(current);
+                }
+            }
+        }
+    }
+
+    /// The entry of the signer and nonce, if any
+    pub fn get(&self, signer: &Address, nonce: u64) -> OutboxResult<Option<OutboxEntry>> {
+        self.tree
+            .get(key(signer, nonce))?
+            .map(|bytes| self.read(&bytes))
+            .transpose()
+    }
+
+    /// The status of the entry of the signer and nonce, if any
+    pub fn status(&self, signer: &Address, nonce: u64) -> OutboxResult<Option<EntryStatus>> {
+        Ok(self.get(signer, nonce)?.map(|entry| entry.status))
+    }
+
+    /// All the entries, by signer and nonce
+    pub fn entries(&self) -> OutboxResult<Vec<OutboxEntry>> {
+        self.tree.iter().map(|item| self.read(&item?.1)).collect()
+    }
+
+    /// The entries not known to have been applied, by signer and nonce
+    pub fn pending(&self) -> OutboxResult<Vec<OutboxEntry>> {
+        let entries = self.entries()?.into_iter();
+        Ok(entries
+            .filter(|entry| entry.status == EntryStatus::Pending)
+            .collect())
+    }
+
+    /// The nonce following the last one recorded for the signer, if any (the
+    /// account nonce on the node may lag behind it while entries are pending);
+    /// the failed messages at the end did not consume their nonces
+    pub fn next_nonce(&self, signer: &Address) -> OutboxResult<Option<u64>> {
+        for item in self.tree.scan_prefix(signer.as_slice()).rev() {
+            let entry = self.read(&item?.1)?;
+            if !matches!(entry.status, EntryStatus::Failed { .. }) {
+                return Ok(Some(entry.nonce() + 1));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes the entries which are done, and returns their number
+    pub fn prune(&self) -> OutboxResult<usize> {
+        let mut pruned = 0;
+        for entry in self.entries()? {
+            if matches!(entry.status, EntryStatus::Done { .. }) {
+                self.tree.remove(key(&entry.signer(), entry.nonce()))?;
+                pruned += 1;
+            }
+        }
+        self.tree.flush()?;
+        Ok(pruned)
+    }
+
+    /// Records the signed message, then submits it (if newly recorded) or
+    /// reconciles it (if recorded before)
+    pub async fn send<T: ClientT>(
+        &self,
+        rpc_client: &T,
+        envelope: SignedEnvelope,
+    ) -> OutboxResult<OutboxEntry> {
+        let (entry, new) = self.record(envelope)?;
+        if new {
+            self.submit(rpc_client, entry).await
+        } else {
+            self.reconcile_entry(rpc_client, entry, &mut HashMap::new())
+                .await
+        }
+    }
+
+    /// Submits the message of the entry, and records the outcome: the entry fails
+    /// if the node rejects the message, and stays pending if the outcome is unknown
+    /// (e.g. the node could not be reached, or a gateway answered instead)
+    async fn submit<T: ClientT>(
+        &self,
+        rpc_client: &T,
+        mut entry: OutboxEntry,
+    ) -> OutboxResult<OutboxEntry> {
+        entry.status = match broadcast(rpc_client, &entry.envelope).await {
+            Ok(receipt) => EntryStatus::Done {
+                receipt: Some(receipt),
+            },
+            Err(RpcWrapperError::RpcError(ClientError::Call(error)))
+                if is_node_rejection(&error) =>
+            {
+                EntryStatus::Failed {
+                    error: error.message().to_string(),
+                }
+            }
+            Err(error) => return Err(error.into()),
+        };
+        self.write(&entry)?;
+        Ok(entry)
+    }
+
+    /// Checks a pending entry against the node, then submits it again or records
+    /// its outcome; `nonces` caches the account nonces on the node
+    async fn reconcile_entry<T: ClientT>(
+        &self,
+        rpc_client: &T,
+        mut entry: OutboxEntry,
+        nonces: &mut HashMap<Address, u64>,
+    ) -> OutboxResult<OutboxEntry> {
+        if entry.status != EntryStatus::Pending {
+            return Ok(entry);
+        }
+        let message = &entry.envelope.message;
+        let claim_id = message.claim_id();
+        if let Some(claim_id) = &claim_id
+            && claim_exists(
+                rpc_client,
+                claim_stage(message),
+                claim_id,
+                self.claim_not_found,
+            )
+            .await?
+        {
+            entry.status = EntryStatus::Done {
+                receipt: Some(BroadcastReceipt::Claim(*claim_id)),
+            };
+            self.write(&entry)?;
+            return Ok(entry);
+        }
+        let signer = entry.signer();
+        let account_nonce = match nonces.get(&signer) {
+            Some(nonce) => *nonce,
+            None => get_account_nonce(rpc_client, &signer).await?,
+        };
+        nonces.insert(signer, account_nonce);
+        if entry.nonce() >= account_nonce {
+            let entry = self.submit(rpc_client, entry).await?;
+            if matches!(entry.status, EntryStatus::Done { .. }) {
+                nonces.insert(signer, entry.nonce() + 1);
+            }
+            return Ok(entry);
+        }
+        // the nonce was consumed: by this message, unless its claim is unknown
+        entry.status = match claim_id {
+            None => EntryStatus::Done { receipt: None },
+            Some(_) => EntryStatus::Failed {
+                error: "the nonce was consumed by another message".to_string(),
+            },
+        };
+        self.write(&entry)?;
+        Ok(entry)
+    }
+
+    /// Reconciles all the pending entries with the node (in nonce order for each
+    /// signer), and returns them with their updated status
+    pub async fn reconcile<T: ClientT>(&self, rpc_client: &T) -> OutboxResult<Vec<OutboxEntry>> {
+        let mut nonces = HashMap::new();
+        let mut reconciled = vec![];
+        for entry in self.pending()? {
+            reconciled.push(self.reconcile_entry(rpc_client, entry, &mut nonces).await?);
+        }
+        Ok(reconciled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use alloy::signers::local::PrivateKeySigner;
+    use jsonrpsee::http_client::HttpClient;
+    use jsonrpsee::server::ServerHandle;
+
+    use super::*;
+    use crate::Amount;
+    use crate::envelope::{UnsignedEnvelope, UnsignedMessage};
+    use crate::rpc_messages::AccountStateHash;
+    use crate::test_node::{self, Ledger};
+
+    async fn serve(node: Arc<Mutex<Ledger>>) -> (HttpClient, ServerHandle) {
+        test_node::serve(Ledger::module(node)).await
+    }
+
+    fn outbox() -> Outbox {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        Outbox::new(db.open_tree(OUTBOX_TREE).unwrap())
+    }
+
+    fn sign(signer: &PrivateKeySigner, message: UnsignedMessage) -> SignedEnvelope {
+        UnsignedEnvelope::new(message, None)
+            .unwrap()
+            .sign(signer)
+            .unwrap()
+    }
+
+    fn payment(signer: &PrivateKeySigner, nonce: u64, amount: u128) -> SignedEnvelope {
+        let to = Address::repeat_byte(0xff);
+        let amount = Amount::from_subunits(amount);
+        sign(
+            signer,
+            UnsignedMessage::pay(&signer.address(), nonce, &to, &amount),
+        )
+    }
+
+    #[test]
+    fn test_record() {
+        let outbox = outbox();
+        let signer = PrivateKeySigner::random();
+        assert_eq!(outbox.next_nonce(&signer.address()).unwrap(), None);
+        let (entry, new) = outbox.record(payment(&signer, 3, 1)).unwrap();
+        assert!(new);
+        assert_eq!(entry.status, EntryStatus::Pending);
+        let (_, new) = outbox.record(payment(&signer, 3, 1)).unwrap();
+        assert!(!new);
+        assert!(matches!(
+            outbox.record(payment(&signer, 3, 2)),
+            Err(OutboxError::Conflict { nonce: 3, .. })
+        ));
+        outbox.record(payment(&signer, 4, 1)).unwrap();
+        assert_eq!(outbox.next_nonce(&signer.address()).unwrap(), Some(5));
+        assert_eq!(outbox.pending().unwrap().len(), 2);
+
+        let mut tampered = payment(&signer, 5, 1);
+        tampered.nonce = 6;
+        assert!(matches!(
+            outbox.record(tampered),
+            Err(OutboxError::Envelope(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let node = Arc::new(Mutex::new(Ledger::default()));
+        let (client, _handle) = serve(node.clone()).await;
+        let outbox = outbox();
+        let signer = PrivateKeySigner::random();
+
+        let entry = outbox.send(&client, payment(&signer, 0, 1)).await.unwrap();
+        let claim_id = entry.envelope.message.claim_id().unwrap();
+        assert_eq!(
+            entry.status,
+            EntryStatus::Done {
+                receipt: Some(BroadcastReceipt::Claim(claim_id))
+            }
+        );
+        // sending again does not pay twice
+        let again = outbox.send(&client, payment(&signer, 0, 1)).await.unwrap();
+        assert_eq!(again.status, entry.status);
+        assert_eq!(node.lock().unwrap().payments, 1);
+
+        let entry = outbox.send(&client, payment(&signer, 1, 0)).await.unwrap();
+        assert_eq!(
+            entry.status,
+            EntryStatus::Failed {
+                error: "invalid amount".to_string()
+            }
+        );
+        assert_eq!(
+            outbox.status(&signer.address(), 1).unwrap(),
+            Some(entry.status)
+        );
+
+        // the rejected payment did not consume its nonce, which is used again
+        assert_eq!(outbox.next_nonce(&signer.address()).unwrap(), Some(1));
+        let entry = outbox.send(&client, payment(&signer, 1, 2)).await.unwrap();
+        assert!(matches!(entry.status, EntryStatus::Done { .. }));
+        assert_eq!(outbox.next_nonce(&signer.address()).unwrap(), Some(2));
+        assert_eq!(node.lock().unwrap().payments, 2);
+        assert_eq!(outbox.prune().unwrap(), 2);
+        assert!(outbox.entries().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_outcome() {
+        let node = Arc::new(Mutex::new(Ledger {
+            upstream_error_after: Some(0),
+            ..Ledger::default()
+        }));
+        let (client, _handle) = serve(node.clone()).await;
+        let outbox = outbox();
+        let signer = PrivateKeySigner::random();
+
+        // the payment went through, but only the gateway answered
+        assert!(outbox.send(&client, payment(&signer, 0, 1)).await.is_err());
+        assert_eq!(
+            outbox.status(&signer.address(), 0).unwrap(),
+            Some(EntryStatus::Pending)
+        );
+        // so its nonce cannot be taken by another message
+        assert!(matches!(
+            outbox.record(payment(&signer, 0, 2)),
+            Err(OutboxError::Conflict { nonce: 0, .. })
+        ));
+        assert_eq!(outbox.next_nonce(&signer.address()).unwrap(), Some(1));
+
+        let reconciled = outbox.reconcile(&client).await.unwrap();
+        assert!(matches!(
+            reconciled[0].status,
+            EntryStatus::Done { receipt: Some(_) }
+        ));
+        assert_eq!(node.lock().unwrap().payments, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile() {
+        let node = Arc::new(Mutex::new(Ledger::default()));
+        let (client, _handle) = serve(node.clone()).await;
+        let outbox = outbox();
+        let signer = PrivateKeySigner::random();
+
+        // submitted before the crash, but not marked done
+        let landed = payment(&signer, 0, 1);
+        outbox.record(landed.clone()).unwrap();
+        broadcast(&client, &landed).await.unwrap();
+        // a state update, submitted before the crash
+        let state = AccountStateHash::hash(b"state");
+        let state = sign(
+            &signer,
+            UnsignedMessage::set_state(&signer.address(), 1, &state),
+        );
+        outbox.record(state.clone()).unwrap();
+        broadcast(&client, &state).await.unwrap();
+        // not submitted before the crash
+        outbox.record(payment(&signer, 2, 1)).unwrap();
+        outbox.record(payment(&signer, 3, 1)).unwrap();
+
+        let reconciled = outbox.reconcile(&client).await.unwrap();
+        let statuses: Vec<_> = reconciled.into_iter().map(|entry| entry.status).collect();
+        assert_eq!(
+            statuses[0],
+            EntryStatus::Done {
+                receipt: Some(BroadcastReceipt::Claim(landed.message.claim_id().unwrap()))
+            }
+        );
+        assert_eq!(statuses[1], EntryStatus::Done { receipt: None });
+        assert!(matches!(
+            statuses[2],
+            EntryStatus::Done { receipt: Some(_) }
+        ));
+        assert!(matches!(
+            statuses[3],
+            EntryStatus::Done { receipt: Some(_) }
+        ));
+        assert_eq!(node.lock().unwrap().payments, 3);
+        assert_eq!(node.lock().unwrap().nonce, 4);
+        assert!(outbox.pending().unwrap().is_empty());
+
+        // the nonce of a recorded payment was consumed by another message
+        let other = payment(&signer, 4, 2);
+        broadcast(&client, &other).await.unwrap();
+        outbox.record(payment(&signer, 4, 1)).unwrap();
+        let reconciled = outbox.reconcile(&client).await.unwrap();
+        assert!(matches!(reconciled[0].status, EntryStatus::Failed { .. }));
+    }
+}
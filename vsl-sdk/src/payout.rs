@@ -0,0 +1,831 @@
+//! # Payout Module
+//!
+//! Bulk payouts (airdrops, payroll) from a manifest of `(address, amount, asset)`
+//! rows, read from CSV (`address,amount[,asset]`, with an optional header line)
+//! or from a JSON array of objects with the same fields. Amounts are in tokens
+//! (e.g. `12.5`) of the asset, or of VSL if no asset is given.
+//!
+//! A [Payout] is prepared by validating every row up front (addresses, assets
+//! and decimals), and checks that the balances of the sender cover the amounts
+//! and the validation fee of each payment before anything is sent.
+//!
+//! Payments are signed with consecutive nonces, starting at the account nonce,
+//! and recorded as sent in a receipt file before being submitted back to back.
+//! The receipt file maps each row to the ID of its settled claim, and is used to
+//! resume an interrupted payout: sent rows whose claim was settled are marked as
+//! such, the others are submitted again (or signed again if their nonce was
+//! consumed), so that no row is paid twice. If the node cannot tell whether a
+//! claim was settled (e.g., a rate limit), the payout stops instead.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::path::Path;
+//!
+//! use alloy::signers::local::PrivateKeySigner;
+//! use jsonrpsee::http_client::HttpClientBuilder;
+//! use vsl_sdk::Amount;
+//! use vsl_sdk::payout::{Manifest, Payout};
+//!
+//! let client = HttpClientBuilder::new().build("http://127.0.0.1:44444")?;
+//! let signer = PrivateKeySigner::random();
+//! let manifest = Manifest::read("airdrop.csv")?;
+//! let receipts = Path::new("airdrop.receipts.json");
+//! let mut payout = Payout::prepare(&client, &manifest, Amount::from_subunits(1)).await?;
+//! payout.resume(receipts)?;
+//! payout.run(&client, &signer, receipts).await?;
+//! for receipt in payout.receipts() {
+//!     println!("{}: {:?}", receipt.row, receipt.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use alloy::signers::local::PrivateKeySigner;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use serde::{Deserialize, Serialize};
+
+use crate::envelope::{
+    BroadcastReceipt, EnvelopeError, SignedEnvelope, UnsignedEnvelope, UnsignedMessage, broadcast,
+};
+use crate::rpc_wrapper::{self, AssetData, ClaimNotFound, ClaimStage, RpcWrapperError};
+use crate::{Address, Amount, AssetId, B256, TokenAmount};
+
+/// The number of payments submitted between two saves of the receipt file
+pub const SAVE_INTERVAL: usize = 100;
+
+/// A row of the manifest which is not valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    /// The index of the row (0-based, not counting the CSV header)
+    pub row: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.reason)
+    }
+}
+
+#[derive(Debug)]
+pub enum PayoutError {
+    Io(std::io::Error),
+    Encoding(serde_json::Error),
+    /// Some rows of the manifest are not valid
+    InvalidRows(Vec<RowError>),
+    /// The receipt file does not match the manifest
+    ManifestChanged {
+        row: usize,
+    },
+    /// The receipt file holds payments signed by another account
+    SignerMismatch {
+        expected: Address,
+        found: Address,
+    },
+    /// The balance of the sender cannot cover the payments (`asset` is `None` for
+    /// VSL, including the validation fees)
+    InsufficientBalance {
+        asset: Option<AssetId>,
+        required: Amount,
+        available: Amount,
+    },
+    Envelope(EnvelopeError),
+    /// The node could not be reached, or returned an unexpected answer
+    Rpc(RpcWrapperError),
+}
+
+impl fmt::Display for PayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayoutError::Io(error) => write!(f, "{error}"),
+            PayoutError::Encoding(error) => write!(f, "invalid manifest or receipts: {error}"),
+            PayoutError::InvalidRows(errors) => {
+                write!(f, "invalid manifest:")?;
+                for error in errors {
+                    write!(f, "\n  {error}")?;
+                }
+                Ok(())
+            }
+            PayoutError::ManifestChanged { row } => {
+                write!(f, "the receipts do not match row {row} of the manifest")
+            }
+            PayoutError::SignerMismatch { expected, found } => {
+                write!(f, "the receipts were signed by {found}, not {expected}")
+            }
+            PayoutError::InsufficientBalance {
+                asset,
+                required,
+                available,
+            } => {
+                let asset = asset.map_or("VSL".to_string(), |asset| format!("asset {asset}"));
+                write!(
+                    f,
+                    "insufficient {asset} balance: {required} subunits required, {available} available"
+                )
+            }
+            PayoutError::Envelope(error) => write!(f, "invalid envelope: {error}"),
+            PayoutError::Rpc(error) => write!(f, "RPC error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PayoutError {}
+
+impl From<std::io::Error> for PayoutError {
+    fn from(error: std::io::Error) -> Self {
+        PayoutError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for PayoutError {
+    fn from(error: serde_json::Error) -> Self {
+        PayoutError::Encoding(error)
+    }
+}
+
+impl From<EnvelopeError> for PayoutError {
+    fn from(error: EnvelopeError) -> Self {
+        PayoutError::Envelope(error)
+    }
+}
+
+impl From<RpcWrapperError> for PayoutError {
+    fn from(error: RpcWrapperError) -> Self {
+        PayoutError::Rpc(error)
+    }
+}
+
+impl From<ClientError> for PayoutError {
+    fn from(error: ClientError) -> Self {
+        PayoutError::Rpc(RpcWrapperError::RpcError(error))
+    }
+}
+
+pub type PayoutResult<T> = Result<T, PayoutError>;
+
+/// A row of the manifest, as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestRow {
+    /// The address of the account to be paid
+    pub address: String,
+    /// The amount, in tokens of the asset
+    pub amount: String,
+    /// The ID of the asset to be transferred (VSL if not given)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+}
+
+/// The rows of a payout
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Manifest {
+    pub rows: Vec<ManifestRow>,
+}
+
+impl Manifest {
+    /// Reads a manifest from a JSON (`.json`) or CSV (any other extension) file
+    pub fn read<P: AsRef<Path>>(path: P) -> PayoutResult<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Self::from_csv(&contents),
+        }
+    }
+
+    /// Parses a JSON array of rows
+    pub fn from_json(json: &str) -> PayoutResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Parses `address,amount[,asset]` lines; empty lines, `#` comments and a
+    /// header line starting with `address` are skipped
+    pub fn from_csv(csv: &str) -> PayoutResult<Self> {
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let lines = csv
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for (index, line) in lines.enumerate() {
+            let fields: Vec<&str> = line
+                .split(',')
+                .map(|field| field.trim().trim_matches('"'))
+                .collect();
+            if index == 0 && fields[0].eq_ignore_ascii_case("address") {
+                continue;
+            }
+            let row = rows.len() + errors.len();
+            match fields[..] {
+                [address, amount] | [address, amount, ""] => rows.push(ManifestRow {
+                    address: address.to_string(),
+                    amount: amount.to_string(),
+                    asset: None,
+                }),
+                [address, amount, asset] => rows.push(ManifestRow {
+                    address: address.to_string(),
+                    amount: amount.to_string(),
+                    asset: Some(asset.to_string()),
+                }),
+                _ => errors.push(RowError {
+                    row,
+                    reason: format!("expected 2 or 3 fields, found {}", fields.len()),
+                }),
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(Manifest { rows }),
+            false => Err(PayoutError::InvalidRows(errors)),
+        }
+    }
+}
+
+/// The status of a row of the payout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PayoutStatus {
+    /// Not signed yet
+    Unsent,
+    /// Signed and (possibly) submitted, with an unknown outcome
+    Sent { envelope: Box<SignedEnvelope> },
+    /// Paid
+    Settled { claim_id: B256 },
+    /// Rejected by the node
+    Failed { error: String },
+}
+
+/// A validated row of the manifest, and its status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutReceipt {
+    /// The index of the row in the manifest
+    pub row: usize,
+    pub to: Address,
+    pub amount: TokenAmount,
+    #[serde(flatten)]
+    pub status: PayoutStatus,
+}
+
+impl PayoutReceipt {
+    /// Whether the payment remains to be made
+    fn is_open(&self) -> bool {
+        matches!(
+            self.status,
+            PayoutStatus::Unsent | PayoutStatus::Sent { .. }
+        )
+    }
+
+    fn message(&self, from: &Address, nonce: u64) -> UnsignedMessage {
+        match self.amount.asset() {
+            Some(asset_id) => UnsignedMessage::transfer_asset(
+                from,
+                nonce,
+                asset_id,
+                &self.to,
+                &self.amount.amount(),
+            ),
+            None => UnsignedMessage::pay(from, nonce, &self.to, &self.amount.amount()),
+        }
+    }
+}
+
+/// Validates a row, looking up (and caching) the metadata of its asset
+async fn validate<T: ClientT>(
+    rpc_client: &T,
+    assets: &mut HashMap<AssetId, Option<AssetData>>,
+    row: &ManifestRow,
+) -> Result<(Address, TokenAmount), String> {
+    let to = Address::from_str(&row.address)
+        .map_err(|error| format!("invalid address {}: {error}", row.address))?;
+    let amount = match &row.asset {
+        None => TokenAmount::parse_vsl(&row.amount),
+        Some(asset) => {
+            let asset_id = AssetId::from_str(asset)
+                .map_err(|error| format!("invalid asset ID {asset}: {error}"))?;
+            let asset_data = match assets.entry(asset_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let asset_data = rpc_wrapper::get_asset_by_id(rpc_client, &asset_id)
+                        .await
+                        .map_err(|error| format!("cannot get asset {asset}: {error}"))?;
+                    entry.insert(asset_data)
+                }
+            };
+            let Some(asset_data) = asset_data else {
+                return Err(format!("unknown asset {asset}"));
+            };
+            TokenAmount::parse_asset(&row.amount, asset_id, asset_data)
+        }
+    }
+    .map_err(|error| format!("invalid amount {}: {error}", row.amount))?;
+    if amount.is_zero() {
+        return Err("zero amount".to_string());
+    }
+    Ok((to, amount))
+}
+
+/// The content of a receipt file
+#[derive(Serialize, Deserialize)]
+struct ReceiptFile {
+    #[serde(with = "crate::amount_serde::decimal")]
+    validation_fee: Amount,
+    receipts: Vec<PayoutReceipt>,
+}
+
+/// A validated payout, with the status of each row
+#[derive(Debug, Clone)]
+pub struct Payout {
+    receipts: Vec<PayoutReceipt>,
+    validation_fee: Amount,
+    network: Option<String>,
+    claim_not_found: ClaimNotFound,
+}
+
+impl Payout {
+    /// Validates all the rows of the manifest, given the validation fee charged
+    /// for each payment
+    pub async fn prepare<T: ClientT>(
+        rpc_client: &T,
+        manifest: &Manifest,
+        validation_fee: Amount,
+    ) -> PayoutResult<Self> {
+        let mut assets = HashMap::new();
+        let mut receipts = Vec::with_capacity(manifest.rows.len());
+        let mut errors = Vec::new();
+        for (row, fields) in manifest.rows.iter().enumerate() {
+            match validate(rpc_client, &mut assets, fields).await {
+                Ok((to, amount)) => receipts.push(PayoutReceipt {
+                    row,
+                    to,
+                    amount,
+                    status: PayoutStatus::Unsent,
+                }),
+                Err(reason) => errors.push(RowError { row, reason }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(PayoutError::InvalidRows(errors));
+        }
+        Ok(Payout {
+            receipts,
+            validation_fee,
+            network: None,
+            claim_not_found: ClaimNotFound::default(),
+        })
+    }
+
+    /// Signs the payments for the given network
+    pub fn with_network(mut self, network: &str) -> Self {
+        self.network = Some(network.to_string());
+        self
+    }
+
+    /// Only takes the given answer of the node for an unknown claim, when
+    /// checking the payments sent before
+    pub fn with_claim_not_found(mut self, claim_not_found: ClaimNotFound) -> Self {
+        self.claim_not_found = claim_not_found;
+        self
+    }
+
+    pub fn receipts(&self) -> &[PayoutReceipt] {
+        &self.receipts
+    }
+
+    /// Whether all the rows are settled or failed
+    pub fn is_complete(&self) -> bool {
+        !self.receipts.iter().any(PayoutReceipt::is_open)
+    }
+
+    /// Takes the statuses from the receipt file, if it exists, and returns
+    /// whether it did
+    pub fn resume(&mut self, path: &Path) -> PayoutResult<bool> {
+        let file: ReceiptFile = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error.into()),
+        };
+        if file.receipts.len() != self.receipts.len() {
+            let row = file.receipts.len().min(self.receipts.len());
+            return Err(PayoutError::ManifestChanged { row });
+        }
+        for (receipt, recorded) in self.receipts.iter().zip(&file.receipts) {
+            if (recorded.row, recorded.to, recorded.amount)
+                != (receipt.row, receipt.to, receipt.amount)
+            {
+                return Err(PayoutError::ManifestChanged { row: receipt.row });
+            }
+        }
+        self.receipts = file.receipts;
+        Ok(true)
+    }
+
+    /// Writes the receipt file (atomically, replacing it)
+    pub fn save(&self, path: &Path) -> PayoutResult<()> {
+        let file = ReceiptFile {
+            validation_fee: self.validation_fee,
+            receipts: self.receipts.clone(),
+        };
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(&file)?)?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// The amounts remaining to be paid, by asset (`None` for VSL, including
+    /// the validation fees)
+    pub fn required(&self) -> HashMap<Option<AssetId>, Amount> {
+        let mut required = HashMap::new();
+        for receipt in self.receipts.iter().filter(|receipt| receipt.is_open()) {
+            *required.entry(None).or_default() += self.validation_fee;
+            *required.entry(receipt.amount.asset().copied()).or_default() +=
+                receipt.amount.amount();
+        }
+        required
+    }
+
+    /// Checks that the balances of the sender cover the remaining payments
+    pub async fn check_balance<T: ClientT>(
+        &self,
+        rpc_client: &T,
+        sender: &Address,
+    ) -> PayoutResult<()> {
+        for (asset, required) in self.required() {
+            let available = match &asset {
+                None => rpc_wrapper::get_balance(rpc_client, sender).await?,
+                Some(asset_id) => {
+                    rpc_wrapper::get_asset_balance(rpc_client, sender, asset_id).await?
+                }
+            };
+            if available < required {
+                return Err(PayoutError::InsufficientBalance {
+                    asset,
+                    required,
+                    available,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the sent rows whose claim was settled, and keeps the other sent rows
+    /// only if their nonces follow the account nonce (the others are signed again)
+    async fn reconcile<T: ClientT>(
+        &mut self,
+        rpc_client: &T,
+        sender: &Address,
+    ) -> PayoutResult<()> {
+        let mut sent = Vec::new();
+        for (index, receipt) in self.receipts.iter().enumerate() {
+            if let PayoutStatus::Sent { envelope } = &receipt.status {
+                if envelope.signer != *sender {
+                    return Err(PayoutError::SignerMismatch {
+                        expected: *sender,
+                        found: envelope.signer,
+                    });
+                }
+                sent.push((envelope.nonce, index));
+            }
+        }
+        sent.sort();
+        let mut next = rpc_wrapper::get_account_nonce(rpc_client, sender).await?;
+        for (nonce, index) in sent {
+            let receipt = &mut self.receipts[index];
+            let PayoutStatus::Sent { envelope } = &receipt.status else {
+                continue;
+            };
+            let claim_id = envelope
+                .message
+                .claim_id()
+                .expect("payments have a claim ID");
+            let settled = rpc_wrapper::claim_exists(
+                rpc_client,
+                ClaimStage::Settled,
+                &claim_id,
+                self.claim_not_found,
+            )
+            .await?;
+            receipt.status = if settled {
+                PayoutStatus::Settled { claim_id }
+            } else if nonce == next {
+                next += 1;
+                continue;
+            } else {
+                PayoutStatus::Unsent
+            };
+        }
+        Ok(())
+    }
+
+    /// Signs the unsent rows with consecutive nonces, following the given one
+    /// and those of the sent rows; returns the indices of the sent rows, by nonce
+    fn sign_unsent(&mut self, signer: &PrivateKeySigner, nonce: u64) -> PayoutResult<Vec<usize>> {
+        let mut sent: Vec<(u64, usize)> = Vec::new();
+        for (index, receipt) in self.receipts.iter().enumerate() {
+            if let PayoutStatus::Sent { envelope } = &receipt.status {
+                sent.push((envelope.nonce, index));
+            }
+        }
+        let mut next = sent
+            .iter()
+            .map(|(nonce, _)| nonce + 1)
+            .max()
+            .unwrap_or(nonce);
+        for (index, receipt) in self.receipts.iter_mut().enumerate() {
+            if !matches!(receipt.status, PayoutStatus::Unsent) {
+                continue;
+            }
+            let message = receipt.message(&signer.address(), next);
+            let envelope = UnsignedEnvelope::new(message, self.network.clone())?.sign(signer)?;
+            receipt.status = PayoutStatus::Sent {
+                envelope: Box::new(envelope),
+            };
+            sent.push((next, index));
+            next += 1;
+        }
+        sent.sort();
+        Ok(sent.into_iter().map(|(_, index)| index).collect())
+    }
+
+    /// Pays the remaining rows, recording their status in the receipt file.
+    ///
+    /// If a payment is rejected by the node, the row is marked as failed, and the
+    /// following rows are signed again (with the nonces left free). If the outcome
+    /// of a payment is unknown (e.g. the node cannot be reached, or a gateway
+    /// answers instead), the receipt file is saved, and the payout can be resumed
+    /// later.
+    pub async fn run<T: ClientT>(
+        &mut self,
+        rpc_client: &T,
+        signer: &PrivateKeySigner,
+        path: &Path,
+    ) -> PayoutResult<()> {
+        let sender = signer.address();
+        self.reconcile(rpc_client, &sender).await?;
+        self.save(path)?;
+        self.check_balance(rpc_client, &sender).await?;
+        while !self.is_complete() {
+            let nonce = rpc_wrapper::get_account_nonce(rpc_client, &sender).await?;
+            let sent = self.sign_unsent(signer, nonce)?;
+            self.save(path)?;
+            let mut rejected = false;
+            for (count, index) in sent.into_iter().enumerate() {
+                let receipt = &mut self.receipts[index];
+                let PayoutStatus::Sent { envelope } = &receipt.status else {
+                    continue;
+                };
+                if rejected {
+                    receipt.status = PayoutStatus::Unsent;
+                    continue;
+                }
+                receipt.status = match broadcast(rpc_client, envelope).await {
+                    Ok(BroadcastReceipt::Claim(claim_id))
+                    | Ok(BroadcastReceipt::Asset { claim_id, .. }) => {
+                        PayoutStatus::Settled { claim_id }
+                    }
+                    Err(RpcWrapperError::RpcError(ClientError::Call(error)))
+                        if rpc_wrapper::is_node_rejection(&error) =>
+                    {
+                        rejected = true;
+                        PayoutStatus::Failed {
+                            error: error.message().to_string(),
+                        }
+                    }
+                    Err(error) => {
+                        self.save(path)?;
+                        return Err(error.into());
+                    }
+                };
+                if (count + 1) % SAVE_INTERVAL == 0 {
+                    self.save(path)?;
+                }
+            }
+            self.save(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use jsonrpsee::core::RpcResult;
+    use jsonrpsee::http_client::HttpClient;
+    use jsonrpsee::server::ServerHandle;
+    use jsonrpsee::types::Params;
+
+    use super::*;
+    use crate::rpc_messages::CreateAssetMessage;
+    use crate::test_node::{self, Ledger};
+
+    const ASSET: &str = "fefefefefefefefefefefefefefefefefefefefefefefefefefefefefefefefe";
+
+    /// A stand-in node paying from the ledger, and knowing the asset [ASSET]
+    async fn serve(node: Arc<Mutex<Ledger>>) -> (HttpClient, ServerHandle) {
+        let mut module = Ledger::module(node);
+        module
+            .register_method(
+                "vsl_getAssetById",
+                |params: Params, _, _| -> RpcResult<Option<CreateAssetMessage>> {
+                    let asset_id: String = params.one()?;
+                    Ok((asset_id == ASSET).then(|| CreateAssetMessage {
+                        account_id: Address::ZERO.to_string(),
+                        nonce: "0".to_string(),
+                        ticker_symbol: "TCK".to_string(),
+                        decimals: 2,
+                        total_supply: Amount::from_tokens(1000, 2).to_hex_str(),
+                    }))
+                },
+            )
+            .unwrap();
+        test_node::serve(module).await
+    }
+
+    fn manifest() -> Manifest {
+        let csv = format!(
+            "address,amount,asset\n\
+             {},1.5\n\
+             # a comment\n\
+             {},2\n\
+             {},0.25,{ASSET}\n",
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+            Address::repeat_byte(0x03),
+        );
+        Manifest::from_csv(&csv).unwrap()
+    }
+
+    fn receipts_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("vsl-payout-{name}-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn claim_ids(payout: &Payout) -> Vec<Option<B256>> {
+        let receipts = payout.receipts().iter();
+        receipts
+            .map(|receipt| match receipt.status {
+                PayoutStatus::Settled { claim_id } => Some(claim_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_manifest() {
+        let manifest = manifest();
+        assert_eq!(manifest.rows.len(), 3);
+        assert_eq!(manifest.rows[1].amount, "2");
+        assert_eq!(manifest.rows[2].asset.as_deref(), Some(ASSET));
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+
+        let Err(PayoutError::InvalidRows(errors)) = Manifest::from_csv("0x01,1\n0x02\n") else {
+            panic!("expected invalid rows");
+        };
+        assert_eq!(errors[0].row, 1);
+    }
+
+    #[tokio::test]
+    async fn test_prepare() {
+        let node = Arc::new(Mutex::new(Ledger::default()));
+        let (client, _handle) = serve(node.clone()).await;
+        let fee = Amount::from_vsl_tokens(1);
+        let payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        let required = payout.required();
+        assert_eq!(required[&None], Amount::from_subunits(65) * 10u128.pow(17));
+        let asset = AssetId::from_str(ASSET).unwrap();
+        assert_eq!(required[&Some(asset)], Amount::from_tokens(25, 2) / 100);
+        payout.check_balance(&client, &Address::ZERO).await.unwrap();
+
+        node.lock().unwrap().balance = Amount::from_vsl_tokens(6);
+        assert!(matches!(
+            payout.check_balance(&client, &Address::ZERO).await,
+            Err(PayoutError::InsufficientBalance { asset: None, .. })
+        ));
+
+        let mut manifest = manifest();
+        manifest.rows[0].address = "0x1234".to_string();
+        manifest.rows[1].amount = "0.0000000000000000001".to_string();
+        manifest.rows[2].asset = Some(B256::repeat_byte(0x01).to_string());
+        let Err(PayoutError::InvalidRows(errors)) = Payout::prepare(&client, &manifest, fee).await
+        else {
+            panic!("expected invalid rows");
+        };
+        let rows: Vec<_> = errors.iter().map(|error| error.row).collect();
+        assert_eq!(rows, [0, 1, 2]);
+        assert!(errors[2].reason.starts_with("unknown asset"));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let node = Arc::new(Mutex::new(Ledger {
+            reject_after: Some(1),
+            ..Ledger::default()
+        }));
+        let (client, _handle) = serve(node.clone()).await;
+        let signer = PrivateKeySigner::random();
+        let path = receipts_path("run");
+        let fee = Amount::from_subunits(1);
+        let mut payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        payout.run(&client, &signer, &path).await.unwrap();
+        assert!(payout.is_complete());
+        // the second row is rejected, and the third one is signed again
+        assert!(matches!(
+            payout.receipts()[1].status,
+            PayoutStatus::Failed { .. }
+        ));
+        let paid = claim_ids(&payout);
+        assert!(paid[0].is_some() && paid[2].is_some());
+        assert_eq!(node.lock().unwrap().payments, 2);
+
+        // resuming a complete payout pays nothing
+        let mut resumed = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        assert!(resumed.resume(&path).unwrap());
+        resumed.run(&client, &signer, &path).await.unwrap();
+        assert_eq!(claim_ids(&resumed), paid);
+        assert_eq!(node.lock().unwrap().payments, 2);
+
+        let mut changed = manifest();
+        changed.rows[1].amount = "3".to_string();
+        let mut changed = Payout::prepare(&client, &changed, fee).await.unwrap();
+        assert!(matches!(
+            changed.resume(&path),
+            Err(PayoutError::ManifestChanged { row: 1 })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_outcome() {
+        let node = Arc::new(Mutex::new(Ledger {
+            upstream_error_after: Some(1),
+            ..Ledger::default()
+        }));
+        let (client, _handle) = serve(node.clone()).await;
+        let signer = PrivateKeySigner::random();
+        let path = receipts_path("unknown_outcome");
+        let fee = Amount::from_subunits(1);
+
+        // the second payment goes through, but only the gateway answers
+        let mut payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        let result = payout.run(&client, &signer, &path).await;
+        assert!(matches!(result, Err(PayoutError::Rpc(_))));
+        assert!(matches!(
+            payout.receipts()[1].status,
+            PayoutStatus::Sent { .. }
+        ));
+        assert_eq!(node.lock().unwrap().payments, 2);
+
+        // resuming finds it settled, and pays the last row only
+        let mut payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        assert!(payout.resume(&path).unwrap());
+        payout.run(&client, &signer, &path).await.unwrap();
+        assert!(claim_ids(&payout).iter().all(Option::is_some));
+        assert_eq!(node.lock().unwrap().payments, 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let node = Arc::new(Mutex::new(Ledger::default()));
+        let (client, _handle) = serve(node.clone()).await;
+        let signer = PrivateKeySigner::random();
+        let path = receipts_path("resume");
+        let fee = Amount::from_subunits(1);
+
+        // interrupted after signing all rows, and submitting the first one
+        let mut interrupted = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        let sent = interrupted.sign_unsent(&signer, 0).unwrap();
+        interrupted.save(&path).unwrap();
+        let PayoutStatus::Sent { envelope } = &interrupted.receipts()[sent[0]].status else {
+            panic!("expected a sent row");
+        };
+        broadcast(&client, envelope).await.unwrap();
+
+        // whether the rows landed cannot be known: nothing is signed again
+        node.lock().unwrap().rate_limited = true;
+        let mut payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        assert!(payout.resume(&path).unwrap());
+        let result = payout.run(&client, &signer, &path).await;
+        assert!(matches!(result, Err(PayoutError::Rpc(_))));
+        assert_eq!(node.lock().unwrap().payments, 1);
+        node.lock().unwrap().rate_limited = false;
+
+        let mut payout = Payout::prepare(&client, &manifest(), fee).await.unwrap();
+        assert!(payout.resume(&path).unwrap());
+        payout.run(&client, &signer, &path).await.unwrap();
+        assert!(claim_ids(&payout).iter().all(Option::is_some));
+        assert_eq!(node.lock().unwrap().payments, 3);
+        assert_eq!(node.lock().unwrap().nonce, 3);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,643 @@
+//! # Retry Module
+//!
+//! A policy layer over a JSON-RPC client: [RetryClient] implements [ClientT]
+//! (hence [crate::rpc_service::ClaimRpcClient], and can be used with
+//! [crate::rpc_wrapper::RpcWrapper], or over a [crate::failover::FailoverClient]):
+//!
+//! - each attempt of a call is bounded by the timeout of the method (if any);
+//! - reads failing because the endpoint could not be reached are retried, with
+//!   exponential backoff and jitter;
+//! - writes are not retried by default, since the first attempt may have landed.
+//!   With [RetryPolicy::with_write_retries], the writes whose claim ID can be
+//!   computed locally (`vsl_submitClaim`, `vsl_pay` and `vsl_transferAsset`)
+//!   are retried once the claim is known not to have been recorded (with
+//!   `vsl_getSubmittedClaimById` or `vsl_getSettledClaimById`); the claim ID is
+//!   returned if it was.
+//!
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::time::Duration;
+
+use alloy::consensus::Signed;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::{BatchResponse, ClientT, Subscription, SubscriptionClientT};
+use jsonrpsee::core::params::BatchRequestBuilder;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::tokio;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_json::value::RawValue;
+
+use crate::B256;
+use crate::failover::{is_unavailable, is_write_method};
+use crate::rpc_messages::{IdentifiableClaim, PayMessage, SubmittedClaim, TransferAssetMessage};
+use crate::rpc_wrapper::{ClaimNotFound, ClaimStage, claim_exists};
+
+/// When and how often failed calls are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts of a call (1: no retries)
+    pub max_attempts: u32,
+    /// The delay before the first retry
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts
+    pub max_backoff: Duration,
+    /// The factor applied to the delay after each attempt
+    pub multiplier: f64,
+    /// The fraction (between 0 and 1) of each delay which is randomized
+    pub jitter: f64,
+    /// The timeout of each attempt of the methods without a specific timeout
+    pub timeout: Option<Duration>,
+    /// The timeouts of specific methods
+    pub method_timeouts: HashMap<String, Duration>,
+    /// Whether the writes whose outcome can be checked are retried
+    pub retry_writes: bool,
+    /// How the node answers the lookup of an unknown claim
+    pub claim_not_found: ClaimNotFound,
+}
+
+/// No retries and no timeouts
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+            timeout: None,
+            method_timeouts: HashMap::new(),
+            retry_writes: false,
+            claim_not_found: ClaimNotFound::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts of each read
+    pub fn with_max_attempts(self, max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    pub fn with_backoff(self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    pub fn with_jitter(self, jitter: f64) -> Self {
+        RetryPolicy {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        RetryPolicy {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    pub fn with_method_timeout(mut self, method: &str, timeout: Duration) -> Self {
+        self.method_timeouts.insert(method.to_string(), timeout);
+        self
+    }
+
+    /// Retries the writes whose outcome can be checked (see the module documentation)
+    pub fn with_write_retries(self) -> Self {
+        RetryPolicy {
+            retry_writes: true,
+            ..self
+        }
+    }
+
+    /// Only takes the given answer of the node for an unknown claim, when checking
+    /// whether a write landed
+    pub fn with_claim_not_found(self, claim_not_found: ClaimNotFound) -> Self {
+        RetryPolicy {
+            claim_not_found,
+            ..self
+        }
+    }
+
+    /// The timeout of each attempt of the method, if any
+    pub fn timeout_of(&self, method: &str) -> Option<Duration> {
+        self.method_timeouts.get(method).copied().or(self.timeout)
+    }
+
+    /// The delay before the given retry (the first retry being 1), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+
+    /// The delay before the given retry, with jitter
+    fn jittered_backoff(&self, retry: u32) -> Duration {
+        // the jitter only spreads the retries of several clients: a
+        // cryptographically secure source of randomness is not needed
+        let random = RandomState::new().build_hasher().finish();
+        let fraction = (random >> 11) as f64 / (1u64 << 53) as f64;
+        self.backoff(retry).mul_f64(1.0 - self.jitter * fraction)
+    }
+}
+
+/// Already serialized parameters, which can be sent several times
+#[derive(Clone)]
+struct RawParams(Option<Box<RawValue>>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
+
+/// The first of positional (`[value, ...]`) or named (`{"name": value, ...}`) parameters
+fn first_param(params: Value) -> Option<Value> {
+    match params {
+        Value::Array(values) => values.into_iter().next(),
+        Value::Object(values) => values.into_iter().next().map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+/// How the outcome of a write can be checked
+enum WriteCheck {
+    Submitted(B256),
+    Settled(B256),
+}
+
+impl WriteCheck {
+    /// The check of the write with the given (signed message) parameters, if supported
+    fn of(method: &str, params: &Option<Box<RawValue>>) -> Option<Self> {
+        let params: Value = serde_json::from_str(params.as_ref()?.get()).ok()?;
+        let message = first_param(params)?;
+        fn claim_id<T: IdentifiableClaim + DeserializeOwned>(message: Value) -> Option<B256>
+        where
+            Signed<T>: DeserializeOwned,
+        {
+            let signed: Signed<T> = serde_json::from_value(message).ok()?;
+            Some(signed.tx().claim_id())
+        }
+        match method {
+            "vsl_submitClaim" => claim_id::<SubmittedClaim>(message).map(WriteCheck::Submitted),
+            "vsl_pay" => claim_id::<PayMessage>(message).map(WriteCheck::Settled),
+            "vsl_transferAsset" => {
+                claim_id::<TransferAssetMessage>(message).map(WriteCheck::Settled)
+            }
+            _ => None,
+        }
+    }
+
+    fn claim_id(&self) -> &B256 {
+        match self {
+            WriteCheck::Submitted(claim_id) | WriteCheck::Settled(claim_id) => claim_id,
+        }
+    }
+}
+
+/// A client applying a [RetryPolicy] to the calls of another client
+#[derive(Debug, Clone)]
+pub struct RetryClient<C> {
+    client: C,
+    policy: RetryPolicy,
+}
+
+impl<C> RetryClient<C> {
+    pub fn new(client: C, policy: RetryPolicy) -> Self {
+        RetryClient { client, policy }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+/// Bounds the future by the timeout, if any
+async fn with_timeout<R>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<R, ClientError>>,
+) -> Result<R, ClientError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or(Err(ClientError::RequestTimeout)),
+        None => future.await,
+    }
+}
+
+impl<C: ClientT + Send + Sync> RetryClient<C> {
+    async fn request_once<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: RawParams,
+    ) -> Result<R, ClientError> {
+        let timeout = self.policy.timeout_of(method);
+        with_timeout(timeout, self.client.request(method, params)).await
+    }
+
+    async fn retry_read<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: RawParams,
+    ) -> Result<R, ClientError> {
+        let mut attempt = 1;
+        loop {
+            match self.request_once(method, params.clone()).await {
+                Err(error) if is_unavailable(&error) && attempt < self.policy.max_attempts => {}
+                result => return result,
+            }
+            tokio::time::sleep(self.policy.jittered_backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Whether the claim of the write has been recorded (an error if that cannot
+    /// be known)
+    async fn landed(&self, check: &WriteCheck) -> Result<bool, ClientError> {
+        let (stage, claim_id) = match check {
+            WriteCheck::Submitted(claim_id) => (ClaimStage::Submitted, claim_id),
+            WriteCheck::Settled(claim_id) => (ClaimStage::Settled, claim_id),
+        };
+        let timeout = self.policy.timeout_of(stage.method());
+        let not_found = self.policy.claim_not_found;
+        with_timeout(
+            timeout,
+            claim_exists(&self.client, stage, claim_id, not_found),
+        )
+        .await
+    }
+
+    async fn retry_write<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: RawParams,
+        check: WriteCheck,
+    ) -> Result<R, ClientError> {
+        let mut error = match self.request_once(method, params.clone()).await {
+            Err(error) if is_unavailable(&error) => error,
+            result => return result,
+        };
+        let mut attempt = 1;
+        while attempt < self.policy.max_attempts {
+            tokio::time::sleep(self.policy.jittered_backoff(attempt)).await;
+            attempt += 1;
+            match self.landed(&check).await {
+                Ok(true) => {
+                    let claim_id = Value::String(check.claim_id().to_string());
+                    return Ok(serde_json::from_value(claim_id)?);
+                }
+                Ok(false) => match self.request_once(method, params.clone()).await {
+                    Err(retry_error) if is_unavailable(&retry_error) => error = retry_error,
+                    result => return result,
+                },
+                // the outcome of the write is still unknown
+                Err(check_error) if is_unavailable(&check_error) => {}
+                Err(check_error) => return Err(check_error),
+            }
+        }
+        Err(error)
+    }
+}
+
+impl<C: ClientT + Send + Sync> ClientT for RetryClient<C> {
+    async fn notification<Params>(&self, method: &str, params: Params) -> Result<(), ClientError>
+    where
+        Params: ToRpcParams + Send,
+    {
+        let timeout = self.policy.timeout_of(method);
+        with_timeout(timeout, self.client.notification(method, params)).await
+    }
+
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        let params = params.to_rpc_params()?;
+        if !is_write_method(method) {
+            return self.retry_read(method, RawParams(params)).await;
+        }
+        match WriteCheck::of(method, &params) {
+            Some(check) if self.policy.retry_writes => {
+                self.retry_write(method, RawParams(params), check).await
+            }
+            _ => self.request_once(method, RawParams(params)).await,
+        }
+    }
+
+    /// Batches may contain writes: they are not retried
+    async fn batch_request<'a, R>(
+        &self,
+        batch: BatchRequestBuilder<'a>,
+    ) -> Result<BatchResponse<'a, R>, ClientError>
+    where
+        R: DeserializeOwned + std::fmt::Debug + 'a,
+    {
+        with_timeout(self.policy.timeout, self.client.batch_request(batch)).await
+    }
+}
+
+impl<C: SubscriptionClientT + Send + Sync> SubscriptionClientT for RetryClient<C> {
+    async fn subscribe<'a, Notif, Params>(
+        &self,
+        subscribe_method: &'a str,
+        params: Params,
+        unsubscribe_method: &'a str,
+    ) -> Result<Subscription<Notif>, ClientError>
+    where
+        Params: ToRpcParams + Send,
+        Notif: DeserializeOwned,
+    {
+        let params = RawParams(params.to_rpc_params()?);
+        let timeout = self.policy.timeout_of(subscribe_method);
+        let mut attempt = 1;
+        loop {
+            let subscription =
+                self.client
+                    .subscribe(subscribe_method, params.clone(), unsubscribe_method);
+            match with_timeout(timeout, subscription).await {
+                Err(error) if is_unavailable(&error) && attempt < self.policy.max_attempts => {}
+                result => return result,
+            }
+            tokio::time::sleep(self.policy.jittered_backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn subscribe_to_method<Notif>(
+        &self,
+        method: &str,
+    ) -> Result<Subscription<Notif>, ClientError>
+    where
+        Notif: DeserializeOwned,
+    {
+        self.client.subscribe_to_method(method).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::gateway::RATE_LIMITED_CODE;
+    use crate::rpc_service::ClaimRpcClient;
+    use crate::{Address, IntoSigned};
+
+    /// A stand-in client, whose connection drops as scripted
+    #[derive(Default)]
+    struct FlakyClient {
+        /// The number of calls of each method
+        calls: Mutex<HashMap<String, u32>>,
+        /// The number of times each method fails before being reached
+        failures: HashMap<&'static str, u32>,
+        /// Whether writes land, but their responses are lost
+        lose_responses: bool,
+        /// Whether the claim lookups are rate limited (by a gateway)
+        rate_limited: bool,
+        /// The IDs of the recorded claims
+        claims: Mutex<HashSet<String>>,
+    }
+
+    impl FlakyClient {
+        fn calls(&self, method: &str) -> u32 {
+            self.calls.lock().unwrap().get(method).copied().unwrap_or(0)
+        }
+
+        fn answer(&self, method: &str, param: Value) -> Result<Value, ClientError> {
+            let calls = {
+                let mut calls = self.calls.lock().unwrap();
+                let count = calls.entry(method.to_string()).or_default();
+                *count += 1;
+                *count
+            };
+            let lost = || ClientError::Transport("connection reset".into());
+            if calls <= self.failures.get(method).copied().unwrap_or(0) {
+                return Err(lost());
+            }
+            match method {
+                "vsl_getHealth" => Ok("ok".into()),
+                "vsl_pay" => {
+                    let signed: Signed<PayMessage> = serde_json::from_value(param)?;
+                    let claim_id = signed.tx().claim_id().to_string();
+                    self.claims.lock().unwrap().insert(claim_id.clone());
+                    if self.lose_responses && calls == 1 {
+                        return Err(lost());
+                    }
+                    Ok(claim_id.into())
+                }
+                "vsl_getSettledClaimById" => {
+                    let claim_id = param.as_str().unwrap_or_default();
+                    let error = |code, message| {
+                        ClientError::Call(jsonrpsee::types::ErrorObjectOwned::owned(
+                            code, message, None::<()>,
+                        ))
+                    };
+                    if self.rate_limited {
+                        Err(error(RATE_LIMITED_CODE, "rate limited"))
+                    } else if self.claims.lock().unwrap().contains(claim_id) {
+                        Ok(serde_json::json!({ "id": claim_id }))
+                    } else {
+                        Err(error(-32000, "unknown claim"))
+                    }
+                }
+                _ => Err(lost()),
+            }
+        }
+    }
+
+    impl ClientT for FlakyClient {
+        async fn notification<Params>(&self, _: &str, _: Params) -> Result<(), ClientError>
+        where
+            Params: ToRpcParams + Send,
+        {
+            Ok(())
+        }
+
+        async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+        where
+            R: DeserializeOwned,
+            Params: ToRpcParams + Send,
+        {
+            let param = match params.to_rpc_params()? {
+                Some(params) => first_param(serde_json::from_str(params.get())?),
+                None => None,
+            };
+            if method == "vsl_getAccountNonce" {
+                // never answers
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            let answer = self.answer(method, param.unwrap_or_default())?;
+            Ok(serde_json::from_value(answer)?)
+        }
+
+        async fn batch_request<'a, R>(
+            &self,
+            _: BatchRequestBuilder<'a>,
+        ) -> Result<BatchResponse<'a, R>, ClientError>
+        where
+            R: DeserializeOwned + std::fmt::Debug + 'a,
+        {
+            Err(ClientError::HttpNotImplemented)
+        }
+    }
+
+    impl SubscriptionClientT for FlakyClient {
+        async fn subscribe<'a, Notif, Params>(
+            &self,
+            _: &'a str,
+            _: Params,
+            _: &'a str,
+        ) -> Result<Subscription<Notif>, ClientError>
+        where
+            Params: ToRpcParams + Send,
+            Notif: DeserializeOwned,
+        {
+            Err(ClientError::HttpNotImplemented)
+        }
+
+        async fn subscribe_to_method<Notif>(
+            &self,
+            _: &str,
+        ) -> Result<Subscription<Notif>, ClientError>
+        where
+            Notif: DeserializeOwned,
+        {
+            Err(ClientError::HttpNotImplemented)
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(4))
+    }
+
+    fn payment() -> Signed<PayMessage> {
+        let signer = PrivateKeySigner::random();
+        PayMessage {
+            from: signer.address().to_string(),
+            to: Address::repeat_byte(0x01).to_string(),
+            amount: "0x1".to_string(),
+            nonce: "0".to_string(),
+        }
+        .into_signed(&signer)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .with_jitter(0.5);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        for retry in 1..4 {
+            let delay = policy.jittered_backoff(retry);
+            assert!(delay <= policy.backoff(retry));
+            assert!(delay >= policy.backoff(retry) / 2);
+        }
+        let policy = policy.with_timeout(Duration::from_secs(1));
+        let policy = policy.with_method_timeout("vsl_pay", Duration::from_secs(5));
+        assert_eq!(policy.timeout_of("vsl_pay"), Some(Duration::from_secs(5)));
+        assert_eq!(
+            policy.timeout_of("vsl_getHealth"),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_retries() {
+        let flaky = FlakyClient {
+            failures: [("vsl_getHealth", 2)].into(),
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy());
+        assert_eq!(client.get_health().await.unwrap(), "ok");
+        assert_eq!(client.inner().calls("vsl_getHealth"), 3);
+
+        let flaky = FlakyClient {
+            failures: [("vsl_getHealth", 3)].into(),
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy());
+        assert!(is_unavailable(&client.get_health().await.unwrap_err()));
+        assert_eq!(client.inner().calls("vsl_getHealth"), 3);
+
+        // no retries by default
+        let flaky = FlakyClient {
+            failures: [("vsl_getHealth", 1)].into(),
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, RetryPolicy::default());
+        assert!(client.get_health().await.is_err());
+        assert_eq!(client.inner().calls("vsl_getHealth"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let policy = policy().with_method_timeout("vsl_getAccountNonce", Duration::from_millis(10));
+        let client = RetryClient::new(FlakyClient::default(), policy);
+        let nonce = client.get_nonce(Address::ZERO.to_string()).await;
+        assert!(matches!(nonce, Err(ClientError::RequestTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_write_retries() {
+        // the payment landed, but the response was lost: it is not sent again
+        let flaky = FlakyClient {
+            lose_responses: true,
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy().with_write_retries());
+        let signed = payment();
+        let claim_id = signed.tx().claim_id();
+        assert_eq!(client.pay(signed).await.unwrap(), claim_id.to_string());
+        assert_eq!(client.inner().calls("vsl_pay"), 1);
+
+        // the payment did not land: it is sent again
+        let flaky = FlakyClient {
+            failures: [("vsl_pay", 1)].into(),
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy().with_write_retries());
+        client.pay(payment()).await.unwrap();
+        assert_eq!(client.inner().calls("vsl_pay"), 2);
+
+        // whether the payment landed is unknown: it is not sent again
+        let flaky = FlakyClient {
+            lose_responses: true,
+            rate_limited: true,
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy().with_write_retries());
+        let result = client.pay(payment()).await;
+        assert!(
+            matches!(result, Err(ClientError::Call(error)) if error.code() == RATE_LIMITED_CODE)
+        );
+        assert_eq!(client.inner().calls("vsl_pay"), 1);
+
+        // writes are not retried by default
+        let flaky = FlakyClient {
+            failures: [("vsl_pay", 1)].into(),
+            ..Default::default()
+        };
+        let client = RetryClient::new(flaky, policy());
+        assert!(client.pay(payment()).await.is_err());
+        assert_eq!(client.inner().calls("vsl_pay"), 1);
+    }
+}
@@ -341,6 +341,17 @@ pub trait ClaimRpc {
     #[method(name = "vsl_getHealth", param_kind = map)]
     async fn get_health(&self) -> RpcResult<String>;
 
+    /// Returns the name of the network the server belongs to, used as the
+    /// domain of signed messages.
+    ///
+    /// - Returns: the network name (e.g. "vsl-devnet"), or null if the server
+    ///   accepts messages signed without a domain. Servers which predate this
+    ///   method (the default) advertise no network.
+    #[method(name = "vsl_getNetwork", param_kind = map)]
+    async fn get_network(&self) -> RpcResult<Option<String>> {
+        Ok(None)
+    }
+
     /// [Subscription](https://geth.ethereum.org/docs/rpc/pubsub) to the settled claims metadata
     ///
     /// - yields: a stream of timestamped [SettledClaimData]s
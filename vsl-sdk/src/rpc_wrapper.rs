@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use alloy::consensus::Signed;
 use alloy::hex::{FromHex as _, FromHexError};
@@ -8,18 +11,27 @@ use alloy::signers::k256::SecretKey;
 use alloy::signers::local::PrivateKeySigner;
 use jsonrpsee::core::client::{ClientT, Error as RpcError, Subscription, SubscriptionClientT};
 use jsonrpsee::rpc_params;
+use jsonrpsee::types::ErrorObjectOwned;
+use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, METHOD_NOT_FOUND_CODE};
 use jsonrpsee::ws_client::WsClient;
+use serde::{Deserialize, Serialize};
 
+use crate::amount_serde;
+use crate::clock_skew::{ClockSkew, SkewEstimator, SkewPolicy, SkewSample, SkewWarning};
+use crate::envelope::EnvelopeError;
+use crate::gateway::{UNAUTHORIZED_CODE, UPSTREAM_ERROR_CODE};
 use crate::helpers::IntoSigned;
 use crate::rpc_messages::{
     AccountStateHash, CreateAssetMessage, CreateAssetResult, IdentifiableClaim as _, PayMessage,
     SetStateMessage, SettleClaimMessage, SettledClaimData, SettledVerifiedClaim, SubmittedClaim,
     SubmittedClaimData, Timestamped, TransferAssetMessage,
 };
-use crate::{Address, ParseAmountError, Timestamp, B256};
-use crate::{Amount, AssetId};
+use crate::state_tree::StateTree;
+use crate::{Address, B256, Clock, ParseAmountError, SystemClock, Timestamp};
+use crate::{Amount, AssetId, TokenAmount, TokenAmountError};
 
 /// Metadata about an asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetData {
     /// The address of the account creating the asset
     pub account_id: Address,
@@ -30,6 +42,7 @@ pub struct AssetData {
     /// Number of decimals
     pub decimals: u8,
     /// The amount used to initialize the asset
+    #[serde(with = "amount_serde::hex")]
     pub total_supply: Amount,
 }
 
@@ -49,7 +62,7 @@ impl TryFrom<CreateAssetMessage> for AssetData {
                 "Cannot parse Address".to_string(),
             ));
         };
-        let Ok(nonce) = u64::from_str_radix(&nonce, 10) else {
+        let Ok(nonce) = nonce.parse::<u64>() else {
             return Err(RpcWrapperError::ParseError(
                 "Cannot parse nonce".to_string(),
             ));
@@ -85,11 +98,62 @@ pub enum RpcWrapperError {
     FromHexError(FromHexError),
     SignError(SignError),
     AmountError(ParseAmountError),
+    TokenAmountError(TokenAmountError),
+    EnvelopeError(EnvelopeError),
     AssetError(bcs::Error),
     ParseError(String),
     NonExistentAsset,
+    /// The claim expiration time has already passed
+    ExpiredClaim(Timestamp),
+    /// The claim expiration time falls within the measured server clock skew
+    ExpiryWithinClockSkew {
+        expires: Timestamp,
+        skew: ClockSkew,
+    },
+    /// The server advertises a different network than the one signed for
+    NetworkMismatch {
+        expected: String,
+        found: String,
+    },
+    /// The server does not advertise any network, so it cannot be checked
+    NetworkNotAdvertised {
+        expected: String,
+    },
+}
+
+impl fmt::Display for RpcWrapperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RpcWrapperError::RpcError(error) => write!(f, "{error}"),
+            RpcWrapperError::FromHexError(error) => write!(f, "invalid hex string: {error}"),
+            RpcWrapperError::SignError(error) => write!(f, "cannot sign the message: {error}"),
+            RpcWrapperError::AmountError(error) => write!(f, "invalid amount: {error}"),
+            RpcWrapperError::TokenAmountError(error) => write!(f, "{error}"),
+            RpcWrapperError::EnvelopeError(error) => write!(f, "{error}"),
+            RpcWrapperError::AssetError(error) => write!(f, "invalid asset data: {error}"),
+            RpcWrapperError::ParseError(error) => write!(f, "{error}"),
+            RpcWrapperError::NonExistentAsset => write!(f, "no such asset"),
+            RpcWrapperError::ExpiredClaim(expires) => {
+                write!(f, "the claim expired at {}", expires.to_rfc3339())
+            }
+            RpcWrapperError::ExpiryWithinClockSkew { expires, skew } => write!(
+                f,
+                "the claim expires at {}, within the server clock skew margin of {:?}",
+                expires.to_rfc3339(),
+                skew.margin()
+            ),
+            RpcWrapperError::NetworkMismatch { expected, found } => {
+                write!(f, "expected network {expected}, the server is on {found}")
+            }
+            RpcWrapperError::NetworkNotAdvertised { expected } => {
+                write!(f, "expected network {expected}, the server advertises none")
+            }
+        }
+    }
 }
 
+impl std::error::Error for RpcWrapperError {}
+
 impl From<RpcError> for RpcWrapperError {
     fn from(value: RpcError) -> Self {
         Self::RpcError(value)
@@ -120,6 +184,18 @@ impl From<ParseAmountError> for RpcWrapperError {
     }
 }
 
+impl From<TokenAmountError> for RpcWrapperError {
+    fn from(value: TokenAmountError) -> Self {
+        Self::TokenAmountError(value)
+    }
+}
+
+impl From<EnvelopeError> for RpcWrapperError {
+    fn from(value: EnvelopeError) -> Self {
+        Self::EnvelopeError(value)
+    }
+}
+
 pub type RpcWrapperResult<T> = Result<T, RpcWrapperError>;
 
 pub struct RpcWrapper<T> {
@@ -127,6 +203,13 @@ pub struct RpcWrapper<T> {
     address: Address,
     nonce: u64,
     rpc_client: T,
+    clock: Arc<dyn Clock>,
+    skew_policy: SkewPolicy,
+    skew: SkewEstimator,
+    skew_warning: Option<SkewWarning>,
+    network: Option<String>,
+    network_checked: bool,
+    require_advertised_network: bool,
 }
 
 impl<T> RpcWrapper<T>
@@ -138,15 +221,53 @@ where
         nonce: Option<u64>,
         rpc_client: &T,
     ) -> RpcWrapperResult<Self> {
-        let nonce = nonce.unwrap_or(get_account_nonce(rpc_client, &signer.address()).await?);
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => get_account_nonce(rpc_client, &signer.address()).await?,
+        };
         Ok(Self {
             address: signer.address(),
             key: signer,
             nonce,
             rpc_client: rpc_client.clone(),
+            clock: Arc::new(SystemClock),
+            skew_policy: SkewPolicy::default(),
+            skew: SkewEstimator::default(),
+            skew_warning: None,
+            network: None,
+            network_checked: false,
+            require_advertised_network: false,
         })
     }
 
+    /// Signs messages for the given network only (see [crate::network]), and refuses
+    /// to submit them to a server advertising a different network
+    pub fn with_network(mut self, network: &str) -> Self {
+        self.network = Some(network.to_string());
+        self.network_checked = false;
+        self
+    }
+
+    /// Also refuses to submit the messages signed for the configured network to
+    /// servers which do not advertise any network (e.g., older ones)
+    pub fn require_advertised_network(mut self) -> Self {
+        self.require_advertised_network = true;
+        self
+    }
+
+    /// Replaces the clock used for reading the current time (the system clock by default)
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets whether to measure the server clock skew on claim submissions, and
+    /// what to do with claims expiring within it (ignored by default)
+    pub fn with_skew_policy(mut self, skew_policy: SkewPolicy) -> Self {
+        self.skew_policy = skew_policy;
+        self
+    }
+
     pub async fn from_private_key_str(
         private_key_str: &str,
         nonce: Option<u64>,
@@ -190,17 +311,111 @@ where
         &self.rpc_client
     }
 
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// The expiration time of a claim submitted now, to be dropped after `ttl`
+    pub fn expires_in(&self, ttl: Duration) -> Timestamp {
+        self.clock.in_(ttl)
+    }
+
+    /// The estimated skew of the server clock, if measured
+    pub fn clock_skew(&self) -> Option<ClockSkew> {
+        self.skew.estimate()
+    }
+
+    /// Records a server timestamp observed during a request, e.g., obtained from
+    /// a different source than claim submissions
+    pub fn record_skew_sample(&mut self, sample: SkewSample) {
+        self.skew.record(sample);
+    }
+
+    /// The warning raised by the last claim submission, if its expiration time
+    /// was within the measured server clock skew (with [SkewPolicy::Warn])
+    pub fn last_skew_warning(&self) -> Option<&SkewWarning> {
+        self.skew_warning.as_ref()
+    }
+
+    /// Checks `expires` against the measured server clock skew, according to the skew policy
+    fn check_clock_skew(&mut self, expires: &Timestamp) -> RpcWrapperResult<()> {
+        self.skew_warning = None;
+        let Some(skew) = self.clock_skew() else {
+            return Ok(());
+        };
+        if !skew.within_margin(expires, &self.clock.now()) {
+            return Ok(());
+        }
+        match self.skew_policy {
+            SkewPolicy::Ignore => Ok(()),
+            SkewPolicy::Warn => {
+                self.skew_warning = Some(SkewWarning {
+                    expires: *expires,
+                    skew,
+                });
+                Ok(())
+            }
+            SkewPolicy::Refuse => Err(RpcWrapperError::ExpiryWithinClockSkew {
+                expires: *expires,
+                skew,
+            }),
+        }
+    }
+
+    /// The network messages are signed for, if any
+    pub fn network(&self) -> Option<&str> {
+        self.network.as_deref()
+    }
+
+    /// Signs a message, for the configured network (if any)
     pub fn sign<Signable: IntoSigned>(
         &self,
         message: Signable,
     ) -> alloy::signers::Result<Signed<Signable>> {
-        message.into_signed(&self.key)
+        message.into_signed_for(&self.key, self.network())
+    }
+
+    /// Checks (once) that the server does not advertise a different network
+    /// than the configured one
+    async fn check_network(&mut self) -> RpcWrapperResult<()> {
+        let Some(expected) = &self.network else {
+            return Ok(());
+        };
+        if self.network_checked {
+            return Ok(());
+        }
+        match get_network(&self.rpc_client).await? {
+            Some(found) if found != *expected => {
+                return Err(RpcWrapperError::NetworkMismatch {
+                    expected: expected.clone(),
+                    found,
+                });
+            }
+            None if self.require_advertised_network => {
+                return Err(RpcWrapperError::NetworkNotAdvertised {
+                    expected: expected.clone(),
+                });
+            }
+            _ => {}
+        }
+        self.network_checked = true;
+        Ok(())
     }
 
     pub fn claim_id(&self, claim: &str) -> B256 {
         SubmittedClaim::claim_id_hash(&self.address.to_string(), &self.nonce.to_string(), claim)
     }
 
+    /// Submits a request-for-verification claim.
+    ///
+    /// - Returns: the claim ID of the submitted claim
+    ///
+    /// Will fail if:
+    ///
+    /// - `expires` has already passed
+    /// - `expires` falls within the measured server clock skew, with [SkewPolicy::Refuse]
+    /// - sender balance cannot cover validation + verification fees
+    #[allow(clippy::too_many_arguments)]
     pub async fn submit_claim(
         &mut self,
         // the claim to be verified
@@ -218,6 +433,11 @@ where
         // the total fee for verification and claim validation
         fee: Amount,
     ) -> RpcWrapperResult<B256> {
+        if self.clock.is_expired(&expires) {
+            return Err(RpcWrapperError::ExpiredClaim(expires));
+        }
+        self.check_clock_skew(&expires)?;
+        self.check_network().await?;
         let submitted_claim = SubmittedClaim {
             claim,
             claim_type,
@@ -230,12 +450,26 @@ where
             fee: fee.to_hex_str(),
         };
         let claim = self.sign(submitted_claim)?;
+        let local_sent = self.clock.now();
         let response: String = self
             .rpc_client
             .request("vsl_submitClaim", rpc_params![claim])
             .await?;
+        let local_received = self.clock.now();
         self.inc_nonce();
-        Ok(B256::from_str(&response)?)
+        let claim_id = B256::from_str(&response)?;
+        if self.skew_policy != SkewPolicy::Ignore {
+            // the server timestamped the claim while handling the submission;
+            // failing to retrieve it only means there is no sample this time
+            if let Ok(submitted) = self.get_submitted_claim_by_id(&claim_id).await {
+                self.skew.record(SkewSample {
+                    local_sent,
+                    server: submitted.timestamp,
+                    local_received,
+                });
+            }
+        }
+        Ok(claim_id)
     }
 
     pub async fn settle_claim(
@@ -243,6 +477,7 @@ where
         // The id of the claim for which claim settlement is requested
         claim_id: &B256,
     ) -> RpcWrapperResult<()> {
+        self.check_network().await?;
         let settle_claim_message = SettleClaimMessage {
             from: self.address.to_string(),
             nonce: self.nonce().to_string(),
@@ -273,6 +508,7 @@ where
     ///
     /// - sender balance cannot cover the specified `amount` and the validation fee
     pub async fn pay(&mut self, to: &Address, amount: &Amount) -> RpcWrapperResult<B256> {
+        self.check_network().await?;
         let pay_message = PayMessage {
             from: self.address().to_string(),
             nonce: self.nonce().to_string(),
@@ -306,6 +542,7 @@ where
         decimals: u8,
         total_supply: &Amount,
     ) -> RpcWrapperResult<(AssetId, B256)> {
+        self.check_network().await?;
         let create_asset_message =
             self.create_asset_message(ticker_symbol, decimals, total_supply)?;
         let signed_claim = self.sign(create_asset_message)?;
@@ -358,8 +595,8 @@ where
         to: &Address,
         amount: &Amount,
     ) -> RpcWrapperResult<B256> {
-        let transfer_asset_message =
-            self.transfer_asset_message(asset_id, to, amount)?;
+        self.check_network().await?;
+        let transfer_asset_message = self.transfer_asset_message(asset_id, to, amount)?;
         let signed_claim = self.sign(transfer_asset_message)?;
         let response: String = self
             .rpc_client
@@ -384,6 +621,23 @@ where
         })
     }
 
+    /// Transfers an amount of a specific asset from one account to another.
+    ///
+    /// Same as [RpcWrapper::transfer_asset], but taking the asset id from the [TokenAmount].
+    ///
+    /// Will fail if:
+    ///
+    /// - `amount` is an amount of VSL tokens (use [RpcWrapper::pay] instead)
+    /// - [RpcWrapper::transfer_asset] fails
+    pub async fn transfer_tokens(
+        &mut self,
+        to: &Address,
+        amount: &TokenAmount,
+    ) -> RpcWrapperResult<B256> {
+        let asset_id = *amount.asset_id()?;
+        self.transfer_asset(&asset_id, to, &amount.amount()).await
+    }
+
     /// Sets the account's current state.
     ///
     /// - Input:
@@ -394,6 +648,7 @@ where
     ///
     /// - sender balance cannot cover validation fee    
     pub async fn set_account_state(&mut self, state: &AccountStateHash) -> RpcWrapperResult<B256> {
+        self.check_network().await?;
         let set_state_message = SetStateMessage {
             from: self.address().to_string(),
             nonce: self.nonce().to_string(),
@@ -408,6 +663,15 @@ where
         Ok(B256::from_str(&response)?)
     }
 
+    /// Sets the account's current state to the root of a [StateTree].
+    ///
+    /// - Input:
+    ///   * tree - the records the state commits to
+    /// - Returns: a settled claim ID for the set state transaction.
+    pub async fn set_state_root(&mut self, tree: &StateTree) -> RpcWrapperResult<B256> {
+        self.set_account_state(&tree.root()).await
+    }
+
     /// Retrieves a settled claim by its unique claim ID.
     ///
     /// - Input: a claim ID, which is the Keccak256 hash of the claim creator, creation nonce, and claim string.
@@ -492,6 +756,28 @@ where
         get_asset_balances(self.rpc_client(), self.address()).await
     }
 
+    /// Retrieves the balance of a specific asset held by the wrapped account,
+    /// together with the decimals of the asset.
+    ///
+    /// - Input: the asset ID to query.
+    /// - Returns: the asset balance
+    pub async fn get_asset_token_balance(
+        &self,
+        asset_id: &AssetId,
+    ) -> RpcWrapperResult<TokenAmount> {
+        get_asset_token_balance(self.rpc_client(), self.address(), asset_id).await
+    }
+
+    /// Retrieves the balances of all assets held by the wrapped account,
+    /// together with the decimals of each asset.
+    ///
+    /// - Returns: a map of asset IDs to balances
+    pub async fn get_asset_token_balances(
+        &self,
+    ) -> RpcWrapperResult<HashMap<AssetId, TokenAmount>> {
+        get_asset_token_balances(self.rpc_client(), self.address()).await
+    }
+
     /// Retrieves creation metadata for a given asset by its ID.
     ///
     /// - Input: the asset ID to query.
@@ -626,7 +912,10 @@ pub async fn get_submitted_claim_by_id<T: ClientT>(
     claim_id: &B256,
 ) -> RpcWrapperResult<Timestamped<Signed<SubmittedClaim>>> {
     let response = rpc_client
-        .request("vsl_getSubmittedClaimById", rpc_params![claim_id.to_string()])
+        .request(
+            "vsl_getSubmittedClaimById",
+            rpc_params![claim_id.to_string()],
+        )
         .await?;
     Ok(response)
 }
@@ -650,6 +939,81 @@ pub async fn get_settled_claim_by_id<T: ClientT>(
     Ok(response)
 }
 
+/// Whether the error answered to a call is the rejection of the request by the
+/// node itself: any error but an internal error, or an error of a gateway (see
+/// [crate::gateway]), after which a write may or may not have been applied
+pub fn is_node_rejection(error: &ErrorObjectOwned) -> bool {
+    !matches!(
+        error.code(),
+        INTERNAL_ERROR_CODE | UPSTREAM_ERROR_CODE..=UNAUTHORIZED_CODE
+    )
+}
+
+/// How a node answers `vsl_getSubmittedClaimById` or `vsl_getSettledClaimById`
+/// when no claim has the requested ID (besides `null`). The node API only states
+/// that the call fails, so by default any rejection is taken as such.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClaimNotFound {
+    /// Any rejection by the node (see [is_node_rejection])
+    #[default]
+    AnyRejection,
+    /// An error with the given code only
+    Code(i32),
+}
+
+impl ClaimNotFound {
+    /// Whether the error answered to a claim lookup means that the claim is unknown
+    pub fn matches(&self, error: &RpcError) -> bool {
+        match self {
+            ClaimNotFound::AnyRejection => {
+                matches!(error, RpcError::Call(error) if is_node_rejection(error))
+            }
+            ClaimNotFound::Code(code) => {
+                matches!(error, RpcError::Call(error) if error.code() == *code)
+            }
+        }
+    }
+}
+
+/// The claims a claim ID is looked up among
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimStage {
+    Submitted,
+    Settled,
+}
+
+impl ClaimStage {
+    /// The method retrieving a claim of this stage by ID
+    pub fn method(&self) -> &'static str {
+        match self {
+            ClaimStage::Submitted => "vsl_getSubmittedClaimById",
+            ClaimStage::Settled => "vsl_getSettledClaimById",
+        }
+    }
+}
+
+/// Whether the node knows the claim with the given ID, among the submitted or
+/// the settled claims
+///
+/// Only the node answering that the claim is not found (`null`, or an error
+/// matching `not_found`) means that it is unknown. Any other error (e.g., a
+/// gateway rate limit) is returned, since the claim may well exist.
+pub async fn claim_exists<T: ClientT>(
+    rpc_client: &T,
+    stage: ClaimStage,
+    claim_id: &B256,
+    not_found: ClaimNotFound,
+) -> Result<bool, RpcError> {
+    let claim: Result<serde_json::Value, _> = rpc_client
+        .request(stage.method(), rpc_params![claim_id.to_string()])
+        .await;
+    match claim {
+        Ok(claim) => Ok(!claim.is_null()),
+        Err(error) if not_found.matches(&error) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
 /// Yields (recent) settled claims metadata
 ///
 /// - Input: a [Timestamp] (`since`)
@@ -807,6 +1171,55 @@ pub async fn get_asset_balances<T: ClientT>(
     Ok(result)
 }
 
+/// Retrieves the balance of a specific asset held by an account,
+/// together with the decimals of the asset.
+///
+/// - Input: the account address.
+/// - Input: the asset ID to query.
+/// - Returns: the asset balance
+///
+/// Will fail if:
+///
+/// - no asset with the given ID was created
+pub async fn get_asset_token_balance<T: ClientT>(
+    rpc_client: &T,
+    account_id: &Address,
+    asset_id: &AssetId,
+) -> RpcWrapperResult<TokenAmount> {
+    let Some(asset_data) = get_asset_by_id(rpc_client, asset_id).await? else {
+        return Err(RpcWrapperError::NonExistentAsset);
+    };
+    let amount = get_asset_balance(rpc_client, account_id, asset_id).await?;
+    Ok(TokenAmount::from_asset_data(*asset_id, &asset_data, amount))
+}
+
+/// Retrieves the balances of all assets held by an account,
+/// together with the decimals of each asset.
+///
+/// - Input: the account address to query.
+/// - Returns: a map of asset IDs to balances
+///
+/// Will fail if:
+///
+/// - the metadata of a held asset cannot be retrieved
+pub async fn get_asset_token_balances<T: ClientT>(
+    rpc_client: &T,
+    account_id: &Address,
+) -> RpcWrapperResult<HashMap<AssetId, TokenAmount>> {
+    let balances = get_asset_balances(rpc_client, account_id).await?;
+    let mut result = HashMap::with_capacity(balances.len());
+    for (asset_id, amount) in balances {
+        let Some(asset_data) = get_asset_by_id(rpc_client, &asset_id).await? else {
+            return Err(RpcWrapperError::NonExistentAsset);
+        };
+        result.insert(
+            asset_id,
+            TokenAmount::from_asset_data(asset_id, &asset_data, amount),
+        );
+    }
+    Ok(result)
+}
+
 /// Retrieves creation metadata for a given asset by its ID.
 ///
 /// - Input: the asset ID to query.
@@ -862,6 +1275,16 @@ pub async fn get_health<T: ClientT>(rpc_client: &T) -> RpcWrapperResult<()> {
     Ok(())
 }
 
+/// Returns the name of the network advertised by the server (see [crate::network]),
+/// or `None` if the server does not advertise one
+pub async fn get_network<T: ClientT>(rpc_client: &T) -> RpcWrapperResult<Option<String>> {
+    match rpc_client.request("vsl_getNetwork", rpc_params!()).await {
+        Ok(network) => Ok(network),
+        Err(RpcError::Call(error)) if error.code() == METHOD_NOT_FOUND_CODE => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
 /// [Subscribe](https://geth.ethereum.org/docs/rpc/pubsub) to the claim verification requests metadata
 ///
 /// - yields: a stream of timestamped [SubmittedClaimData]s
@@ -925,3 +1348,234 @@ pub async fn subscribe_to_settled_claims_for_receiver(
         )
         .await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::core::RpcResult;
+    use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+    use jsonrpsee::server::ServerHandle;
+
+    use super::*;
+    use crate::ManualClock;
+    use crate::gateway::RATE_LIMITED_CODE;
+    use crate::network::{DEVNET, LOCAL};
+    use crate::test_node::{self, error};
+
+    /// Starts a server advertising the given network (if any), and no other method
+    async fn serve_network(network: Option<&'static str>) -> (HttpClient, ServerHandle) {
+        let mut module = RpcModule::new(());
+        if let Some(network) = network {
+            module
+                .register_method("vsl_getNetwork", move |_, _, _| -> RpcResult<String> {
+                    Ok(network.to_string())
+                })
+                .unwrap();
+        }
+        test_node::serve(module).await
+    }
+
+    #[tokio::test]
+    async fn test_claim_exists() {
+        let known = B256::repeat_byte(0x01);
+        let mut module = RpcModule::new(());
+        module
+            .register_method(
+                "vsl_getSettledClaimById",
+                move |params, _, _| -> RpcResult<String> {
+                    let claim_id: B256 = params.one()?;
+                    match claim_id == known {
+                        true => Ok(claim_id.to_string()),
+                        false => Err(error(-32001, "claim not found")),
+                    }
+                },
+            )
+            .unwrap();
+        module
+            .register_method(
+                "vsl_getSubmittedClaimById",
+                |_, _, _| -> RpcResult<String> { Err(error(RATE_LIMITED_CODE, "rate limited")) },
+            )
+            .unwrap();
+        let (http_client, _handle) = test_node::serve(module).await;
+
+        let unknown = B256::repeat_byte(0x02);
+        let exists = |stage, claim_id, not_found| {
+            let http_client = http_client.clone();
+            async move { claim_exists(&http_client, stage, &claim_id, not_found).await }
+        };
+        let settled = ClaimStage::Settled;
+        assert!(
+            exists(settled, known, ClaimNotFound::default())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !exists(settled, unknown, ClaimNotFound::default())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !exists(settled, unknown, ClaimNotFound::Code(-32001))
+                .await
+                .unwrap()
+        );
+        // an error with another code than the configured one leaves the claim unknown
+        assert!(
+            exists(settled, unknown, ClaimNotFound::Code(-32000))
+                .await
+                .is_err()
+        );
+        // errors of a gateway never mean that the claim is unknown
+        let submitted = ClaimStage::Submitted;
+        assert!(
+            exists(submitted, unknown, ClaimNotFound::default())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_network() {
+        let (http_client, _handle) = serve_network(Some(DEVNET.name)).await;
+        let network = get_network(&http_client).await.unwrap();
+        assert_eq!(network.as_deref(), Some(DEVNET.name));
+        let (http_client, _handle) = serve_network(None).await;
+        assert_eq!(get_network(&http_client).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_refuse_network_mismatch() {
+        let (http_client, _handle) = serve_network(Some(LOCAL.name)).await;
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_network(DEVNET.name);
+        let result = account.pay(&Address::ZERO, &Amount::ZERO).await;
+        assert!(matches!(
+            result,
+            Err(RpcWrapperError::NetworkMismatch { expected, found })
+                if expected == DEVNET.name && found == LOCAL.name
+        ));
+
+        // the matching network lets the payment through (to a server unable to handle it)
+        let (http_client, _handle) = serve_network(Some(DEVNET.name)).await;
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_network(DEVNET.name);
+        let result = account.pay(&Address::ZERO, &Amount::ZERO).await;
+        assert!(matches!(result, Err(RpcWrapperError::RpcError(_))));
+
+        // a server advertising no network cannot be checked, and is only refused on request
+        let (http_client, _handle) = serve_network(None).await;
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_network(DEVNET.name);
+        let result = account.pay(&Address::ZERO, &Amount::ZERO).await;
+        assert!(matches!(result, Err(RpcWrapperError::RpcError(_))));
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_network(DEVNET.name)
+                .require_advertised_network();
+        let result = account.pay(&Address::ZERO, &Amount::ZERO).await;
+        assert!(matches!(
+            result,
+            Err(RpcWrapperError::NetworkNotAdvertised { expected }) if expected == DEVNET.name
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_submit_expired_claim() {
+        // the request is rejected before reaching the (unreachable) server
+        let http_client = HttpClientBuilder::new()
+            .build("http://127.0.0.1:1")
+            .unwrap();
+        let clock = ManualClock::new(Timestamp::from_seconds(1_000));
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_clock(clock.clone());
+        let expires = account.expires_in(Duration::from_secs(60));
+        assert_eq!(expires, Timestamp::from_seconds(1_060));
+        clock.advance(Duration::from_secs(61));
+        let result = account
+            .submit_claim(
+                "claim".to_string(),
+                "type".to_string(),
+                "proof".to_string(),
+                vec![],
+                0,
+                expires,
+                Amount::ZERO,
+            )
+            .await;
+        assert!(matches!(result, Err(RpcWrapperError::ExpiredClaim(ts)) if ts == expires));
+        assert_eq!(account.nonce(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_submit_claim_within_clock_skew() {
+        let http_client = HttpClientBuilder::new()
+            .build("http://127.0.0.1:1")
+            .unwrap();
+        let clock = ManualClock::new(Timestamp::from_seconds(1_000));
+        let mut account =
+            RpcWrapper::from_signer(PrivateKeySigner::random(), Some(0), &http_client)
+                .await
+                .unwrap()
+                .with_clock(clock)
+                .with_skew_policy(SkewPolicy::Refuse);
+        // the server clock is 30 seconds ahead
+        account.record_skew_sample(SkewSample {
+            local_sent: Timestamp::from_seconds(990),
+            server: Timestamp::from_seconds(1_021),
+            local_received: Timestamp::from_seconds(992),
+        });
+        let skew = account.clock_skew().unwrap();
+        assert_eq!(skew.margin(), Duration::from_secs(31));
+        let expires = account.expires_in(Duration::from_secs(20));
+        let result = account
+            .submit_claim(
+                "claim".to_string(),
+                "type".to_string(),
+                "proof".to_string(),
+                vec![],
+                0,
+                expires,
+                Amount::ZERO,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(RpcWrapperError::ExpiryWithinClockSkew { expires: ts, .. }) if ts == expires
+        ));
+        assert_eq!(account.nonce(), 0);
+        assert!(account.last_skew_warning().is_none());
+
+        // with a warning, the claim is submitted (and fails to reach the server)
+        let mut account = account.with_skew_policy(SkewPolicy::Warn);
+        let result = account
+            .submit_claim(
+                "claim".to_string(),
+                "type".to_string(),
+                "proof".to_string(),
+                vec![],
+                0,
+                expires,
+                Amount::ZERO,
+            )
+            .await;
+        assert!(matches!(result, Err(RpcWrapperError::RpcError(_))));
+        let warning = account.last_skew_warning().unwrap();
+        assert_eq!(warning.expires, expires);
+        assert_eq!(warning.skew, skew);
+    }
+}
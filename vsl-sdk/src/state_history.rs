@@ -0,0 +1,330 @@
+//! # State History Module
+//!
+//! `vsl_getAccountState` only returns the current state of an account. A
+//! [StateHistory] reconstructs the earlier states from the settled claims of the
+//! account: each settled `SetState` claim is a [StateTransition], together with
+//! the settled claim as evidence.
+//!
+//! The node lists (at most [LIST_LIMIT] of) the most recent settled claims since a
+//! timestamp, so older claims may be out of reach: the history records from when
+//! it is known to be complete, and [StateHistory::state_at] refuses to answer for
+//! earlier times. Syncing regularly (more often than [LIST_LIMIT] claims are
+//! settled for the account) keeps the history complete.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use jsonrpsee::http_client::HttpClientBuilder;
+//! use vsl_sdk::state_history::StateHistory;
+//! use vsl_sdk::{Address, Timestamp};
+//!
+//! let client = HttpClientBuilder::new().build("http://127.0.0.1:44444")?;
+//! let mut history = StateHistory::new(Address::repeat_byte(0x01));
+//! history.sync(&client).await.expect("cannot list settled claims");
+//! match history.state_at(&Timestamp::from_seconds(1_750_000_000))? {
+//!     Some(transition) => println!("{} (set by {})", transition.state, transition.claim_id),
+//!     None => println!("no state"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::consensus::Signed;
+use jsonrpsee::core::client::ClientT;
+
+use crate::rpc_messages::{
+    AccountStateHash, SetStateMessage, SettledVerifiedClaim, Timestamped, ValidatorVerifiedClaim,
+};
+use crate::rpc_wrapper::{self, RpcWrapperResult};
+use crate::{Address, B256, ClaimCursor, Timestamp};
+
+/// The maximal number of claims listed by the node at once
+pub const LIST_LIMIT: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    /// The history is only known to be complete since the given time
+    Incomplete { complete_since: Option<Timestamp> },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::Incomplete {
+                complete_since: Some(since),
+            } => write!(f, "the state history is only known since {since}"),
+            HistoryError::Incomplete {
+                complete_since: None,
+            } => write!(f, "the state history was not synced"),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+/// A change of the state of an account
+#[derive(Debug, Clone)]
+pub struct StateTransition {
+    pub state: AccountStateHash,
+    /// The nonce of the account used by the change
+    pub nonce: u64,
+    pub timestamp: Timestamp,
+    pub claim_id: B256,
+    /// The settled claim recording the change, as listed by the node
+    pub evidence: Timestamped<Signed<SettledVerifiedClaim>>,
+}
+
+impl StateTransition {
+    /// The transition recorded by a settled claim of the account, if it is a
+    /// `SetState` claim
+    pub fn from_claim(
+        account_id: &Address,
+        claim: &Timestamped<Signed<SettledVerifiedClaim>>,
+    ) -> Option<Self> {
+        let verified = &claim.data.tx().verified_claim;
+        let Ok(ValidatorVerifiedClaim::SetState(message)) = serde_json::from_str(&verified.claim)
+        else {
+            return None;
+        };
+        let SetStateMessage { from, nonce, state } = message;
+        if Address::from_str(&from).ok()? != *account_id {
+            return None;
+        }
+        Some(StateTransition {
+            state: AccountStateHash::from_str(&state).ok()?,
+            nonce: nonce.parse().ok()?,
+            timestamp: claim.timestamp,
+            claim_id: B256::from_str(&claim.id).ok()?,
+            evidence: claim.clone(),
+        })
+    }
+
+    fn cursor(&self) -> ClaimCursor {
+        ClaimCursor::new(self.timestamp, self.claim_id)
+    }
+}
+
+/// The known state transitions of an account
+#[derive(Debug, Clone)]
+pub struct StateHistory {
+    account_id: Address,
+    /// The transitions, by timestamp (then claim ID)
+    transitions: Vec<StateTransition>,
+    /// The last settled claim seen (of any kind)
+    last_seen: Option<ClaimCursor>,
+    complete_since: Option<Timestamp>,
+}
+
+impl StateHistory {
+    pub fn new(account_id: Address) -> Self {
+        StateHistory {
+            account_id,
+            transitions: Vec::new(),
+            last_seen: None,
+            complete_since: None,
+        }
+    }
+
+    pub fn account_id(&self) -> &Address {
+        &self.account_id
+    }
+
+    /// The known transitions, by timestamp
+    pub fn transitions(&self) -> &[StateTransition] {
+        &self.transitions
+    }
+
+    /// The time since which all transitions are known (`None` before syncing)
+    pub fn complete_since(&self) -> Option<Timestamp> {
+        self.complete_since
+    }
+
+    /// Adds the settled claims of the account listed by the node, and returns the
+    /// number of new transitions
+    pub fn extend(&mut self, claims: &[Timestamped<Signed<SettledVerifiedClaim>>]) -> usize {
+        let new_claims: Vec<_> = claims
+            .iter()
+            .filter_map(|claim| Some((ClaimCursor::of(claim)?, claim)))
+            .filter(|(cursor, _)| self.last_seen.is_none_or(|last| *cursor > last))
+            .collect();
+        // a full list not reaching back to the last claim seen may have missed some
+        let overlaps = claims.len() > new_claims.len();
+        if claims.len() >= LIST_LIMIT && !overlaps {
+            self.complete_since = new_claims.iter().map(|(cursor, _)| cursor.timestamp).min();
+        } else if self.complete_since.is_none() {
+            self.complete_since = Some(Timestamp::default());
+        }
+        let mut added = 0;
+        for (cursor, claim) in new_claims {
+            self.last_seen = self.last_seen.max(Some(cursor));
+            if let Some(transition) = StateTransition::from_claim(&self.account_id, claim) {
+                self.transitions.push(transition);
+                added += 1;
+            }
+        }
+        self.transitions.sort_by_key(StateTransition::cursor);
+        added
+    }
+
+    /// Fetches the settled claims of the account since the last one seen, and
+    /// returns the number of new transitions
+    pub async fn sync<T: ClientT>(&mut self, rpc_client: &T) -> RpcWrapperResult<usize> {
+        let since = self
+            .last_seen
+            .map(|last| last.timestamp)
+            .unwrap_or_default();
+        let claims =
+            rpc_wrapper::list_settled_claims_for_sender(rpc_client, &self.account_id, &since)
+                .await?;
+        Ok(self.extend(&claims))
+    }
+
+    /// The transition which set the state of the account at the given time, or
+    /// `None` if the account had no state
+    pub fn state_at(
+        &self,
+        timestamp: &Timestamp,
+    ) -> Result<Option<&StateTransition>, HistoryError> {
+        let incomplete = HistoryError::Incomplete {
+            complete_since: self.complete_since,
+        };
+        let Some(complete_since) = self.complete_since else {
+            return Err(incomplete);
+        };
+        if *timestamp < complete_since {
+            return Err(incomplete);
+        }
+        let before = self
+            .transitions
+            .partition_point(|transition| transition.timestamp <= *timestamp);
+        let Some(transition) = before.checked_sub(1).map(|index| &self.transitions[index]) else {
+            // no transition at all, unless some were missed
+            return match complete_since == Timestamp::default() {
+                true => Ok(None),
+                false => Err(incomplete),
+            };
+        };
+        // a transition before a gap may have been followed by missed ones
+        match transition.timestamp >= complete_since || complete_since == Timestamp::default() {
+            true => Ok(Some(transition)),
+            false => Err(incomplete),
+        }
+    }
+
+    /// The latest known transition
+    pub fn current(&self) -> Option<&StateTransition> {
+        self.transitions.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::IntoSigned;
+    use crate::rpc_messages::{PayMessage, VerifiedClaim};
+
+    fn settled(
+        signer: &PrivateKeySigner,
+        seconds: u64,
+        claim: ValidatorVerifiedClaim,
+    ) -> Timestamped<Signed<SettledVerifiedClaim>> {
+        let claim_id = B256::from(alloy::primitives::U256::from(seconds));
+        let settled = SettledVerifiedClaim {
+            verified_claim: VerifiedClaim {
+                claim: serde_json::to_string(&claim).unwrap(),
+                claim_id: claim_id.to_string(),
+                claim_type: claim.kind().to_string(),
+                claim_owner: signer.address().to_string(),
+            },
+            verifiers: vec![],
+        };
+        Timestamped::new(
+            claim_id.to_string(),
+            Timestamp::from_seconds(seconds),
+            settled.into_signed(signer).unwrap(),
+        )
+    }
+
+    fn set_state(
+        signer: &PrivateKeySigner,
+        seconds: u64,
+        nonce: u64,
+    ) -> Timestamped<Signed<SettledVerifiedClaim>> {
+        let message = SetStateMessage {
+            from: signer.address().to_string(),
+            nonce: nonce.to_string(),
+            state: AccountStateHash::hash(&nonce.to_be_bytes()).to_string(),
+        };
+        settled(signer, seconds, ValidatorVerifiedClaim::SetState(message))
+    }
+
+    fn pay(
+        signer: &PrivateKeySigner,
+        seconds: u64,
+        nonce: u64,
+    ) -> Timestamped<Signed<SettledVerifiedClaim>> {
+        let message = PayMessage {
+            from: signer.address().to_string(),
+            to: Address::ZERO.to_string(),
+            amount: "0x1".to_string(),
+            nonce: nonce.to_string(),
+        };
+        settled(signer, seconds, ValidatorVerifiedClaim::Payment(message))
+    }
+
+    fn state_nonce(history: &StateHistory, seconds: u64) -> Result<Option<u64>, HistoryError> {
+        let transition = history.state_at(&Timestamp::from_seconds(seconds))?;
+        Ok(transition.map(|transition| transition.nonce))
+    }
+
+    #[test]
+    fn test_state_at() {
+        let signer = PrivateKeySigner::random();
+        let mut history = StateHistory::new(signer.address());
+        assert!(state_nonce(&history, 10).is_err());
+
+        let claims = [
+            set_state(&signer, 10, 0),
+            pay(&signer, 15, 1),
+            set_state(&signer, 20, 2),
+        ];
+        assert_eq!(history.extend(&claims), 2);
+        assert_eq!(state_nonce(&history, 5), Ok(None));
+        assert_eq!(state_nonce(&history, 10), Ok(Some(0)));
+        assert_eq!(state_nonce(&history, 19), Ok(Some(0)));
+        assert_eq!(state_nonce(&history, 25), Ok(Some(2)));
+        let current = history.current().unwrap();
+        assert_eq!(current.state, AccountStateHash::hash(&2u64.to_be_bytes()));
+        assert_eq!(current.evidence.id, claims[2].id);
+
+        // listing again from the last claim seen adds nothing
+        assert_eq!(history.extend(&claims[2..]), 0);
+        assert_eq!(history.transitions().len(), 2);
+        assert_eq!(history.complete_since(), Some(Timestamp::default()));
+    }
+
+    #[test]
+    fn test_gap() {
+        let signer = PrivateKeySigner::random();
+        let mut history = StateHistory::new(signer.address());
+        history.extend(&[set_state(&signer, 10, 0)]);
+        // a full list of claims not overlapping the ones seen
+        let mut claims: Vec<_> = (0..LIST_LIMIT as u64 - 1)
+            .map(|i| pay(&signer, 100 + i, 1 + i))
+            .collect();
+        claims.push(set_state(&signer, 200, 100));
+        history.extend(&claims);
+        assert_eq!(history.complete_since(), Some(Timestamp::from_seconds(100)));
+        assert!(matches!(
+            state_nonce(&history, 50),
+            Err(HistoryError::Incomplete { .. })
+        ));
+        // the state set at 10 may have been changed by a missed claim
+        assert!(state_nonce(&history, 150).is_err());
+        assert_eq!(state_nonce(&history, 200), Ok(Some(100)));
+    }
+}
@@ -0,0 +1,499 @@
+//! # State Tree Module
+//!
+//! An [AccountStateHash] can commit to structured state, as the root of a Merkle
+//! tree over key/value records. The leaves of a [StateTree] are sorted by key, so
+//! that the absence of a key is proven by the two adjacent leaves surrounding it.
+//!
+//! Hashes are keccak-256, domain-separated to tell leaves from inner nodes:
+//!
+//! - a leaf hashes `0x00 || len(key) (u32, big-endian) || key || value`;
+//! - an inner node hashes `0x01 || left || right`; the last node of a level with
+//!   an odd number of nodes is carried up unchanged;
+//! - the root hashes `0x02 || number of leaves (u64, big-endian) || top node`
+//!   (the top node of the empty tree is zero).
+//!
+//! The root is set with [RpcWrapper::set_state_root], and a [StateProof] is
+//! checked against the state of an account with [verify_account_proof].
+//!
+//! [RpcWrapper::set_state_root]: crate::rpc_wrapper::RpcWrapper::set_state_root
+//!
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use alloy::primitives::{Bytes, Keccak256};
+use jsonrpsee::core::client::ClientT;
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_messages::AccountStateHash;
+use crate::rpc_wrapper::{self, RpcWrapperError};
+use crate::{Address, B256};
+
+#[derive(Debug)]
+pub enum StateProofError {
+    /// The proof does not lead to the expected root
+    RootMismatch {
+        expected: AccountStateHash,
+        found: AccountStateHash,
+    },
+    /// The proof is about another key
+    KeyMismatch,
+    /// The leaves of a non-inclusion proof do not surround the key
+    NotAdjacent,
+    /// A leaf index is out of range, or the number of siblings is wrong
+    InvalidPath,
+    /// The account has no state
+    NoState,
+    /// The state of the account could not be retrieved
+    Rpc(RpcWrapperError),
+}
+
+impl fmt::Display for StateProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateProofError::RootMismatch { expected, found } => {
+                write!(f, "the proof leads to root {found}, not {expected}")
+            }
+            StateProofError::KeyMismatch => write!(f, "the proof is about another key"),
+            StateProofError::NotAdjacent => {
+                write!(f, "the leaves of the proof do not surround the key")
+            }
+            StateProofError::InvalidPath => write!(f, "invalid Merkle path"),
+            StateProofError::NoState => write!(f, "the account has no state"),
+            StateProofError::Rpc(error) => write!(f, "RPC error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StateProofError {}
+
+impl From<RpcWrapperError> for StateProofError {
+    fn from(error: RpcWrapperError) -> Self {
+        StateProofError::Rpc(error)
+    }
+}
+
+fn leaf_hash(key: &[u8], value: &[u8]) -> B256 {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x00]);
+    hasher.update((key.len() as u32).to_be_bytes());
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize()
+}
+
+fn node_hash(left: &B256, right: &B256) -> B256 {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+fn root_hash(leaf_count: u64, top: &B256) -> AccountStateHash {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x02]);
+    hasher.update(leaf_count.to_be_bytes());
+    hasher.update(top);
+    hasher.finalize().into()
+}
+
+/// A Merkle tree over key/value records, sorted by key
+#[derive(Debug, Clone, Default)]
+pub struct StateTree {
+    records: Vec<(Bytes, Bytes)>,
+    /// The hashes of each level, from the leaves up to the top node
+    levels: Vec<Vec<B256>>,
+}
+
+impl StateTree {
+    /// The tree of the given records (for a key given several times, the last
+    /// value is kept)
+    pub fn new<K, V>(records: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        let records: BTreeMap<Bytes, Bytes> = records
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        let records: Vec<(Bytes, Bytes)> = records.into_iter().collect();
+        let mut levels = vec![
+            records
+                .iter()
+                .map(|(key, value)| leaf_hash(key, value))
+                .collect::<Vec<_>>(),
+        ];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let level = levels.last().unwrap();
+            let parents = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+            levels.push(parents);
+        }
+        StateTree { records, levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The records, by key
+    pub fn records(&self) -> &[(Bytes, Bytes)] {
+        &self.records
+    }
+
+    /// The value of the key, if any
+    pub fn get(&self, key: &[u8]) -> Option<&Bytes> {
+        let index = self.search(key).ok()?;
+        Some(&self.records[index].1)
+    }
+
+    fn search(&self, key: &[u8]) -> Result<usize, usize> {
+        self.records
+            .binary_search_by(|(record, _)| record.as_ref().cmp(key))
+    }
+
+    /// The commitment to the records, to be set as the account state
+    pub fn root(&self) -> AccountStateHash {
+        let top = self
+            .levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default();
+        root_hash(self.len() as u64, &top)
+    }
+
+    fn leaf_proof(&self, index: usize) -> LeafProof {
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(*sibling);
+            }
+            position /= 2;
+        }
+        let (key, value) = self.records[index].clone();
+        LeafProof {
+            index: index as u64,
+            key,
+            value,
+            siblings,
+        }
+    }
+
+    /// Proves the value of the key, or its absence
+    pub fn prove(&self, key: &[u8]) -> StateProof {
+        let leaf_count = self.len() as u64;
+        match self.search(key) {
+            Ok(index) => StateProof::Inclusion {
+                leaf_count,
+                leaf: self.leaf_proof(index),
+            },
+            Err(index) => StateProof::NonInclusion {
+                leaf_count,
+                key: Bytes::copy_from_slice(key),
+                left: index.checked_sub(1).map(|left| self.leaf_proof(left)),
+                right: (index < self.len()).then(|| self.leaf_proof(index)),
+            },
+        }
+    }
+}
+
+/// A leaf of the tree, with the hashes of its siblings from the bottom up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafProof {
+    pub index: u64,
+    pub key: Bytes,
+    pub value: Bytes,
+    pub siblings: Vec<B256>,
+}
+
+impl LeafProof {
+    /// The root of a tree of `leaf_count` leaves holding this leaf
+    fn root(&self, leaf_count: u64) -> Result<AccountStateHash, StateProofError> {
+        if self.index >= leaf_count {
+            return Err(StateProofError::InvalidPath);
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = leaf_hash(&self.key, &self.value);
+        let (mut position, mut size) = (self.index, leaf_count);
+        while size > 1 {
+            // the last node of an odd level has no sibling
+            if position ^ 1 < size {
+                let sibling = siblings.next().ok_or(StateProofError::InvalidPath)?;
+                hash = match position % 2 {
+                    0 => node_hash(&hash, sibling),
+                    _ => node_hash(sibling, &hash),
+                };
+            }
+            position /= 2;
+            size = size.div_ceil(2);
+        }
+        if siblings.next().is_some() {
+            return Err(StateProofError::InvalidPath);
+        }
+        Ok(root_hash(leaf_count, &hash))
+    }
+}
+
+/// A proof that a key has a given value in a [StateTree], or no value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StateProof {
+    Inclusion {
+        leaf_count: u64,
+        leaf: LeafProof,
+    },
+    /// The leaves surrounding the key (missing at either end of the tree)
+    NonInclusion {
+        leaf_count: u64,
+        key: Bytes,
+        left: Option<LeafProof>,
+        right: Option<LeafProof>,
+    },
+}
+
+impl StateProof {
+    /// The key the proof is about
+    pub fn key(&self) -> &Bytes {
+        match self {
+            StateProof::Inclusion { leaf, .. } => &leaf.key,
+            StateProof::NonInclusion { key, .. } => key,
+        }
+    }
+
+    /// Checks the proof for the key against the root, and returns the value of the
+    /// key (`None` if proven absent)
+    pub fn verify(
+        &self,
+        root: &AccountStateHash,
+        key: &[u8],
+    ) -> Result<Option<&Bytes>, StateProofError> {
+        if self.key().as_ref() != key {
+            return Err(StateProofError::KeyMismatch);
+        }
+        let check_root = |found: AccountStateHash| match found == *root {
+            true => Ok(()),
+            false => Err(StateProofError::RootMismatch {
+                expected: *root,
+                found,
+            }),
+        };
+        match self {
+            StateProof::Inclusion { leaf_count, leaf } => {
+                check_root(leaf.root(*leaf_count)?)?;
+                Ok(Some(&leaf.value))
+            }
+            StateProof::NonInclusion {
+                leaf_count,
+                left,
+                right,
+                ..
+            } => {
+                let below = |leaf: &LeafProof| leaf.key.as_ref().cmp(key) == Ordering::Less;
+                let above = |leaf: &LeafProof| leaf.key.as_ref().cmp(key) == Ordering::Greater;
+                let adjacent = match (left, right) {
+                    (None, None) => *leaf_count == 0,
+                    (Some(left), None) => {
+                        below(left) && left.index.checked_add(1) == Some(*leaf_count)
+                    }
+                    (None, Some(right)) => above(right) && right.index == 0,
+                    (Some(left), Some(right)) => {
+                        below(left)
+                            && above(right)
+                            && left.index.checked_add(1) == Some(right.index)
+                    }
+                };
+                if !adjacent {
+                    return Err(StateProofError::NotAdjacent);
+                }
+                if *leaf_count == 0 {
+                    check_root(root_hash(0, &B256::ZERO))?;
+                }
+                for leaf in left.iter().chain(right) {
+                    check_root(leaf.root(*leaf_count)?)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Checks the proof for the key against the current state of the account, and
+/// returns the value of the key (`None` if proven absent)
+pub async fn verify_account_proof<T: ClientT>(
+    rpc_client: &T,
+    account_id: &Address,
+    key: &[u8],
+    proof: &StateProof,
+) -> Result<Option<Bytes>, StateProofError> {
+    let Some(root) = rpc_wrapper::get_account_state(rpc_client, account_id).await? else {
+        return Err(StateProofError::NoState);
+    };
+    Ok(proof.verify(&root, key)?.cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::core::RpcResult;
+
+    use super::*;
+    use crate::test_node;
+
+    fn tree(size: usize) -> StateTree {
+        StateTree::new((0..size).map(|i| {
+            let key = format!("key-{:03}", i * 2);
+            (key.into_bytes(), format!("value-{i}").into_bytes())
+        }))
+    }
+
+    #[test]
+    fn test_inclusion() {
+        for size in 1..=9 {
+            let tree = tree(size);
+            let root = tree.root();
+            for (key, value) in tree.records() {
+                let proof = tree.prove(key);
+                assert_eq!(proof.verify(&root, key).unwrap(), Some(value));
+                // the proof does not hold for another value
+                let StateProof::Inclusion {
+                    leaf_count,
+                    mut leaf,
+                } = proof.clone()
+                else {
+                    panic!("expected an inclusion proof");
+                };
+                leaf.value = Bytes::from_static(b"forged");
+                let forged = StateProof::Inclusion { leaf_count, leaf };
+                assert!(matches!(
+                    forged.verify(&root, key),
+                    Err(StateProofError::RootMismatch { .. })
+                ));
+                assert!(matches!(
+                    proof.verify(&root, b"key-001"),
+                    Err(StateProofError::KeyMismatch)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_inclusion() {
+        for size in 0..=5 {
+            let tree = tree(size);
+            let root = tree.root();
+            for i in 0..=size {
+                let key = format!("key-{:03}", i * 2 + 1);
+                let proof = tree.prove(key.as_bytes());
+                assert_eq!(proof.verify(&root, key.as_bytes()).unwrap(), None);
+            }
+            // a leaf cannot be hidden by skipping it
+            if size >= 3 {
+                let StateProof::NonInclusion { left, .. } = tree.prove(b"key-001") else {
+                    panic!("expected a non-inclusion proof");
+                };
+                let StateProof::NonInclusion { right, .. } = tree.prove(b"key-003") else {
+                    panic!("expected a non-inclusion proof");
+                };
+                let skipping = StateProof::NonInclusion {
+                    leaf_count: size as u64,
+                    key: Bytes::from_static(b"key-002"),
+                    left,
+                    right,
+                };
+                assert!(matches!(
+                    skipping.verify(&root, b"key-002"),
+                    Err(StateProofError::NotAdjacent)
+                ));
+            }
+            // an untrusted index cannot overflow the adjacency check
+            if size >= 1 {
+                let StateProof::NonInclusion { left, .. } = tree.prove(b"key-999") else {
+                    panic!("expected a non-inclusion proof");
+                };
+                let mut left = left.expect("a left neighbour");
+                left.index = u64::MAX;
+                let overflowing = StateProof::NonInclusion {
+                    leaf_count: 0,
+                    key: Bytes::from_static(b"key-999"),
+                    left: Some(left),
+                    right: None,
+                };
+                assert!(matches!(
+                    overflowing.verify(&root, b"key-999"),
+                    Err(StateProofError::NotAdjacent)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_root() {
+        assert_eq!(
+            StateTree::default().root(),
+            StateTree::new(tree(0).records().to_vec()).root()
+        );
+        // the root depends on all records, but not on their order
+        let records = tree(4).records().to_vec();
+        let mut reversed = records.clone();
+        reversed.reverse();
+        assert_eq!(StateTree::new(reversed).root(), tree(4).root());
+        assert_ne!(tree(3).root(), tree(4).root());
+        let mut changed = records;
+        changed[3].1 = Bytes::from_static(b"changed");
+        assert_ne!(StateTree::new(changed).root(), tree(4).root());
+        assert_eq!(tree(4).get(b"key-004").unwrap().as_ref(), b"value-2");
+        assert!(tree(4).get(b"key-005").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_account_proof() {
+        let state = tree(5);
+        let mut module = RpcModule::new(state.root());
+        module
+            .register_method(
+                "vsl_getAccountState",
+                |params, root, _| -> RpcResult<Option<String>> {
+                    let account_id: String = params.one()?;
+                    let account_id: Address = account_id.parse().unwrap();
+                    Ok((account_id == Address::ZERO).then(|| root.to_string()))
+                },
+            )
+            .unwrap();
+        let (client, _handle) = test_node::serve(module).await;
+
+        let proof = state.prove(b"key-004");
+        let value = verify_account_proof(&client, &Address::ZERO, b"key-004", &proof)
+            .await
+            .unwrap();
+        assert_eq!(value.unwrap().as_ref(), b"value-2");
+        let proof = state.prove(b"key-005");
+        let value = verify_account_proof(&client, &Address::ZERO, b"key-005", &proof)
+            .await
+            .unwrap();
+        assert!(value.is_none());
+        let stale = tree(4).prove(b"key-004");
+        assert!(matches!(
+            verify_account_proof(&client, &Address::ZERO, b"key-004", &stale).await,
+            Err(StateProofError::RootMismatch { .. })
+        ));
+        let other = Address::repeat_byte(0x01);
+        assert!(matches!(
+            verify_account_proof(&client, &other, b"key-004", &proof).await,
+            Err(StateProofError::NoState)
+        ));
+    }
+}
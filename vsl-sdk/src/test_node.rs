@@ -0,0 +1,178 @@
+//! Stand-in nodes for the tests: JSON-RPC servers answering the given methods,
+//! and a single-account ledger answering the account and payment methods.
+//!
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use alloy::consensus::Signed;
+use jsonrpsee::RpcModule;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::types::{ErrorObjectOwned, Params};
+
+use crate::gateway::{RATE_LIMITED_CODE, UPSTREAM_ERROR_CODE};
+use crate::rpc_messages::{IdentifiableClaim, PayMessage, SetStateMessage, TransferAssetMessage};
+use crate::{Amount, B256};
+
+/// Starts a server answering the methods of the module, on a free local port
+pub(crate) async fn start<Context: Send + Sync + 'static>(
+    module: RpcModule<Context>,
+) -> (SocketAddr, ServerHandle) {
+    let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+    let address = server.local_addr().unwrap();
+    (address, server.start(module))
+}
+
+/// Starts a server answering the methods of the module, and connects to it
+pub(crate) async fn serve<Context: Send + Sync + 'static>(
+    module: RpcModule<Context>,
+) -> (HttpClient, ServerHandle) {
+    let (address, handle) = start(module).await;
+    let client = HttpClientBuilder::new()
+        .build(format!("http://{address}"))
+        .unwrap();
+    (client, handle)
+}
+
+pub(crate) fn error(code: i32, message: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(code, message, None::<()>)
+}
+
+/// The rejection of a request by the node
+pub(crate) fn rejected(message: &str) -> ErrorObjectOwned {
+    error(-32000, message)
+}
+
+/// The state of a stand-in node, paying from a single account
+pub(crate) struct Ledger {
+    pub nonce: u64,
+    pub balance: Amount,
+    pub asset_balance: Amount,
+    pub settled: HashSet<String>,
+    pub payments: usize,
+    /// The number of payments after which the next one is rejected
+    pub reject_after: Option<usize>,
+    /// Whether the claim lookups are rate limited (by a gateway)
+    pub rate_limited: bool,
+    /// The number of payments after which the next one goes through, but a
+    /// gateway answers with an error
+    pub upstream_error_after: Option<usize>,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Ledger {
+            nonce: 0,
+            balance: Amount::from_vsl_tokens(100),
+            asset_balance: Amount::from_tokens(100, 2),
+            settled: HashSet::new(),
+            payments: 0,
+            reject_after: None,
+            rate_limited: false,
+            upstream_error_after: None,
+        }
+    }
+}
+
+impl Ledger {
+    fn settle(&mut self, nonce: &str, amount: &str, claim_id: B256) -> RpcResult<String> {
+        if nonce != self.nonce.to_string() {
+            return Err(rejected("invalid nonce"));
+        }
+        if amount == "0x0" {
+            return Err(rejected("invalid amount"));
+        }
+        if self.reject_after == Some(self.payments) {
+            self.reject_after = None;
+            return Err(rejected("rejected"));
+        }
+        let upstream_error = self.upstream_error_after == Some(self.payments);
+        self.nonce += 1;
+        self.payments += 1;
+        self.settled.insert(claim_id.to_string());
+        if upstream_error {
+            self.upstream_error_after = None;
+            return Err(error(UPSTREAM_ERROR_CODE, "upstream error"));
+        }
+        Ok(claim_id.to_string())
+    }
+
+    /// A module answering `vsl_getAccountNonce`, `vsl_getBalance`,
+    /// `vsl_getAssetBalance`, `vsl_getSettledClaimById`, `vsl_pay`,
+    /// `vsl_transferAsset` and `vsl_setAccountState` from the ledger
+    pub(crate) fn module(ledger: Arc<Mutex<Ledger>>) -> RpcModule<Arc<Mutex<Ledger>>> {
+        let mut module = RpcModule::new(ledger);
+        module
+            .register_method("vsl_getAccountNonce", |_, ledger, _| -> RpcResult<u64> {
+                Ok(ledger.lock().unwrap().nonce)
+            })
+            .unwrap();
+        module
+            .register_method("vsl_getBalance", |_, ledger, _| -> RpcResult<String> {
+                Ok(ledger.lock().unwrap().balance.to_hex_str())
+            })
+            .unwrap();
+        module
+            .register_method("vsl_getAssetBalance", |_, ledger, _| -> RpcResult<String> {
+                Ok(ledger.lock().unwrap().asset_balance.to_hex_str())
+            })
+            .unwrap();
+        module
+            .register_method(
+                "vsl_getSettledClaimById",
+                |params: Params, ledger, _| -> RpcResult<String> {
+                    let claim_id: String = params.one()?;
+                    let ledger = ledger.lock().unwrap();
+                    if ledger.rate_limited {
+                        Err(error(RATE_LIMITED_CODE, "rate limited"))
+                    } else if ledger.settled.contains(&claim_id) {
+                        Ok(claim_id)
+                    } else {
+                        Err(rejected("unknown claim"))
+                    }
+                },
+            )
+            .unwrap();
+        module
+            .register_method(
+                "vsl_pay",
+                |params: Params, ledger, _| -> RpcResult<String> {
+                    let payment: Signed<PayMessage> = params.one()?;
+                    let payment = payment.tx();
+                    let claim_id = payment.claim_id();
+                    ledger
+                        .lock()
+                        .unwrap()
+                        .settle(&payment.nonce, &payment.amount, claim_id)
+                },
+            )
+            .unwrap();
+        module
+            .register_method(
+                "vsl_transferAsset",
+                |params: Params, ledger, _| -> RpcResult<String> {
+                    let transfer: Signed<TransferAssetMessage> = params.one()?;
+                    let transfer = transfer.tx();
+                    let claim_id = transfer.claim_id();
+                    ledger
+                        .lock()
+                        .unwrap()
+                        .settle(&transfer.nonce, &transfer.amount, claim_id)
+                },
+            )
+            .unwrap();
+        module
+            .register_method(
+                "vsl_setAccountState",
+                |params: Params, ledger, _| -> RpcResult<String> {
+                    let _: Signed<SetStateMessage> = params.one()?;
+                    ledger.lock().unwrap().nonce += 1;
+                    Ok(B256::repeat_byte(0x01).to_string())
+                },
+            )
+            .unwrap();
+        module
+    }
+}
@@ -0,0 +1,289 @@
+//! # Test Vectors Module
+//!
+//! Golden test vectors for implementations of the signing scheme described in
+//! `docs/signing.md` (in other languages): for each kind of message, a vector
+//! records the message fields, their RLP encoding, the bytes and EIP-191 hash
+//! to be signed, the (deterministic, RFC 6979) signature, the signer address,
+//! and the signed message as sent to the RPC server.
+//!
+//! The vectors are [generate]d with [crate::IntoSigned], and published in
+//! `docs/signing_test_vectors.json` (see the `test-vectors` example, which also
+//! checks signed messages produced by other implementations, see
+//! [TestVector::verify_signed]).
+//!
+//! **The private keys of the vectors are public: never use them for real accounts.**
+//!
+use std::fmt;
+
+use alloy::primitives::{Bytes, keccak256};
+use alloy::signers::Signature;
+use alloy::signers::local::PrivateKeySigner;
+use serde::{Deserialize, Serialize};
+
+use crate::envelope::{SignedMessage, UnsignedMessage};
+use crate::network::DEVNET;
+use crate::rpc_messages::AccountStateHash;
+use crate::{Address, Amount, AssetId, B256, Timestamp, VerificationError, signing_preimage};
+
+/// The version of the test vectors format (and of the signing scheme)
+pub const TEST_VECTORS_VERSION: u32 = 1;
+
+/// A versioned set of test vectors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u32,
+    pub vectors: Vec<TestVector>,
+}
+
+/// A message, with all the intermediate values of its signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVector {
+    /// The (unique) name of the vector
+    pub name: String,
+    /// The private key of the signer
+    pub private_key: B256,
+    /// The signing domain (network), if any
+    pub network: Option<String>,
+    /// The kind and fields of the message
+    #[serde(flatten)]
+    pub message: UnsignedMessage,
+    /// The RPC method receiving the message
+    pub method: String,
+    /// The RLP encoding of the message
+    pub rlp: Bytes,
+    /// The bytes to be signed: the RLP encoding, prefixed with the domain (if any)
+    pub preimage: Bytes,
+    /// The EIP-191 hash of the preimage
+    pub hash: B256,
+    pub signature: Signature,
+    /// The address recovered from the signature
+    pub signer: Address,
+    /// The signed message, as sent to the RPC method
+    pub signed: serde_json::Value,
+}
+
+/// Why a signed message does not match a test vector
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VectorError {
+    /// The signed message cannot be parsed as a message of the vector's kind
+    InvalidMessage(String),
+    /// The fields of the signed message differ from those of the vector
+    FieldsMismatch,
+    /// The signed message fails (strict) verification
+    Verification(VerificationError),
+}
+
+impl fmt::Display for VectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VectorError::InvalidMessage(error) => write!(f, "invalid signed message: {error}"),
+            VectorError::FieldsMismatch => write!(f, "the message fields differ from the vector"),
+            VectorError::Verification(error) => write!(f, "verification failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for VectorError {}
+
+impl From<VerificationError> for VectorError {
+    fn from(error: VerificationError) -> Self {
+        VectorError::Verification(error)
+    }
+}
+
+impl TestVector {
+    /// Computes the vector of the message, signed with the given key
+    pub fn new(
+        name: &str,
+        private_key: B256,
+        network: Option<&str>,
+        message: UnsignedMessage,
+    ) -> Self {
+        let signer = PrivateKeySigner::from_bytes(&private_key).expect("valid private key");
+        let rlp = message.rlp();
+        let preimage = Bytes::from(signing_preimage(&rlp, network));
+        let signed = message
+            .clone()
+            .sign(&signer, network)
+            .expect("local signing does not fail");
+        let json = serde_json::to_value(&signed).expect("serializable");
+        TestVector {
+            name: name.to_string(),
+            private_key,
+            network: network.map(str::to_string),
+            method: signed.method().to_string(),
+            hash: signed.hash(),
+            signature: signed.signature(),
+            signer: signer.address(),
+            signed: json["message"].clone(),
+            message,
+            rlp,
+            preimage,
+        }
+    }
+
+    /// Checks a signed message (as sent to the RPC method) produced externally
+    /// from the fields of the vector: it must have the same fields and hash, and
+    /// be validly signed by the signer of the vector
+    pub fn verify_signed(&self, signed: &serde_json::Value) -> Result<(), VectorError> {
+        let tagged = serde_json::json!({ "kind": self.message.kind(), "message": signed });
+        let signed: SignedMessage = serde_json::from_value(tagged)
+            .map_err(|e| VectorError::InvalidMessage(e.to_string()))?;
+        if signed.unsigned().rlp() != self.rlp {
+            return Err(VectorError::FieldsMismatch);
+        }
+        signed.verify(self.network.as_deref())?;
+        Ok(())
+    }
+}
+
+impl TestVectors {
+    /// The vector with the given name, if any
+    pub fn get(&self, name: &str) -> Option<&TestVector> {
+        self.vectors.iter().find(|vector| vector.name == name)
+    }
+}
+
+/// The (public!) private key used for the vector with the given name
+fn private_key(name: &str) -> B256 {
+    keccak256(format!("vsl-sdk test vector {name}"))
+}
+
+fn address(name: &str) -> Address {
+    PrivateKeySigner::from_bytes(&private_key(name))
+        .expect("valid private key")
+        .address()
+}
+
+/// Generates the test vectors (deterministically)
+pub fn generate() -> TestVectors {
+    let receiver = Address::repeat_byte(0xff);
+    let mut vectors = Vec::new();
+    let mut add = |name: &str, network: Option<&str>, message: UnsignedMessage| {
+        vectors.push(TestVector::new(name, private_key(name), network, message));
+    };
+    add(
+        "pay",
+        None,
+        UnsignedMessage::pay(&address("pay"), 100, &receiver, &Amount::from_subunits(10)),
+    );
+    add(
+        "pay_zero",
+        None,
+        UnsignedMessage::pay(&address("pay_zero"), 0, &receiver, &Amount::ZERO),
+    );
+    add(
+        "pay_devnet",
+        Some(DEVNET.domain()),
+        UnsignedMessage::pay(
+            &address("pay_devnet"),
+            100,
+            &receiver,
+            &Amount::from_vsl_tokens(1),
+        ),
+    );
+    add(
+        "submit_claim",
+        None,
+        UnsignedMessage::submit_claim(
+            &address("submit_claim"),
+            7,
+            "the claim".to_string(),
+            "test".to_string(),
+            "the proof".to_string(),
+            &[Address::repeat_byte(0x01), Address::repeat_byte(0x02)],
+            2,
+            Timestamp::from_parts(1_750_000_000, 500_000_000),
+            &Amount::from_subunits(1_000_000),
+        ),
+    );
+    add(
+        "settle_claim",
+        None,
+        UnsignedMessage::settle_claim(&address("settle_claim"), 3, &B256::repeat_byte(0xab)),
+    );
+    add(
+        "create_asset",
+        None,
+        UnsignedMessage::create_asset(
+            &address("create_asset"),
+            1,
+            "TST",
+            6,
+            &Amount::from_tokens(1_000_000, 6),
+        )
+        .expect("valid asset"),
+    );
+    add(
+        "transfer_asset",
+        None,
+        UnsignedMessage::transfer_asset(
+            &address("transfer_asset"),
+            2,
+            &AssetId::repeat_byte(0xcd),
+            &receiver,
+            &Amount::from_tokens(5, 6),
+        ),
+    );
+    add(
+        "set_state",
+        None,
+        UnsignedMessage::set_state(&address("set_state"), 4, &AccountStateHash::hash(b"state")),
+    );
+    TestVectors {
+        version: TEST_VECTORS_VERSION,
+        vectors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOLDEN: &str = include_str!("../../docs/signing_test_vectors.json");
+
+    #[test]
+    fn test_golden_vectors() {
+        // regenerate with `cargo run --example test-vectors > docs/signing_test_vectors.json`
+        let golden: serde_json::Value = serde_json::from_str(GOLDEN).unwrap();
+        assert_eq!(serde_json::to_value(generate()).unwrap(), golden);
+    }
+
+    #[test]
+    fn test_verify_signed() {
+        let vectors: TestVectors = serde_json::from_str(GOLDEN).unwrap();
+        assert_eq!(vectors.version, TEST_VECTORS_VERSION);
+        for vector in &vectors.vectors {
+            vector.verify_signed(&vector.signed).unwrap();
+        }
+
+        let pay = vectors.get("pay").unwrap();
+        let mut decimal_amount = pay.signed.clone();
+        decimal_amount["amount"] = "10".into();
+        assert_eq!(
+            pay.verify_signed(&decimal_amount),
+            Err(VectorError::FieldsMismatch)
+        );
+        let mut wrong_hash = pay.signed.clone();
+        wrong_hash["hash"] = B256::ZERO.to_string().into();
+        assert!(matches!(
+            pay.verify_signed(&wrong_hash),
+            Err(VectorError::Verification(
+                VerificationError::HashMismatch { .. }
+            ))
+        ));
+        let mut unsigned = pay.signed.clone();
+        unsigned.as_object_mut().unwrap().remove("r");
+        assert!(matches!(
+            pay.verify_signed(&unsigned),
+            Err(VectorError::InvalidMessage(_))
+        ));
+
+        // the domain-separated signature does not verify without domain
+        let devnet = vectors.get("pay_devnet").unwrap();
+        assert_ne!(devnet.preimage, devnet.rlp);
+        let mut plain = devnet.clone();
+        plain.network = None;
+        assert!(plain.verify_signed(&devnet.signed).is_err());
+    }
+}
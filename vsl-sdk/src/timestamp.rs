@@ -2,21 +2,29 @@
 //!
 //! This module provides the `Timestamp` struct, a compact representation of time with
 //! second and nanosecond precision. It supports natural ordering (`Ord`, `PartialOrd`),
-//! formatted display, string parsing, and JSON serialization.
+//! formatted display, string parsing, JSON serialization, and arithmetic with
+//! [Duration]s (converting from and to [SystemTime]).
+//!
+//! For byte-ordered key-value stores, [Timestamp::to_key_bytes] provides a
+//! fixed-width big-endian encoding whose lexicographic order matches the
+//! order of the timestamps.
 //!
 use alloy_rlp::{RlpDecodable, RlpEncodable};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(
     Debug,
     Clone,
     Copy,
+    Default,
     PartialEq,
     Eq,
+    Hash,
     Serialize,
     Deserialize,
     JsonSchema,
@@ -33,13 +41,65 @@ pub struct Timestamp {
 
 impl Timestamp {
     pub fn now() -> Self {
-        let duration = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards!");
-        Timestamp {
-            seconds: duration.as_secs(),
-            nanos: duration.subsec_nanos(),
-        }
+        SystemTime::now().into()
+    }
+
+    /// The timestamp `duration` from now
+    pub fn in_(duration: Duration) -> Self {
+        Self::now() + duration
+    }
+
+    /// Whether something expiring at this timestamp has expired at time `now`
+    pub fn is_expired_at(&self, now: &Timestamp) -> bool {
+        self < now
+    }
+
+    /// Whether something expiring at this timestamp has expired
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(&Self::now())
+    }
+
+    /// Time elapsed from the [UNIX_EPOCH]
+    pub fn as_duration(&self) -> Duration {
+        Duration::new(self.seconds, self.nanos)
+    }
+
+    /// The time elapsed from `earlier` to `self`, or `None` if `earlier` is later than `self`
+    pub fn duration_since(&self, earlier: &Timestamp) -> Option<Duration> {
+        self.as_duration().checked_sub(earlier.as_duration())
+    }
+
+    /// The time elapsed from `earlier` to `self`, or zero if `earlier` is later than `self`
+    pub fn saturating_duration_since(&self, earlier: &Timestamp) -> Duration {
+        self.duration_since(earlier).unwrap_or_default()
+    }
+
+    /// The time elapsed from `self` to now (zero if `self` is in the future)
+    pub fn elapsed(&self) -> Duration {
+        Self::now().saturating_duration_since(self)
+    }
+
+    /// `self + duration`, or `None` on overflow
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let sum = self.as_duration().checked_add(duration)?;
+        Some(sum.into())
+    }
+
+    /// `self - duration`, or `None` if the result would precede the [UNIX_EPOCH]
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let difference = self.as_duration().checked_sub(duration)?;
+        Some(difference.into())
+    }
+
+    pub fn saturating_add(&self, duration: Duration) -> Self {
+        self.checked_add(duration).unwrap_or(Timestamp {
+            seconds: u64::MAX,
+            nanos: 999_999_999,
+        })
+    }
+
+    pub fn saturating_sub(&self, duration: Duration) -> Self {
+        self.checked_sub(duration).unwrap_or_default()
     }
 
     pub fn from_parts(seconds: u64, nanos: u32) -> Self {
@@ -58,9 +118,164 @@ impl Timestamp {
         Timestamp { seconds, nanos: 0 }
     }
 
-    /// The timestamp as a formatted string: "seconds.nanos"
-    pub fn to_string(&self) -> String {
-        format!("{}.{}", self.seconds, self.nanos)
+    /// Length of the [Timestamp::to_key_bytes] encoding
+    pub const KEY_LEN: usize = 12;
+
+    /// Encodes the timestamp as big-endian seconds followed by big-endian nanos,
+    /// so that byte-wise comparison of encodings matches timestamp ordering
+    pub fn to_key_bytes(&self) -> [u8; Self::KEY_LEN] {
+        let mut bytes = [0u8; Self::KEY_LEN];
+        bytes[..8].copy_from_slice(&self.seconds.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.nanos.to_be_bytes());
+        bytes
+    }
+
+    /// Decodes a timestamp encoded by [Timestamp::to_key_bytes]
+    pub fn from_key_bytes(bytes: &[u8]) -> Result<Self, InvalidKey> {
+        let bytes: &[u8; Self::KEY_LEN] = bytes.try_into().map_err(|_| InvalidKey)?;
+        let (seconds, nanos) = bytes.split_at(8);
+        let seconds = u64::from_be_bytes(seconds.try_into().expect("8 bytes"));
+        let nanos = u32::from_be_bytes(nanos.try_into().expect("4 bytes"));
+        if nanos >= 1_000_000_000 {
+            return Err(InvalidKey);
+        }
+        Ok(Timestamp { seconds, nanos })
+    }
+
+    /// The timestamp as decimal seconds with exactly nine (zero-padded)
+    /// fractional digits, e.g. `"12.000000005"` for 12 seconds and 5 nanoseconds
+    pub fn to_decimal_string(&self) -> String {
+        format!("{}.{:09}", self.seconds, self.nanos)
+    }
+
+    /// Parses decimal seconds with at most nine fractional digits,
+    /// e.g. `"12.5"` is 12 seconds and 500_000_000 nanoseconds
+    pub fn from_decimal_str(s: &str) -> Result<Self, ParseTimestampError> {
+        let (seconds, fraction) = match s.split_once('.') {
+            Some((seconds, fraction)) if is_digits(fraction) => (seconds, fraction),
+            Some(_) => return Err(ParseTimestampError),
+            None => (s, ""),
+        };
+        if !is_digits(seconds) {
+            return Err(ParseTimestampError);
+        }
+        let seconds = u64::from_str(seconds).map_err(|_| ParseTimestampError)?;
+        Ok(Timestamp {
+            seconds,
+            nanos: parse_fraction(fraction)?,
+        })
+    }
+
+    /// The timestamp as an [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) UTC date-time,
+    /// e.g. `"2023-11-14T22:13:20.000000005Z"`
+    ///
+    /// Fractional seconds are omitted if zero, and are otherwise displayed
+    /// with millisecond, microsecond or nanosecond precision, as needed.
+    pub fn to_rfc3339(&self) -> String {
+        let days = (self.seconds / SECONDS_PER_DAY) as i64;
+        let seconds_of_day = self.seconds % SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        let mut result = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds_of_day / 3600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60
+        );
+        if self.nanos.is_multiple_of(1_000_000) {
+            if self.nanos != 0 {
+                result.push_str(&format!(".{:03}", self.nanos / 1_000_000));
+            }
+        } else if self.nanos.is_multiple_of(1_000) {
+            result.push_str(&format!(".{:06}", self.nanos / 1_000));
+        } else {
+            result.push_str(&format!(".{:09}", self.nanos));
+        }
+        result.push('Z');
+        result
+    }
+
+    /// Parses an [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) date-time,
+    /// e.g. `"2023-11-14T22:13:20.5Z"` or `"2023-11-15 01:13:20+03:00"`
+    ///
+    /// Fails for leap seconds, for more than nine fractional digits,
+    /// and for date-times preceding the [UNIX_EPOCH].
+    pub fn from_rfc3339(s: &str) -> Result<Self, ParseTimestampError> {
+        let bytes = s.as_bytes();
+        // the fields are sliced by byte offset
+        if !s.is_ascii()
+            || bytes.len() < 20
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || !matches!(bytes[10], b'T' | b't' | b' ')
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+        {
+            return Err(ParseTimestampError);
+        }
+        let number = |range: std::ops::Range<usize>| -> Result<i64, ParseTimestampError> {
+            let digits = &s[range];
+            if !is_digits(digits) {
+                return Err(ParseTimestampError);
+            }
+            i64::from_str(digits).map_err(|_| ParseTimestampError)
+        };
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(ParseTimestampError);
+        }
+        let mut rest = &s[19..];
+        let mut nanos = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let end = fraction
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(fraction.len());
+            if end == 0 {
+                return Err(ParseTimestampError);
+            }
+            nanos = parse_fraction(&fraction[..end])?;
+            rest = &fraction[end..];
+        }
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ => {
+                let bytes = rest.as_bytes();
+                if bytes.len() != 6 || bytes[3] != b':' {
+                    return Err(ParseTimestampError);
+                }
+                let sign = match bytes[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return Err(ParseTimestampError),
+                };
+                let (hours, minutes) = (&rest[1..3], &rest[4..6]);
+                if !is_digits(hours) || !is_digits(minutes) {
+                    return Err(ParseTimestampError);
+                }
+                let hours = i64::from_str(hours).map_err(|_| ParseTimestampError)?;
+                let minutes = i64::from_str(minutes).map_err(|_| ParseTimestampError)?;
+                if hours > 23 || minutes > 59 {
+                    return Err(ParseTimestampError);
+                }
+                sign * (hours * 3600 + minutes * 60)
+            }
+        };
+        let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY as i64
+            + hour * 3600
+            + minute * 60
+            + second
+            - offset;
+        let seconds = u64::try_from(seconds).map_err(|_| ParseTimestampError)?;
+        Ok(Timestamp { seconds, nanos })
     }
 
     /// Returns the next timestamp, incrementing the nanos by one.
@@ -86,6 +301,100 @@ use std::str::FromStr;
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseTimestampError;
 
+impl fmt::Display for ParseTimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid timestamp")
+    }
+}
+
+impl std::error::Error for ParseTimestampError {}
+
+/// The timestamp cannot be represented in the target time type
+#[derive(Debug, PartialEq, Eq)]
+pub struct TimestampOutOfRange;
+
+impl fmt::Display for TimestampOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timestamp out of range")
+    }
+}
+
+impl std::error::Error for TimestampOutOfRange {}
+
+/// The bytes are not a valid key encoding (wrong length or out-of-range nanos)
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidKey;
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid key encoding")
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Parses the digits following the decimal point as nanoseconds
+fn parse_fraction(fraction: &str) -> Result<u32, ParseTimestampError> {
+    if fraction.len() > 9 {
+        return Err(ParseTimestampError);
+    }
+    if fraction.is_empty() {
+        return Ok(0);
+    }
+    let padded = format!("{:0<9}", fraction);
+    u32::from_str(&padded).map_err(|_| ParseTimestampError)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from the [UNIX_EPOCH] to the given (proleptic Gregorian) date; see
+/// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The (proleptic Gregorian) date at the given number of days from the [UNIX_EPOCH]; see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 impl FromStr for Timestamp {
     type Err = ParseTimestampError;
 
@@ -106,7 +415,8 @@ impl FromStr for Timestamp {
     }
 }
 
-/// Allow printing the timestamp directly
+/// Allow printing the timestamp directly, as "seconds.nanos" (the nanos are not padded;
+/// see [Timestamp::to_decimal_string] and [Timestamp::to_rfc3339] for readable formats)
 impl fmt::Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}.{}", self.seconds, self.nanos)
@@ -129,10 +439,138 @@ impl Ord for Timestamp {
     }
 }
 
+/// Time elapsed from the [UNIX_EPOCH]
+impl From<Duration> for Timestamp {
+    fn from(duration: Duration) -> Self {
+        Timestamp {
+            seconds: duration.as_secs(),
+            nanos: duration.subsec_nanos(),
+        }
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.as_duration()
+    }
+}
+
+/// Times before the [UNIX_EPOCH] are clamped to the [UNIX_EPOCH]
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        time.duration_since(UNIX_EPOCH).unwrap_or_default().into()
+    }
+}
+
+/// Panics if the timestamp cannot be represented as a [SystemTime]
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        UNIX_EPOCH + timestamp.as_duration()
+    }
+}
+
+/// Panics on overflow; see [Timestamp::checked_add]
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Self::Output {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to timestamp")
+    }
+}
+
+impl AddAssign<Duration> for Timestamp {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+/// Panics if the result would precede the [UNIX_EPOCH]; see [Timestamp::checked_sub]
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Self::Output {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from timestamp")
+    }
+}
+
+impl SubAssign<Duration> for Timestamp {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+/// Panics if `earlier` is later than `self`; see [Timestamp::duration_since]
+impl Sub for Timestamp {
+    type Output = Duration;
+
+    fn sub(self, earlier: Timestamp) -> Self::Output {
+        self.duration_since(&earlier)
+            .expect("subtracted timestamp is later than self")
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_interop {
+    use chrono::{DateTime, Utc};
+
+    use super::{Timestamp, TimestampOutOfRange};
+
+    impl TryFrom<Timestamp> for DateTime<Utc> {
+        type Error = TimestampOutOfRange;
+
+        fn try_from(timestamp: Timestamp) -> Result<Self, Self::Error> {
+            let seconds = i64::try_from(timestamp.seconds()).map_err(|_| TimestampOutOfRange)?;
+            DateTime::from_timestamp(seconds, timestamp.nanos()).ok_or(TimestampOutOfRange)
+        }
+    }
+
+    /// Fails for date-times preceding the [UNIX_EPOCH](std::time::UNIX_EPOCH)
+    impl TryFrom<DateTime<Utc>> for Timestamp {
+        type Error = TimestampOutOfRange;
+
+        fn try_from(time: DateTime<Utc>) -> Result<Self, Self::Error> {
+            let seconds = u64::try_from(time.timestamp()).map_err(|_| TimestampOutOfRange)?;
+            Ok(Timestamp::from_parts(
+                seconds,
+                time.timestamp_subsec_nanos(),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_interop {
+    use time::OffsetDateTime;
+
+    use super::{Timestamp, TimestampOutOfRange};
+
+    impl TryFrom<Timestamp> for OffsetDateTime {
+        type Error = TimestampOutOfRange;
+
+        fn try_from(timestamp: Timestamp) -> Result<Self, Self::Error> {
+            let seconds = i64::try_from(timestamp.seconds()).map_err(|_| TimestampOutOfRange)?;
+            OffsetDateTime::from_unix_timestamp(seconds)
+                .and_then(|time| time.replace_nanosecond(timestamp.nanos()))
+                .map_err(|_| TimestampOutOfRange)
+        }
+    }
+
+    /// Fails for date-times preceding the [UNIX_EPOCH](std::time::UNIX_EPOCH)
+    impl TryFrom<OffsetDateTime> for Timestamp {
+        type Error = TimestampOutOfRange;
+
+        fn try_from(time: OffsetDateTime) -> Result<Self, Self::Error> {
+            let seconds = u64::try_from(time.unix_timestamp()).map_err(|_| TimestampOutOfRange)?;
+            Ok(Timestamp::from_parts(seconds, time.nanosecond()))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json;
     use std::str::FromStr;
 
     #[test]
@@ -263,4 +701,185 @@ mod tests {
         assert_eq!(next_ts_max.seconds(), 12346);
         assert_eq!(next_ts_max.nanos(), 0);
     }
+
+    #[test]
+    fn test_duration_arithmetic() {
+        let ts = Timestamp::from_parts(10, 999_999_999);
+        let later = ts + Duration::from_nanos(2);
+        assert_eq!(later, Timestamp::from_parts(11, 1));
+        assert_eq!(later - Duration::from_nanos(2), ts);
+        assert_eq!(later - ts, Duration::from_nanos(2));
+        assert_eq!(later.duration_since(&ts), Some(Duration::from_nanos(2)));
+        assert_eq!(ts.duration_since(&later), None);
+        assert_eq!(ts.saturating_duration_since(&later), Duration::ZERO);
+
+        let mut ts2 = ts;
+        ts2 += Duration::from_secs(5);
+        ts2 -= Duration::from_secs(1);
+        assert_eq!(ts2, Timestamp::from_parts(14, 999_999_999));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let max = Timestamp::from_parts(u64::MAX, 999_999_999);
+        assert_eq!(max.checked_add(Duration::from_nanos(1)), None);
+        assert_eq!(max.saturating_add(Duration::from_secs(1)), max);
+        let zero = Timestamp::from_seconds(0);
+        assert_eq!(zero.checked_sub(Duration::from_nanos(1)), None);
+        assert_eq!(zero.saturating_sub(Duration::from_secs(1)), zero);
+    }
+
+    #[test]
+    fn test_system_time_conversions() {
+        let ts = Timestamp::from_parts(1_700_000_000, 123);
+        let time: SystemTime = ts.into();
+        assert_eq!(Timestamp::from(time), ts);
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(Timestamp::from(before_epoch), Timestamp::from_seconds(0));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let expires = Timestamp::from_seconds(100);
+        assert!(!expires.is_expired_at(&Timestamp::from_seconds(99)));
+        assert!(!expires.is_expired_at(&expires));
+        assert!(expires.is_expired_at(&expires.tick()));
+        assert!(expires.is_expired());
+        assert!(!Timestamp::in_(Duration::from_secs(60)).is_expired());
+    }
+
+    #[test]
+    fn test_decimal_format() {
+        let ts = Timestamp::from_parts(12, 5);
+        assert_eq!(ts.to_decimal_string(), "12.000000005");
+        assert_eq!(Timestamp::from_decimal_str("12.000000005").unwrap(), ts);
+        assert_eq!(
+            Timestamp::from_decimal_str("12.5").unwrap(),
+            Timestamp::from_parts(12, 500_000_000)
+        );
+        assert_eq!(
+            Timestamp::from_decimal_str("12").unwrap(),
+            Timestamp::from_seconds(12)
+        );
+        assert!(Timestamp::from_decimal_str("12.0000000001").is_err());
+        assert!(Timestamp::from_decimal_str("12.").is_err());
+        assert!(Timestamp::from_decimal_str(".5").is_err());
+        assert!(Timestamp::from_decimal_str("+12").is_err());
+    }
+
+    #[test]
+    fn test_rfc3339_formatting() {
+        assert_eq!(
+            Timestamp::from_seconds(0).to_rfc3339(),
+            "1970-01-01T00:00:00Z"
+        );
+        let ts = Timestamp::from_seconds(1_700_000_000);
+        assert_eq!(ts.to_rfc3339(), "2023-11-14T22:13:20Z");
+        assert_eq!(
+            Timestamp::from_parts(1_700_000_000, 500_000_000).to_rfc3339(),
+            "2023-11-14T22:13:20.500Z"
+        );
+        assert_eq!(
+            Timestamp::from_parts(1_700_000_000, 1_000).to_rfc3339(),
+            "2023-11-14T22:13:20.000001Z"
+        );
+        assert_eq!(
+            Timestamp::from_parts(1_700_000_000, 5).to_rfc3339(),
+            "2023-11-14T22:13:20.000000005Z"
+        );
+        // leap day
+        assert_eq!(
+            Timestamp::from_seconds(951_782_400).to_rfc3339(),
+            "2000-02-29T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_rfc3339_parsing() {
+        let ts = Timestamp::from_parts(1_700_000_000, 500_000_000);
+        assert_eq!(
+            Timestamp::from_rfc3339("2023-11-14T22:13:20.5Z").unwrap(),
+            ts
+        );
+        assert_eq!(
+            Timestamp::from_rfc3339("2023-11-14t22:13:20.500z").unwrap(),
+            ts
+        );
+        assert_eq!(
+            Timestamp::from_rfc3339("2023-11-15 01:13:20.5+03:00").unwrap(),
+            ts
+        );
+        assert_eq!(
+            Timestamp::from_rfc3339("2023-11-14T20:13:20.5-02:00").unwrap(),
+            ts
+        );
+        assert_eq!(Timestamp::from_rfc3339(&ts.to_rfc3339()).unwrap(), ts);
+        assert_eq!(
+            Timestamp::from_rfc3339("2000-02-29T00:00:00Z").unwrap(),
+            Timestamp::from_seconds(951_782_400)
+        );
+        assert!(Timestamp::from_rfc3339("2001-02-29T00:00:00Z").is_err());
+        assert!(Timestamp::from_rfc3339("2023-11-14T22:13:60Z").is_err());
+        assert!(Timestamp::from_rfc3339("2023-11-14T22:13:20").is_err());
+        assert!(Timestamp::from_rfc3339("2023-11-14T22:13:20.Z").is_err());
+        assert!(Timestamp::from_rfc3339("2023-11-14T22:13:20.0000000001Z").is_err());
+        assert!(Timestamp::from_rfc3339("1969-12-31T23:59:59Z").is_err());
+        assert!(Timestamp::from_rfc3339("1970-01-01T00:30:00+01:00").is_err());
+        assert!(Timestamp::from_rfc3339("2024-01-01T00:00:0é0Z").is_err());
+        assert!(Timestamp::from_rfc3339("2024-01-01T00:00:00.5é").is_err());
+    }
+
+    #[test]
+    fn test_rfc3339_round_trip() {
+        for seconds in [0, 68_169_600, 951_868_799, 4_107_542_400, 253_402_300_799] {
+            let ts = Timestamp::from_parts(seconds, 123_456_789);
+            assert_eq!(Timestamp::from_rfc3339(&ts.to_rfc3339()).unwrap(), ts);
+        }
+    }
+
+    #[test]
+    fn test_key_bytes() {
+        let timestamps = [
+            Timestamp::from_parts(0, 0),
+            Timestamp::from_parts(0, 999_999_999),
+            Timestamp::from_parts(1, 0),
+            Timestamp::from_parts(255, 1),
+            Timestamp::from_parts(256, 0),
+            Timestamp::from_parts(u64::MAX, 999_999_999),
+        ];
+        for pair in timestamps.windows(2) {
+            assert!(pair[0].to_key_bytes() < pair[1].to_key_bytes());
+        }
+        for ts in timestamps {
+            assert_eq!(Timestamp::from_key_bytes(&ts.to_key_bytes()).unwrap(), ts);
+        }
+        assert_eq!(
+            Timestamp::from_parts(1, 2).to_key_bytes(),
+            [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2]
+        );
+        assert_eq!(Timestamp::from_key_bytes(&[0; 11]), Err(InvalidKey));
+        let mut bytes = Timestamp::from_seconds(1).to_key_bytes();
+        bytes[8..].copy_from_slice(&1_000_000_000u32.to_be_bytes());
+        assert_eq!(Timestamp::from_key_bytes(&bytes), Err(InvalidKey));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_conversions() {
+        let ts = Timestamp::from_parts(1_700_000_000, 5);
+        let time = chrono::DateTime::<chrono::Utc>::try_from(ts).unwrap();
+        assert_eq!(time.to_rfc3339(), "2023-11-14T22:13:20.000000005+00:00");
+        assert_eq!(Timestamp::try_from(time).unwrap(), ts);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_conversions() {
+        let ts = Timestamp::from_parts(1_700_000_000, 5);
+        let time = time::OffsetDateTime::try_from(ts).unwrap();
+        assert_eq!(time.unix_timestamp(), 1_700_000_000);
+        assert_eq!(Timestamp::try_from(time).unwrap(), ts);
+        let far = Timestamp::from_seconds(u64::MAX);
+        assert!(time::OffsetDateTime::try_from(far).is_err());
+    }
 }
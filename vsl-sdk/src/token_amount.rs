@@ -0,0 +1,306 @@
+//! # Token Amount Module
+//!
+//! This module provides the `TokenAmount` struct, an [Amount] tagged with the
+//! asset it is denominated in (`None` for native VSL tokens) and the number of
+//! decimals of that asset. Arithmetic on token amounts is checked and fails when
+//! the operands refer to different assets.
+//!
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_wrapper::AssetData;
+use crate::{Amount, AmountFormat, AmountParser, AssetId, ParseAmountError, VSL_DECIMALS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// An amount of either VSL tokens or of a given asset
+pub struct TokenAmount {
+    /// The asset being counted (`None` for VSL tokens)
+    asset: Option<AssetId>,
+    /// The amount, in subunits of the asset
+    #[serde(with = "crate::amount_serde::decimal")]
+    amount: Amount,
+    /// The number of decimals of the asset
+    decimals: u8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenAmountError {
+    /// The operands denominate different assets
+    AssetMismatch {
+        expected: Option<AssetId>,
+        found: Option<AssetId>,
+    },
+    /// The operands denominate the same asset, but with different decimals
+    DecimalsMismatch { expected: u8, found: u8 },
+    /// The result of the operation does not fit into an [Amount]
+    Overflow,
+    /// The operation would yield a negative amount
+    Underflow,
+    /// A VSL amount was used where an asset amount was expected
+    NotAnAsset,
+}
+
+impl fmt::Display for TokenAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let asset_name = |asset: &Option<AssetId>| match asset {
+            Some(asset) => format!("asset {}", asset),
+            None => "VSL".to_string(),
+        };
+        match self {
+            TokenAmountError::AssetMismatch { expected, found } => write!(
+                f,
+                "expected an amount of {} but found an amount of {}",
+                asset_name(expected),
+                asset_name(found)
+            ),
+            TokenAmountError::DecimalsMismatch { expected, found } => write!(
+                f,
+                "expected an amount with {} decimals but found one with {} decimals",
+                expected, found
+            ),
+            TokenAmountError::Overflow => write!(f, "token amount overflow"),
+            TokenAmountError::Underflow => write!(f, "token amount underflow"),
+            TokenAmountError::NotAnAsset => write!(f, "expected an asset amount, found VSL"),
+        }
+    }
+}
+
+impl std::error::Error for TokenAmountError {}
+
+impl TokenAmount {
+    /// An amount of native VSL tokens
+    pub fn vsl(amount: Amount) -> Self {
+        TokenAmount {
+            asset: None,
+            amount,
+            decimals: VSL_DECIMALS,
+        }
+    }
+
+    /// An amount of the given asset, using the given number of decimals
+    pub fn of_asset(asset: AssetId, amount: Amount, decimals: u8) -> Self {
+        TokenAmount {
+            asset: Some(asset),
+            amount,
+            decimals,
+        }
+    }
+
+    /// An amount of the given asset, using the decimals from its metadata
+    pub fn from_asset_data(asset: AssetId, asset_data: &AssetData, amount: Amount) -> Self {
+        Self::of_asset(asset, amount, asset_data.decimals)
+    }
+
+    /// A zero amount of the same asset (and decimals) as `self`
+    pub fn zero_like(&self) -> Self {
+        TokenAmount {
+            amount: Amount::ZERO,
+            ..*self
+        }
+    }
+
+    pub fn asset(&self) -> Option<&AssetId> {
+        self.asset.as_ref()
+    }
+
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub fn is_vsl(&self) -> bool {
+        self.asset.is_none()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == Amount::ZERO
+    }
+
+    /// Returns the asset id, failing if this is an amount of VSL tokens
+    pub fn asset_id(&self) -> Result<&AssetId, TokenAmountError> {
+        self.asset.as_ref().ok_or(TokenAmountError::NotAnAsset)
+    }
+
+    /// Checks that `other` denominates the same asset (with the same decimals) as `self`
+    pub fn ensure_same_asset(&self, other: &TokenAmount) -> Result<(), TokenAmountError> {
+        if self.asset != other.asset {
+            return Err(TokenAmountError::AssetMismatch {
+                expected: self.asset,
+                found: other.asset,
+            });
+        }
+        if self.decimals != other.decimals {
+            return Err(TokenAmountError::DecimalsMismatch {
+                expected: self.decimals,
+                found: other.decimals,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn checked_add(&self, rhs: &TokenAmount) -> Result<TokenAmount, TokenAmountError> {
+        self.ensure_same_asset(rhs)?;
+        let amount = self
+            .amount
+            .checked_add(rhs.amount)
+            .ok_or(TokenAmountError::Overflow)?;
+        Ok(TokenAmount { amount, ..*self })
+    }
+
+    pub fn checked_sub(&self, rhs: &TokenAmount) -> Result<TokenAmount, TokenAmountError> {
+        self.ensure_same_asset(rhs)?;
+        let amount = self
+            .amount
+            .checked_sub(rhs.amount)
+            .ok_or(TokenAmountError::Underflow)?;
+        Ok(TokenAmount { amount, ..*self })
+    }
+
+    /// Compares two amounts, failing if they denominate different assets
+    pub fn checked_cmp(&self, other: &TokenAmount) -> Result<Ordering, TokenAmountError> {
+        self.ensure_same_asset(other)?;
+        Ok(self.amount.cmp(&other.amount))
+    }
+
+    /// Parses a decimal string (e.g. `"12.5"`, `"1_000 VSL"` or `"2500 atto"`)
+    /// as an amount of VSL tokens; see [AmountParser::vsl]
+    pub fn parse_vsl(s: &str) -> Result<Self, ParseAmountError> {
+        Ok(Self::vsl(AmountParser::vsl().parse(s)?))
+    }
+
+    /// Parses a decimal string (e.g. `"12.5"` or `"12.5 TCK"`) as an amount of the
+    /// given asset, using the decimals and ticker from its metadata
+    pub fn parse_asset(
+        s: &str,
+        asset: AssetId,
+        asset_data: &AssetData,
+    ) -> Result<Self, ParseAmountError> {
+        let amount =
+            AmountParser::for_asset(&asset_data.ticker_symbol, asset_data.decimals).parse(s)?;
+        Ok(Self::from_asset_data(asset, asset_data, amount))
+    }
+
+    /// Formats the amount using the given format, overriding its decimals
+    pub fn format(&self, format: &AmountFormat) -> String {
+        AmountFormat {
+            decimals: self.decimals,
+            ..format.clone()
+        }
+        .format(&self.amount)
+    }
+}
+
+/// Amounts of different assets are not comparable
+impl PartialOrd for TokenAmount {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.checked_cmp(other).ok()
+    }
+}
+
+/// Displays the amount as a decimal number, according to its decimals
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.amount.to_str_with_decimals(self.decimals))
+    }
+}
+
+impl From<TokenAmount> for Amount {
+    fn from(value: TokenAmount) -> Self {
+        value.amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(byte: u8) -> AssetId {
+        AssetId::from_slice(&[byte; 32])
+    }
+
+    #[test]
+    fn test_same_asset_arithmetic() {
+        let a = TokenAmount::of_asset(asset(1), Amount::from_subunits(150), 2);
+        let b = TokenAmount::of_asset(asset(1), Amount::from_subunits(50), 2);
+        assert_eq!(
+            a.checked_add(&b).unwrap().amount(),
+            Amount::from_subunits(200)
+        );
+        assert_eq!(
+            a.checked_sub(&b).unwrap().amount(),
+            Amount::from_subunits(100)
+        );
+        assert_eq!(b.checked_sub(&a), Err(TokenAmountError::Underflow));
+        assert!(b < a);
+    }
+
+    #[test]
+    fn test_asset_mismatch() {
+        let vsl = TokenAmount::vsl(Amount::from_vsl_tokens(1));
+        let a = TokenAmount::of_asset(asset(1), Amount::from_subunits(1), 18);
+        let b = TokenAmount::of_asset(asset(2), Amount::from_subunits(1), 18);
+        assert_eq!(
+            vsl.checked_add(&a),
+            Err(TokenAmountError::AssetMismatch {
+                expected: None,
+                found: Some(asset(1))
+            })
+        );
+        assert!(a.checked_sub(&b).is_err());
+        assert_eq!(a.partial_cmp(&b), None);
+        assert_eq!(vsl.asset_id(), Err(TokenAmountError::NotAnAsset));
+    }
+
+    #[test]
+    fn test_decimals_mismatch() {
+        let a = TokenAmount::of_asset(asset(1), Amount::from_subunits(1), 2);
+        let b = TokenAmount::of_asset(asset(1), Amount::from_subunits(1), 3);
+        assert_eq!(
+            a.checked_add(&b),
+            Err(TokenAmountError::DecimalsMismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let asset_data = AssetData {
+            account_id: Default::default(),
+            nonce: 0,
+            ticker_symbol: "TST".to_string(),
+            decimals: 2,
+            total_supply: Amount::from_tokens(1000, 2),
+        };
+        let amount = TokenAmount::parse_asset("12.5", asset(1), &asset_data).unwrap();
+        assert_eq!(amount.amount(), Amount::from_subunits(1250));
+        assert_eq!(amount.to_string(), "12.5");
+        let amount = TokenAmount::parse_asset("1_012.5 TST", asset(1), &asset_data).unwrap();
+        assert_eq!(
+            amount.format(
+                &AmountFormat::new(0)
+                    .with_thousands_separator(',')
+                    .with_unit("TST")
+            ),
+            "1,012.5 TST"
+        );
+
+        let vsl = TokenAmount::parse_vsl("0.000000000000000001").unwrap();
+        assert_eq!(vsl.amount(), Amount::from_subunits(1));
+        assert_eq!(vsl.to_string(), "0.000000000000000001");
+    }
+
+    #[test]
+    fn test_serialization() {
+        let amount = TokenAmount::of_asset(asset(1), Amount::MAX, 6);
+        let json = serde_json::to_string(&amount).unwrap();
+        assert!(json.contains(r#""amount":"340282366920938463463374607431768211455""#));
+        assert_eq!(serde_json::from_str::<TokenAmount>(&json).unwrap(), amount);
+    }
+}
@@ -0,0 +1,551 @@
+//! # Validation Module
+//!
+//! Stateless validation of the requests of the [ClaimRpcServer] methods: every
+//! "Will fail if" precondition of the methods which can be checked without the
+//! ledger state (signatures, field formats, quorum and decimals bounds).
+//!
+//! Requests are validated with [Validate], and failures are reported with the
+//! VSL error codes below (see `docs/api/rpc.md`). [Validating] wraps any
+//! [ClaimRpcServer] implementation to validate requests before handling them,
+//! so that gateways and mock nodes share the same checks.
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::consensus::Signed;
+use jsonrpsee::PendingSubscriptionSink;
+use jsonrpsee::core::{RpcResult, SubscriptionResult, async_trait};
+use jsonrpsee::types::ErrorObjectOwned;
+
+use crate::rpc_messages::{
+    CreateAssetMessage, CreateAssetResult, PayMessage, SetStateMessage, SettleClaimMessage,
+    SettledClaimData, SettledVerifiedClaim, SubmittedClaim, SubmittedClaimData, Timestamped,
+    TransferAssetMessage,
+};
+use crate::rpc_service::ClaimRpcServer;
+use crate::{
+    Address, Amount, AssetId, B256, IntoSigned, Timestamp, VSL_DECIMALS, VerificationError,
+};
+
+/// A field of the request is not valid
+pub const INVALID_FIELD_CODE: i32 = -32020;
+/// The signature of the request is not valid, or not by its sender
+pub const INVALID_SIGNATURE_CODE: i32 = -32021;
+/// The `quorum` of a claim is larger than the number of its verifiers
+pub const QUORUM_TOO_LARGE_CODE: i32 = -32022;
+/// The `decimals` of an asset are more than those of VSL
+pub const TOO_MANY_DECIMALS_CODE: i32 = -32023;
+
+/// Why a request is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The named field is not valid
+    InvalidField(&'static str),
+    InvalidSignature(VerificationError),
+    QuorumTooLarge {
+        quorum: u16,
+        verifiers: usize,
+    },
+    TooManyDecimals(u8),
+}
+
+impl ValidationError {
+    /// The JSON-RPC error code of the error
+    pub fn code(&self) -> i32 {
+        match self {
+            ValidationError::InvalidField(_) => INVALID_FIELD_CODE,
+            ValidationError::InvalidSignature(_) => INVALID_SIGNATURE_CODE,
+            ValidationError::QuorumTooLarge { .. } => QUORUM_TOO_LARGE_CODE,
+            ValidationError::TooManyDecimals(_) => TOO_MANY_DECIMALS_CODE,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::InvalidField(field) => write!(f, "`{field}` not valid"),
+            ValidationError::InvalidSignature(error) => write!(f, "signature not valid: {error}"),
+            ValidationError::QuorumTooLarge { quorum, verifiers } => write!(
+                f,
+                "`quorum` ({quorum}) is larger than the number of verifiers ({verifiers})"
+            ),
+            ValidationError::TooManyDecimals(decimals) => {
+                write!(f, "`decimals` ({decimals}) is larger than {VSL_DECIMALS}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<VerificationError> for ValidationError {
+    fn from(error: VerificationError) -> Self {
+        ValidationError::InvalidSignature(error)
+    }
+}
+
+impl From<ValidationError> for ErrorObjectOwned {
+    fn from(error: ValidationError) -> Self {
+        ErrorObjectOwned::owned(error.code(), error.to_string(), None::<()>)
+    }
+}
+
+pub type ValidationResult = Result<(), ValidationError>;
+
+/// Checks that the field is an (Ethereum-style) address
+pub fn validate_address(field: &'static str, value: &str) -> ValidationResult {
+    Address::from_str(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::InvalidField(field))
+}
+
+/// Checks that the field is a 256 bit hash (e.g., a claim ID or a state)
+pub fn validate_hash(field: &'static str, value: &str) -> ValidationResult {
+    B256::from_str(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::InvalidField(field))
+}
+
+/// Checks that the field is an asset ID
+pub fn validate_asset_id(field: &'static str, value: &str) -> ValidationResult {
+    AssetId::from_str(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::InvalidField(field))
+}
+
+/// Checks that the field is an amount (u128 formatted as hex string)
+pub fn validate_amount(field: &'static str, value: &str) -> ValidationResult {
+    Amount::from_hex_str(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::InvalidField(field))
+}
+
+/// Checks that the field is a nonce (64 bit unsigned integer)
+pub fn validate_nonce(value: &str) -> ValidationResult {
+    value
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| ValidationError::InvalidField("nonce"))
+}
+
+/// Requests which can be validated without the ledger state
+pub trait Validate {
+    /// Checks the request, in the given signing domain (network) for signed messages
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult;
+
+    fn validate(&self) -> ValidationResult {
+        self.validate_for(None)
+    }
+}
+
+/// Checks the signature of a message whose fields are valid
+fn validate_signature<T: IntoSigned>(signed: &Signed<T>, domain: Option<&str>) -> ValidationResult {
+    T::verify_for(signed, domain)?;
+    Ok(())
+}
+
+impl Validate for Signed<SubmittedClaim> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let claim = self.tx();
+        validate_amount("fee", &claim.fee)?;
+        validate_address("from", &claim.from)?;
+        validate_nonce(&claim.nonce)?;
+        for to in &claim.to {
+            validate_address("to", to)?;
+        }
+        if claim.quorum as usize > claim.to.len() {
+            return Err(ValidationError::QuorumTooLarge {
+                quorum: claim.quorum,
+                verifiers: claim.to.len(),
+            });
+        }
+        validate_signature(self, domain)
+    }
+}
+
+impl Validate for Signed<SettleClaimMessage> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let message = self.tx();
+        validate_address("from", &message.from)?;
+        validate_nonce(&message.nonce)?;
+        validate_signature(self, domain)
+    }
+}
+
+impl Validate for Signed<PayMessage> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let message = self.tx();
+        validate_address("from", &message.from)?;
+        validate_address("to", &message.to)?;
+        validate_nonce(&message.nonce)?;
+        validate_amount("amount", &message.amount)?;
+        validate_signature(self, domain)
+    }
+}
+
+impl Validate for Signed<CreateAssetMessage> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let message = self.tx();
+        validate_address("account_id", &message.account_id)?;
+        validate_amount("total_supply", &message.total_supply)?;
+        validate_nonce(&message.nonce)?;
+        if message.decimals > VSL_DECIMALS {
+            return Err(ValidationError::TooManyDecimals(message.decimals));
+        }
+        validate_signature(self, domain)
+    }
+}
+
+impl Validate for Signed<TransferAssetMessage> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let message = self.tx();
+        validate_address("from", &message.from)?;
+        validate_address("to", &message.to)?;
+        validate_nonce(&message.nonce)?;
+        validate_amount("amount", &message.amount)?;
+        validate_asset_id("asset_id", &message.asset_id)?;
+        validate_signature(self, domain)
+    }
+}
+
+impl Validate for Signed<SetStateMessage> {
+    fn validate_for(&self, domain: Option<&str>) -> ValidationResult {
+        let message = self.tx();
+        validate_address("from", &message.from)?;
+        validate_nonce(&message.nonce)?;
+        validate_hash("state", &message.state)?;
+        validate_signature(self, domain)
+    }
+}
+
+/// A [ClaimRpcServer] validating the requests (see [Validate]) before handing
+/// them to the wrapped server
+#[derive(Debug, Clone)]
+pub struct Validating<S> {
+    inner: S,
+    network: Option<String>,
+}
+
+impl<S> Validating<S> {
+    pub fn new(inner: S) -> Self {
+        Validating {
+            inner,
+            network: None,
+        }
+    }
+
+    /// Requires the signed messages to be signed for the given network (see [crate::network])
+    pub fn with_network(mut self, network: &str) -> Self {
+        self.network = Some(network.to_string());
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn check<T: Validate>(&self, request: &T) -> RpcResult<()> {
+        Ok(request.validate_for(self.network.as_deref())?)
+    }
+}
+
+fn check_address(field: &'static str, value: &str) -> RpcResult<()> {
+    Ok(validate_address(field, value)?)
+}
+
+#[async_trait]
+impl<S: ClaimRpcServer> ClaimRpcServer for Validating<S> {
+    async fn submit_claim(&self, claim: Signed<SubmittedClaim>) -> RpcResult<String> {
+        self.check(&claim)?;
+        self.inner.submit_claim(claim).await
+    }
+
+    async fn settle_claim(&self, settled_claim: Signed<SettleClaimMessage>) -> RpcResult<String> {
+        self.check(&settled_claim)?;
+        self.inner.settle_claim(settled_claim).await
+    }
+
+    async fn list_settled_claims_metadata(
+        &self,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<SettledClaimData>>> {
+        self.inner.list_settled_claims_metadata(since).await
+    }
+
+    async fn list_submitted_claims_metadata(
+        &self,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<SubmittedClaimData>>> {
+        self.inner.list_submitted_claims_metadata(since).await
+    }
+
+    async fn list_settled_claims_for_receiver(
+        &self,
+        address: Option<String>,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+        if let Some(address) = &address {
+            check_address("address", address)?;
+        }
+        self.inner
+            .list_settled_claims_for_receiver(address, since)
+            .await
+    }
+
+    async fn list_submitted_claims_for_receiver(
+        &self,
+        address: String,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+        check_address("address", &address)?;
+        self.inner
+            .list_submitted_claims_for_receiver(address, since)
+            .await
+    }
+
+    async fn list_settled_claims_for_sender(
+        &self,
+        address: String,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SettledVerifiedClaim>>>> {
+        check_address("address", &address)?;
+        self.inner
+            .list_settled_claims_for_sender(address, since)
+            .await
+    }
+
+    async fn list_submitted_claims_for_sender(
+        &self,
+        address: Option<String>,
+        since: Timestamp,
+    ) -> RpcResult<Vec<Timestamped<Signed<SubmittedClaim>>>> {
+        if let Some(address) = &address {
+            check_address("address", address)?;
+        }
+        self.inner
+            .list_submitted_claims_for_sender(address, since)
+            .await
+    }
+
+    async fn get_claim_data_by_id(&self, claim_id: String) -> RpcResult<String> {
+        self.inner.get_claim_data_by_id(claim_id).await
+    }
+
+    async fn get_proof_by_id(&self, claim_id: String) -> RpcResult<String> {
+        self.inner.get_proof_by_id(claim_id).await
+    }
+
+    async fn get_submitted_claim_by_id(
+        &self,
+        claim_id: String,
+    ) -> RpcResult<Timestamped<Signed<SubmittedClaim>>> {
+        self.inner.get_submitted_claim_by_id(claim_id).await
+    }
+
+    async fn get_settled_claim_by_id(
+        &self,
+        claim_id: String,
+    ) -> RpcResult<Timestamped<Signed<SettledVerifiedClaim>>> {
+        self.inner.get_settled_claim_by_id(claim_id).await
+    }
+
+    async fn pay(&self, payment: Signed<PayMessage>) -> RpcResult<String> {
+        self.check(&payment)?;
+        self.inner.pay(payment).await
+    }
+
+    async fn get_account(&self, account_id: String) -> RpcResult<String> {
+        self.inner.get_account(account_id).await
+    }
+
+    async fn get_balance(&self, account_id: String) -> RpcResult<String> {
+        check_address("account_id", &account_id)?;
+        self.inner.get_balance(account_id).await
+    }
+
+    async fn get_asset_balance(&self, account_id: String, asset_id: String) -> RpcResult<String> {
+        check_address("account_id", &account_id)?;
+        validate_asset_id("asset_id", &asset_id)?;
+        self.inner.get_asset_balance(account_id, asset_id).await
+    }
+
+    async fn get_asset_balances(&self, account_id: String) -> RpcResult<HashMap<String, String>> {
+        check_address("account_id", &account_id)?;
+        self.inner.get_asset_balances(account_id).await
+    }
+
+    async fn create_asset(
+        &self,
+        asset_data: Signed<CreateAssetMessage>,
+    ) -> RpcResult<CreateAssetResult> {
+        self.check(&asset_data)?;
+        self.inner.create_asset(asset_data).await
+    }
+
+    async fn transfer_asset(
+        &self,
+        transfer_asset: Signed<TransferAssetMessage>,
+    ) -> RpcResult<String> {
+        self.check(&transfer_asset)?;
+        self.inner.transfer_asset(transfer_asset).await
+    }
+
+    async fn get_asset_by_id(&self, asset_id: String) -> RpcResult<Option<CreateAssetMessage>> {
+        validate_asset_id("asset_id", &asset_id)?;
+        self.inner.get_asset_by_id(asset_id).await
+    }
+
+    async fn get_state(&self, account_id: String) -> RpcResult<Option<String>> {
+        check_address("account_id", &account_id)?;
+        self.inner.get_state(account_id).await
+    }
+
+    async fn set_state(&self, state: Signed<SetStateMessage>) -> RpcResult<String> {
+        self.check(&state)?;
+        self.inner.set_state(state).await
+    }
+
+    async fn get_nonce(&self, account_id: String) -> RpcResult<u64> {
+        check_address("account_id", &account_id)?;
+        self.inner.get_nonce(account_id).await
+    }
+
+    async fn get_health(&self) -> RpcResult<String> {
+        self.inner.get_health().await
+    }
+
+    async fn get_network(&self) -> RpcResult<Option<String>> {
+        match &self.network {
+            Some(network) => Ok(Some(network.clone())),
+            None => self.inner.get_network().await,
+        }
+    }
+
+    async fn subscribe_to_settled_claims_metadata(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        self.inner
+            .subscribe_to_settled_claims_metadata(pending)
+            .await
+    }
+
+    async fn subscribe_to_submitted_claims_metadata(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        self.inner
+            .subscribe_to_submitted_claims_metadata(pending)
+            .await
+    }
+
+    async fn subscribe_to_settled_claims_for_receiver(
+        &self,
+        pending: PendingSubscriptionSink,
+        address: Option<String>,
+    ) -> SubscriptionResult {
+        if let Some(Err(error)) = address
+            .as_deref()
+            .map(|address| validate_address("address", address))
+        {
+            pending.reject(error).await;
+            return Ok(());
+        }
+        self.inner
+            .subscribe_to_settled_claims_for_receiver(pending, address)
+            .await
+    }
+
+    async fn subscribe_to_submitted_claims_for_receiver(
+        &self,
+        pending: PendingSubscriptionSink,
+        address: String,
+    ) -> SubscriptionResult {
+        if let Err(error) = validate_address("address", &address) {
+            pending.reject(error).await;
+            return Ok(());
+        }
+        self.inner
+            .subscribe_to_submitted_claims_for_receiver(pending, address)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::network::DEVNET;
+
+    fn claim(signer: &PrivateKeySigner, quorum: u16) -> SubmittedClaim {
+        SubmittedClaim {
+            claim: "claim".to_string(),
+            claim_type: "test".to_string(),
+            proof: "proof".to_string(),
+            nonce: "0".to_string(),
+            to: vec![Address::repeat_byte(0x01).to_string()],
+            quorum,
+            from: signer.address().to_string(),
+            expires: Timestamp::from_seconds(1_750_000_000),
+            fee: "0x1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_submit_claim() {
+        let signer = PrivateKeySigner::random();
+        let valid = claim(&signer, 1).into_signed(&signer).unwrap();
+        assert_eq!(valid.validate(), Ok(()));
+        assert!(matches!(
+            valid.validate_for(Some(DEVNET.domain())),
+            Err(ValidationError::InvalidSignature(_))
+        ));
+
+        let error = claim(&signer, 2)
+            .into_signed(&signer)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ValidationError::QuorumTooLarge {
+                quorum: 2,
+                verifiers: 1
+            }
+        );
+        assert_eq!(ErrorObjectOwned::from(error).code(), QUORUM_TOO_LARGE_CODE);
+
+        let mut decimal_fee = claim(&signer, 1);
+        decimal_fee.fee = "1".to_string();
+        let error = decimal_fee.into_signed(&signer).unwrap().validate();
+        assert_eq!(error, Err(ValidationError::InvalidField("fee")));
+
+        let forged = claim(&signer, 1)
+            .into_signed(&PrivateKeySigner::random())
+            .unwrap();
+        let error = forged.validate().unwrap_err();
+        assert_eq!(error.code(), INVALID_SIGNATURE_CODE);
+    }
+
+    #[test]
+    fn test_validate_create_asset() {
+        let signer = PrivateKeySigner::random();
+        let message = |decimals| CreateAssetMessage {
+            account_id: signer.address().to_string(),
+            nonce: "1".to_string(),
+            ticker_symbol: "TST".to_string(),
+            decimals,
+            total_supply: "0x64".to_string(),
+        };
+        let valid = message(VSL_DECIMALS).into_signed(&signer).unwrap();
+        assert_eq!(valid.validate(), Ok(()));
+        let invalid = message(VSL_DECIMALS + 1).into_signed(&signer).unwrap();
+        assert_eq!(
+            invalid.validate(),
+            Err(ValidationError::TooManyDecimals(VSL_DECIMALS + 1))
+        );
+    }
+}